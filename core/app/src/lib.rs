@@ -90,6 +90,8 @@ pub trait Application: Sized {
   fn add_to_debug_menu(&mut self, ui: &mut Ui) {}
   /// Add elements to the menu bar via `ui`.
   fn add_to_menu(&mut self, ui: &mut Ui) {}
+  /// Show debug windows via `gui`, for example windows toggled from elements added in `add_to_debug_menu`.
+  fn show_debug_windows(&mut self, gui: &Gui) {}
 
   /// Simulate a single update `step` with `input`.
   fn simulate(&mut self, step: Step, input: &Self::Input) {}
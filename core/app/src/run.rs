@@ -347,6 +347,8 @@ impl<A: Application> Runner<A> {
 
     // Show timing debugging GUI if enabled.
     self.debug_gui.show_timing(gui.as_ref().unwrap(), &self.timing_stats);
+    // Let the application show its debugging GUI.
+    self.app.show_debug_windows(gui.as_ref().unwrap());
 
     // Get swapchain texture to draw into and present.
     let surface_texture = match self.gfx.surface.get_current_texture() {
@@ -0,0 +1,141 @@
+use ultraviolet::{Mat4, Vec3, Vec4};
+
+/// View frustum as 6 planes with normals pointing inwards. A plane `(n, d)` is stored as `Vec4::new(n.x, n.y, n.z, d)`,
+/// and a point `p` is on the inside of the plane if `n.dot(p) + d >= 0`.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Frustum {
+  planes: [Vec4; 6],
+}
+
+impl Frustum {
+  /// Extracts the frustum planes from `view_projection` (Gribb-Hartmann method), assuming a 0 to 1 depth range, which
+  /// also holds for reversed depth. Planes of infinite projections have a zero normal and always contain every point.
+  ///
+  /// Pass a model-view-projection matrix to get a frustum in the local space of that model.
+  pub fn from_view_projection_matrix(view_projection: &Mat4) -> Self {
+    let row = |i: usize| Vec4::new(view_projection.cols[0][i], view_projection.cols[1][i], view_projection.cols[2][i], view_projection.cols[3][i]);
+    let (row_0, row_1, row_2, row_3) = (row(0), row(1), row(2), row(3));
+    let planes = [
+      row_3 + row_0, // Left
+      row_3 - row_0, // Right
+      row_3 + row_1, // Bottom
+      row_3 - row_1, // Top
+      row_2, // Near (far with reversed depth)
+      row_3 - row_2, // Far (near with reversed depth)
+    ].map(|plane| {
+      let normal_length = plane.truncated().mag();
+      if normal_length > f32::EPSILON { plane / normal_length } else { plane }
+    });
+    Self { planes }
+  }
+
+  #[inline]
+  pub fn planes(&self) -> &[Vec4; 6] { &self.planes }
+
+  /// Returns `true` if `point` is inside this frustum.
+  #[inline]
+  pub fn contains_point(&self, point: Vec3) -> bool {
+    self.planes.iter().all(|plane| plane.truncated().dot(point) + plane.w >= 0.0)
+  }
+
+  /// Returns `true` if the axis-aligned bounding box from `min` to `max` intersects with or is inside this frustum.
+  /// Conservative: may return `true` for some boxes near frustum corners that are outside of the frustum.
+  #[inline]
+  pub fn intersects_aabb(&self, min: Vec3, max: Vec3) -> bool {
+    self.planes.iter().all(|plane| {
+      // Test the corner of the box that is furthest along the plane normal.
+      let normal = plane.truncated();
+      let corner = Vec3::new(
+        if normal.x >= 0.0 { max.x } else { min.x },
+        if normal.y >= 0.0 { max.y } else { min.y },
+        if normal.z >= 0.0 { max.z } else { min.z },
+      );
+      normal.dot(corner) + plane.w >= 0.0
+    })
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use std::f32::consts::FRAC_PI_2;
+
+  use ultraviolet::{Mat4, Vec3};
+
+  use crate::camera::frustum::Frustum;
+  use crate::camera::projection::{look_at_lh, perspective_infinite_reversed_lh_yup_wgpu_dx, perspective_lh_yup_wgpu_dx};
+
+  const NEAR: f32 = 1.0;
+  const FAR: f32 = 100.0;
+
+  /// Frustum of a camera at the origin looking along +Z, with a 90 degree field of view, so that at distance `z` the
+  /// frustum extends `z` to each side.
+  fn frustum(projection: Mat4) -> Frustum {
+    Frustum::from_view_projection_matrix(&projection)
+  }
+
+  fn finite() -> Frustum { frustum(perspective_lh_yup_wgpu_dx(FRAC_PI_2, 1.0, NEAR, FAR)) }
+
+  fn infinite_reversed() -> Frustum { frustum(perspective_infinite_reversed_lh_yup_wgpu_dx(FRAC_PI_2, 1.0, NEAR)) }
+
+  /// Box of `half_size` around `center`.
+  fn intersects(frustum: &Frustum, center: Vec3, half_size: f32) -> bool {
+    frustum.intersects_aabb(center - Vec3::broadcast(half_size), center + Vec3::broadcast(half_size))
+  }
+
+  #[test]
+  fn boxes_inside_and_outside_of_frustum() {
+    for (name, frustum) in [("finite", finite()), ("infinite reversed", infinite_reversed())] {
+      let cases = [
+        (Vec3::new(0.0, 0.0, 10.0), true, "in front"),
+        (Vec3::new(5.0, -5.0, 50.0), true, "in front, off center"),
+        (Vec3::new(0.0, 0.0, -10.0), false, "behind"),
+        (Vec3::new(0.0, 0.0, -0.5), false, "between the camera and the near plane"),
+        (Vec3::new(20.0, 0.0, 10.0), false, "right"),
+        (Vec3::new(-20.0, 0.0, 10.0), false, "left"),
+        (Vec3::new(0.0, 20.0, 10.0), false, "above"),
+        (Vec3::new(0.0, -20.0, 10.0), false, "below"),
+        (Vec3::new(10.0, 0.0, 10.0), true, "straddling the right plane"),
+        (Vec3::new(0.0, -10.0, 10.0), true, "straddling the bottom plane"),
+        (Vec3::new(0.0, 0.0, 1.5), true, "straddling the near plane"),
+      ];
+      for (center, expected, description) in cases {
+        assert_eq!(intersects(&frustum, center, 1.0), expected, "Box {} ({:?}) of {} frustum", description, center, name);
+      }
+    }
+  }
+
+  #[test]
+  fn far_plane_only_culls_with_finite_projection() {
+    assert!(intersects(&finite(), Vec3::new(0.0, 0.0, FAR), 1.0), "Box straddling the far plane is culled");
+    assert!(!intersects(&finite(), Vec3::new(0.0, 0.0, 2.0 * FAR), 1.0), "Box beyond the far plane is not culled");
+    assert!(intersects(&infinite_reversed(), Vec3::new(0.0, 0.0, 1000.0 * FAR), 1.0), "Distant box is culled without a far plane");
+    // The far plane of an infinite projection has a zero normal, and contains every point.
+    let far_plane = infinite_reversed().planes()[4];
+    assert_eq!(far_plane.truncated(), Vec3::zero());
+    assert!(far_plane.w > 0.0);
+  }
+
+  #[test]
+  fn points_inside_and_outside_of_frustum() {
+    for frustum in [finite(), infinite_reversed()] {
+      assert!(frustum.contains_point(Vec3::new(0.0, 0.0, 10.0)));
+      assert!(frustum.contains_point(Vec3::new(9.9, 9.9, 10.0)));
+      assert!(!frustum.contains_point(Vec3::new(10.1, 0.0, 10.0)));
+      assert!(!frustum.contains_point(Vec3::new(0.0, 0.0, 0.5)));
+      assert!(!frustum.contains_point(Vec3::new(0.0, 0.0, -10.0)));
+    }
+  }
+
+  #[test]
+  fn frustum_of_view_projection_is_in_world_space() {
+    // Camera at (0, 0, 100) looking back along -Z towards the origin.
+    let view = look_at_lh(Vec3::new(0.0, 0.0, 100.0), Vec3::zero(), Vec3::unit_y());
+    for projection in [perspective_lh_yup_wgpu_dx(FRAC_PI_2, 1.0, NEAR, FAR), perspective_infinite_reversed_lh_yup_wgpu_dx(FRAC_PI_2, 1.0, NEAR)] {
+      let frustum = Frustum::from_view_projection_matrix(&(projection * view));
+      assert!(intersects(&frustum, Vec3::new(0.0, 0.0, 50.0), 1.0), "Box in front of the camera is culled");
+      assert!(!intersects(&frustum, Vec3::new(0.0, 0.0, 150.0), 1.0), "Box behind the camera is not culled");
+      assert!(!intersects(&frustum, Vec3::new(60.0, 0.0, 50.0), 1.0), "Box beside the camera is not culled");
+    }
+  }
+}
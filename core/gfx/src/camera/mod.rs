@@ -4,9 +4,11 @@ use common::screen::PhysicalSize;
 use common::time::Offset;
 
 use crate::camera::controller::{CameraController, CameraControllerInput, CameraControllerSettings, CameraControllerState};
+use crate::camera::frustum::Frustum;
 use crate::camera::projection::{CameraProjection, CameraProjectionSettings};
 
 pub mod controller;
+pub mod frustum;
 pub mod projection;
 pub mod system;
 #[cfg(feature = "inspector_gui")]
@@ -98,6 +100,9 @@ impl Camera {
   /// Gets the inverse view-projection matrix.
   #[inline]
   pub fn inverse_view_projection_matrix(&self) -> &Mat4 { self.projection.inverse_view_projection_matrix() }
+  /// Gets the view frustum in world space.
+  #[inline]
+  pub fn frustum(&self) -> Frustum { Frustum::from_view_projection_matrix(self.projection.view_projection_matrix()) }
 
   /// Converts screen coordinates (in pixels, relative to the top-left of the screen) to view coordinates (in meters,
   /// relative to the center of the screen).
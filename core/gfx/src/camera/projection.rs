@@ -207,7 +207,7 @@ pub fn look_at_lh(
 /// Creates a left-handed perspective projection matrix with 0-1 depth range.
 #[allow(dead_code)]
 #[inline]
-fn perspective_lh_yup_wgpu_dx(
+pub(crate) fn perspective_lh_yup_wgpu_dx(
   vertical_fov: f32,
   aspect_ratio: f32,
   near: f32,
@@ -249,7 +249,7 @@ fn perspective_infinite_lh_yup_wgpu_dx(
 
 /// Creates an infinite left-handed perspective projection matrix with 1-0 depth range.
 #[inline]
-fn perspective_infinite_reversed_lh_yup_wgpu_dx(
+pub(crate) fn perspective_infinite_reversed_lh_yup_wgpu_dx(
   vertical_fov: f32,
   aspect_ratio: f32,
   near: f32,
@@ -150,16 +150,20 @@ impl DebugRenderer {
   }
 
   pub fn draw_cube_lines(&mut self, min: Vec3, size: f32, col: Vec4) {
+    self.draw_box_lines(min, min + Vec3::broadcast(size), col);
+  }
+
+  pub fn draw_box_lines(&mut self, min: Vec3, max: Vec3, col: Vec4) {
     self.draw_line_vertices_indexed(
       [
         RegularVertex::new(min, col),
-        RegularVertex::new(min + Vec3::unit_x() * size, col),
-        RegularVertex::new(min + Vec3::unit_y() * size, col),
-        RegularVertex::new(min + Vec3::new(size, size, 0.0), col),
-        RegularVertex::new(min + Vec3::unit_z() * size, col),
-        RegularVertex::new(min + Vec3::new(size, 0.0, size), col),
-        RegularVertex::new(min + Vec3::new(0.0, size, size), col),
-        RegularVertex::new(min + Vec3::one() * size, col),
+        RegularVertex::new(Vec3::new(max.x, min.y, min.z), col),
+        RegularVertex::new(Vec3::new(min.x, max.y, min.z), col),
+        RegularVertex::new(Vec3::new(max.x, max.y, min.z), col),
+        RegularVertex::new(Vec3::new(min.x, min.y, max.z), col),
+        RegularVertex::new(Vec3::new(max.x, min.y, max.z), col),
+        RegularVertex::new(Vec3::new(min.x, max.y, max.z), col),
+        RegularVertex::new(max, col),
       ],
       [
         0, 1, // X
@@ -1,6 +1,6 @@
 use std::ops::Deref;
 
-use wgpu::{BindGroupEntry, BindGroupLayoutEntry, Device, FilterMode, Sampler, SamplerDescriptor, ShaderStages};
+use wgpu::{AddressMode, BindGroupEntry, BindGroupLayoutEntry, Device, FilterMode, Sampler, SamplerDescriptor, ShaderStages};
 use crate::bind_group::CombinedBinding;
 
 use crate::bind_group::entry::BindGroupEntryBuilder;
@@ -24,6 +24,14 @@ impl<'a> SamplerBuilder<'a> {
     self
   }
 
+  #[inline]
+  pub fn address_mode(mut self, address_mode: AddressMode) -> Self {
+    self.descriptor.address_mode_u = address_mode;
+    self.descriptor.address_mode_v = address_mode;
+    self.descriptor.address_mode_w = address_mode;
+    self
+  }
+
   #[inline]
   pub fn mag_filter(mut self, mag_filter: FilterMode) -> Self {
     self.descriptor.mag_filter = mag_filter;
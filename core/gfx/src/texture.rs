@@ -197,11 +197,16 @@ impl<'a> GfxTexture {
 
   #[inline]
   pub fn write_texture_data(&self, queue: &Queue, data: &[u8], offset: BufferAddress, bytes_per_row: Option<u32>, rows_per_image: Option<u32>, size: Extent3d) {
+    self.write_texture_data_at(queue, data, Origin3d::ZERO, offset, bytes_per_row, rows_per_image, size);
+  }
+
+  #[inline]
+  pub fn write_texture_data_at(&self, queue: &Queue, data: &[u8], origin: Origin3d, offset: BufferAddress, bytes_per_row: Option<u32>, rows_per_image: Option<u32>, size: Extent3d) {
     queue.write_texture(
       ImageCopyTexture {
         texture: &self.texture,
         mip_level: 0,
-        origin: Origin3d::ZERO,
+        origin,
         aspect: TextureAspect::All,
       },
       data,
@@ -1,6 +1,6 @@
 use image::{DynamicImage, GenericImageView};
 use thiserror::Error;
-use wgpu::{Device, Extent3d, Queue, ShaderStages};
+use wgpu::{AddressMode, Device, Extent3d, FilterMode, Origin3d, Queue, ShaderStages};
 
 use common::idx_assigner;
 use common::idx_assigner::IdxAssigner;
@@ -71,10 +71,14 @@ impl ArrayTextureDefBuilder {
     for (idx, data) in self.data.into_iter().enumerate() {
       let data = data.into_rgba8();
       let (width, height) = data.dimensions();
-      texture.write_texture_data(queue, data.as_raw(), 0, Some(width * 4), None, Extent3d { width, height, depth_or_array_layers: idx as u32 });
+      let origin = Origin3d { x: 0, y: 0, z: idx as u32 };
+      texture.write_texture_data_at(queue, data.as_raw(), origin, 0, Some(width * 4), None, Extent3d { width, height, depth_or_array_layers: 1 });
     }
     let sampler = SamplerBuilder::new()
       .label(sampler_label)
+      .address_mode(AddressMode::Repeat)
+      .mag_filter(FilterMode::Linear)
+      .min_filter(FilterMode::Linear)
       .build(device);
     let sampler_binding = sampler.binding(1, ShaderStages::FRAGMENT);
     let bind_group = CombinedBindGroupBuilder::new()
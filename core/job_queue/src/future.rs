@@ -0,0 +1,52 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use flume::r#async::RecvFut;
+
+use crate::JobError;
+
+// Job outcome
+
+/// Outcome of a job added with [`JobQueue::try_add_job_async`](crate::JobQueue::try_add_job_async).
+#[derive(Clone, Debug)]
+pub enum JobOutcome<JK, O> {
+  Completed(O),
+  Failed(JobError<JK>),
+  /// Job was removed before it completed or failed.
+  Removed,
+  /// Job queue was stopped before the job completed or failed.
+  QueueStopped,
+}
+
+
+// Job future
+
+/// Future that resolves with the [outcome](JobOutcome) of a job added with
+/// [`JobQueue::try_add_job_async`](crate::JobQueue::try_add_job_async). Can be awaited on any executor, or blocked on
+/// with an executor such as `pollster`.
+///
+/// Dropping this future does not remove the job; use
+/// [`JobQueue::try_remove_job_and_orphaned_dependencies`](crate::JobQueue::try_remove_job_and_orphaned_dependencies)
+/// for that.
+#[must_use = "futures do nothing unless polled"]
+pub struct JobFuture<JK: 'static, O: 'static> {
+  receiver: RecvFut<'static, JobOutcome<JK, O>>,
+}
+
+impl<JK, O> JobFuture<JK, O> {
+  #[inline]
+  pub(crate) fn new(receiver: flume::Receiver<JobOutcome<JK, O>>) -> Self {
+    Self { receiver: receiver.into_recv_async() }
+  }
+}
+
+impl<JK, O> Future for JobFuture<JK, O> {
+  type Output = JobOutcome<JK, O>;
+
+  #[inline]
+  fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+    // The sender is dropped without sending an outcome when the manager stops.
+    Pin::new(&mut self.receiver).poll(cx).map(|outcome| outcome.unwrap_or(JobOutcome::QueueStopped))
+  }
+}
@@ -0,0 +1,128 @@
+use std::fmt::{Debug, Write};
+
+// Job graph snapshot
+
+/// Snapshot of the job graph, sent as [`JobQueueMessage::JobGraph`](crate::JobQueueMessage::JobGraph) in response to
+/// [`JobQueue::request_job_graph`](crate::JobQueue::request_job_graph).
+#[derive(Clone, Debug)]
+pub struct JobGraphSnapshot<JK, DK> {
+  /// Jobs in the graph with their status.
+  pub nodes: Vec<(JK, JobGraphNodeStatus)>,
+  /// Dependencies in the graph as (depender job key, dependee job key, dependency key).
+  pub edges: Vec<(JK, JK, DK)>,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum JobGraphNodeStatus {
+  /// Job is waiting for its dependencies to complete.
+  Pending,
+  /// Job has all its dependencies completed, and is waiting to be sent to a worker.
+  Ready,
+  Running,
+  Completed,
+  Failed,
+}
+
+impl JobGraphNodeStatus {
+  #[inline]
+  fn dot_fill_color(&self) -> &'static str {
+    match self {
+      JobGraphNodeStatus::Pending => "lightgray",
+      JobGraphNodeStatus::Ready => "lightyellow",
+      JobGraphNodeStatus::Running => "lightblue",
+      JobGraphNodeStatus::Completed => "palegreen",
+      JobGraphNodeStatus::Failed => "salmon",
+    }
+  }
+}
+
+impl<JK: Debug, DK: Debug> JobGraphSnapshot<JK, DK> {
+  /// Renders this snapshot as a Graphviz DOT digraph. Nodes are labelled with the `Debug` representation of their job
+  /// key and status, and filled with a color based on their status. Edges point from depender to dependee and are
+  /// labelled with the `Debug` representation of their dependency key.
+  pub fn to_dot(&self) -> String {
+    let mut dot = String::new();
+    self.write_dot(&mut dot).unwrap(); // Unwrap OK: writing to a `String` does not fail.
+    dot
+  }
+
+  /// Writes this snapshot as a Graphviz DOT digraph into `writer`. See [`to_dot`](Self::to_dot).
+  pub fn write_dot(&self, writer: &mut impl Write) -> std::fmt::Result {
+    writeln!(writer, "digraph job_graph {{")?;
+    writeln!(writer, "  node [shape=box, style=filled];")?;
+    for (job_key, status) in &self.nodes {
+      let job_key = escape(&format!("{:?}", job_key));
+      writeln!(writer, "  \"{}\" [label=\"{}\\n{:?}\", fillcolor={}];", job_key, job_key, status, status.dot_fill_color())?;
+    }
+    for (depender_job_key, dependee_job_key, dependency_key) in &self.edges {
+      let depender_job_key = escape(&format!("{:?}", depender_job_key));
+      let dependee_job_key = escape(&format!("{:?}", dependee_job_key));
+      let dependency_key = escape(&format!("{:?}", dependency_key));
+      writeln!(writer, "  \"{}\" -> \"{}\" [label=\"{}\"];", depender_job_key, dependee_job_key, dependency_key)?;
+    }
+    writeln!(writer, "}}")
+  }
+}
+
+#[inline]
+fn escape(string: &str) -> String {
+  string.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+
+#[cfg(test)]
+mod tests {
+  use crate::{CancellationToken, Job, JobQueue, JobQueueMessage};
+
+  /// Job with a string key, whose `Debug` representation contains quotes.
+  #[derive(Clone)]
+  struct NamedJob {
+    key: &'static str,
+    dependencies: Vec<(&'static str, NamedJob)>,
+  }
+
+  impl NamedJob {
+    fn new(key: &'static str, dependencies: Vec<(&'static str, NamedJob)>) -> Self { Self { key, dependencies } }
+  }
+
+  impl Job<&'static str, &'static str, ()> for NamedJob {
+    fn key(&self) -> &&'static str { &self.key }
+
+    type DependencyIterator = std::vec::IntoIter<(&'static str, NamedJob)>;
+    fn into(self) -> ((), Self::DependencyIterator) { ((), self.dependencies.into_iter()) }
+  }
+
+  #[test]
+  fn job_graph_is_written_as_dot() {
+    let handler = |key: &'static str, _: (), _: &[(&'static str, ())], _: &CancellationToken| {
+      if key == "fails" { panic!("Job {} panicked", key); }
+    };
+    let mut job_queue: JobQueue<&'static str, &'static str, (), NamedJob, ()> = JobQueue::new_inline(16, handler);
+    let leaf = NamedJob::new("leaf", Vec::new());
+    // Completes `leaf` and fails `fails` and its depender.
+    job_queue.try_add_job(NamedJob::new("depends on \"fails\"", vec![("ok", leaf.clone()), ("not \"ok\"", NamedJob::new("fails", Vec::new()))]), 0).unwrap();
+    job_queue.run_until_idle();
+    // Adds a job that is pending on a new dependency, and requests the graph before running anything.
+    job_queue.try_add_job(NamedJob::new("pending", vec![("ok", leaf), ("new", NamedJob::new("new", Vec::new()))]), 0).unwrap();
+    job_queue.request_job_graph().unwrap();
+    job_queue.run_until_idle();
+    let snapshot = job_queue.get_message_receiver().try_iter()
+      .find_map(|message| if let JobQueueMessage::JobGraph(snapshot) = message { Some(snapshot) } else { None })
+      .expect("Job graph was not sent");
+    // Quotes in `Debug` representations are escaped, and nodes and edges are written in the order they were added.
+    let expected = r#"digraph job_graph {
+  node [shape=box, style=filled];
+  "\"depends on \\\"fails\\\"\"" [label="\"depends on \\\"fails\\\"\"\nFailed", fillcolor=salmon];
+  "\"leaf\"" [label="\"leaf\"\nCompleted", fillcolor=palegreen];
+  "\"fails\"" [label="\"fails\"\nFailed", fillcolor=salmon];
+  "\"pending\"" [label="\"pending\"\nPending", fillcolor=lightgray];
+  "\"new\"" [label="\"new\"\nRunning", fillcolor=lightblue];
+  "\"depends on \\\"fails\\\"\"" -> "\"leaf\"" [label="\"ok\""];
+  "\"depends on \\\"fails\\\"\"" -> "\"fails\"" [label="\"not \\\"ok\\\"\""];
+  "\"pending\"" -> "\"leaf\"" [label="\"ok\""];
+  "\"pending\"" -> "\"new\"" [label="\"new\""];
+}
+"#;
+    assert_eq!(snapshot.to_dot(), expected);
+  }
+}
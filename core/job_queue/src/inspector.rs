@@ -0,0 +1,118 @@
+use std::time::Duration;
+
+use egui::{Align2, CollapsingHeader, Grid, Ui};
+
+use gui::Gui;
+use gui::widget::UiWidgetsExt;
+
+use crate::metrics::{DurationHistogram, JobQueueMetrics};
+
+#[derive(Default, Copy, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub struct JobQueueInspector {
+  pub show_window: bool,
+  pub window_anchor: Option<Align2>,
+}
+
+impl JobQueueInspector {
+  #[profiling::function]
+  pub fn show_window(&mut self, gui: &Gui, metrics: Option<&JobQueueMetrics>) {
+    if !self.show_window { return; }
+    let mut window = gui.window("Job Queue");
+    if let Some(anchor) = self.window_anchor {
+      window = window.anchor(anchor, egui::Vec2::ZERO);
+    }
+    window
+      .open(&mut self.show_window)
+      .auto_sized()
+      .show(gui, |ui| {
+        ui.horizontal(|ui| {
+          ui.label("Anchor");
+          ui.select_align2(&mut self.window_anchor);
+        });
+        let Some(metrics) = metrics else {
+          ui.label("No job queue metrics available");
+          return;
+        };
+        CollapsingHeader::new("Jobs").default_open(true).show(ui, |ui| {
+          Grid::new("Grid")
+            .striped(true)
+            .spacing([10.0, 4.0])
+            .show(ui, |ui| {
+              ui.label("To add");
+              ui.label(format!("{}", metrics.jobs_to_add));
+              ui.end_row();
+              ui.label("Pending");
+              ui.label(format!("{}", metrics.pending_jobs));
+              ui.end_row();
+              ui.label("Ready");
+              ui.label(format!("{}", metrics.ready_jobs));
+              ui.end_row();
+              ui.label("Running");
+              ui.label(format!("{}", metrics.running_jobs));
+              ui.end_row();
+              ui.label("Completed");
+              ui.label(format!("{}", metrics.completed_jobs));
+              ui.end_row();
+              ui.label("Failed");
+              ui.label(format!("{}", metrics.failed_jobs));
+              ui.end_row();
+            });
+        });
+        CollapsingHeader::new("Job graph").default_open(true).show(ui, |ui| {
+          Grid::new("Grid")
+            .striped(true)
+            .spacing([10.0, 4.0])
+            .show(ui, |ui| {
+              ui.label("Jobs");
+              ui.label(format!("{}", metrics.job_graph_nodes));
+              ui.end_row();
+              ui.label("Dependencies");
+              ui.label(format!("{}", metrics.job_graph_edges));
+              ui.end_row();
+              ui.label("Dependency cache hit rate");
+              ui.label(format!("{:5.1}%", metrics.dependency_output_cache_hit_rate() * 100.0));
+              ui.end_row();
+            });
+        });
+        CollapsingHeader::new("Queue wait").default_open(true).show(ui, |ui| {
+          Self::draw_duration_histogram(ui, &metrics.queue_wait);
+        });
+        CollapsingHeader::new("Run time").default_open(true).show(ui, |ui| {
+          Self::draw_duration_histogram(ui, &metrics.run_time);
+        });
+      });
+  }
+
+  pub fn add_to_menu(&mut self, ui: &mut Ui) {
+    ui.checkbox(&mut self.show_window, "Job queue");
+  }
+
+
+  fn draw_duration_histogram(ui: &mut Ui, histogram: &DurationHistogram) {
+    let milliseconds = |duration: Duration| duration.as_secs_f64() * 1000.0;
+    Grid::new("Grid")
+      .striped(true)
+      .spacing([10.0, 4.0])
+      .show(ui, |ui| {
+        ui.label("Count");
+        ui.label(format!("{}", histogram.count()));
+        ui.end_row();
+        ui.label("Mean");
+        ui.label(format!("{:7.3}ms", milliseconds(histogram.mean())));
+        ui.end_row();
+        ui.label("50th percentile");
+        ui.label(format!("{:7.3}ms", milliseconds(histogram.quantile(0.5))));
+        ui.end_row();
+        ui.label("90th percentile");
+        ui.label(format!("{:7.3}ms", milliseconds(histogram.quantile(0.9))));
+        ui.end_row();
+        ui.label("99th percentile");
+        ui.label(format!("{:7.3}ms", milliseconds(histogram.quantile(0.99))));
+        ui.end_row();
+        ui.label("Max");
+        ui.label(format!("{:7.3}ms", milliseconds(histogram.max())));
+        ui.end_row();
+      });
+  }
+}
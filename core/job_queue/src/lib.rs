@@ -1,28 +1,55 @@
 use std::fmt::Debug;
 use std::hash::Hash;
 use std::marker::PhantomData;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::thread::{self, JoinHandle};
 
 use flume::{bounded, Receiver, Sender, unbounded};
 pub use flume::SendError;
+use thiserror::Error;
 
 use manager::ManagerThread;
 use worker::WorkerThread;
 
 use crate::manager::FromQueueMessage;
+pub use crate::future::{JobFuture, JobOutcome};
+pub use crate::graph::{JobGraphNodeStatus, JobGraphSnapshot};
+pub use crate::metrics::{DURATION_HISTOGRAM_BUCKET_COUNT, DurationHistogram, JobQueueMetrics};
 
 mod worker;
 mod manager;
+mod priority_queue;
+mod metrics;
+mod graph;
+mod future;
+mod output_cache;
+#[cfg(feature = "inspector_gui")]
+pub mod inspector;
 
 
 // Message from manager
 
-pub enum JobQueueMessage<JK, I, O> {
+pub enum JobQueueMessage<JK, DK, I, O> {
   JobCompleted(JK, O),
   PendingJobRemoved(JK, I),
   RunningJobRemoved(JK),
   CompletedJobRemoved(JK, O),
+  /// Job failed, either because its handler panicked, or because one of its (transitive) dependencies failed. Failed jobs
+  /// stay in the queue (and are not run again) until they are removed.
+  JobFailed(JK, JobError<JK>),
+  FailedJobRemoved(JK),
   QueueEmpty,
+  Metrics(Box<JobQueueMetrics>),
+  JobGraph(Box<JobGraphSnapshot<JK, DK>>),
+}
+
+#[derive(Error, Clone, Debug)]
+pub enum JobError<JK> {
+  #[error("Job handler panicked: {0}")]
+  Panicked(String),
+  #[error("Dependency {0:?} of the job failed")]
+  DependencyFailed(JK),
 }
 
 
@@ -31,8 +58,11 @@ pub enum JobQueueMessage<JK, I, O> {
 pub struct JobQueue<JK, DK, I, J, O> {
   manager_thread_handle: Option<JoinHandle<()>>,
   worker_thread_handles: Vec<JoinHandle<()>>,
-  to_manager: Sender<manager::FromQueue<JK, J>>,
-  from_manager: Receiver<JobQueueMessage<JK, I, O>>,
+  to_manager: Sender<manager::FromQueue<JK, I, J, O>>,
+  from_manager: Receiver<JobQueueMessage<JK, DK, I, O>>,
+  run_until_idle: Option<Box<dyn FnMut() + Send>>,
+  submitted_job_count: Arc<AtomicUsize>,
+  submission_capacity: Option<usize>,
 
   _dependency_key_phantom: PhantomData<DK>,
 }
@@ -51,12 +81,14 @@ impl<JK: JobKey, DK: DepKey, I: In, J: Job<JK, DK, I>, O: Out> JobQueue<JK, DK,
     let (manager_to_worker_sender, manager_to_worker_receiver) = unbounded();
     let (worker_to_manager_sender, worker_to_manager_receiver) = unbounded();
     let (manager_to_external_sender, manager_to_external_receiver) = unbounded();
+    let submitted_job_count = Arc::new(AtomicUsize::new(0));
 
     let manager_thread = ManagerThread::new(
       external_to_manager_receiver,
       manager_to_worker_sender,
       worker_to_manager_receiver,
       manager_to_external_sender,
+      submitted_job_count.clone(),
       target_running_job_count,
       dependency_output_cache_count,
     );
@@ -81,10 +113,66 @@ impl<JK: JobKey, DK: DepKey, I: In, J: Job<JK, DK, I>, O: Out> JobQueue<JK, DK,
       worker_thread_handles,
       to_manager: external_to_manager_sender,
       from_manager: manager_to_external_receiver,
-      _dependency_key_phantom: PhantomData::default(),
+      run_until_idle: None,
+      submitted_job_count,
+      submission_capacity: None,
+      _dependency_key_phantom: PhantomData,
     })
   }
 
+  /// Creates a job queue that does not spawn any threads, but instead runs jobs on the caller's thread when
+  /// [`run_until_idle`](Self::run_until_idle) is called. Jobs are run one at a time in a deterministic order, and
+  /// results are sent over the same message receiver as a threaded job queue.
+  pub fn new_inline(
+    dependency_output_cache_count: usize,
+    handler: impl Handler<JK, DK, I, O>
+  ) -> Self {
+    let (external_to_manager_sender, external_to_manager_receiver) = unbounded();
+    let (manager_to_worker_sender, manager_to_worker_receiver) = unbounded();
+    let (worker_to_manager_sender, worker_to_manager_receiver) = unbounded();
+    let (manager_to_external_sender, manager_to_external_receiver) = unbounded();
+    let submitted_job_count = Arc::new(AtomicUsize::new(0));
+
+    let mut manager = ManagerThread::new(
+      external_to_manager_receiver,
+      manager_to_worker_sender,
+      worker_to_manager_receiver,
+      manager_to_external_sender,
+      submitted_job_count.clone(),
+      1, // Run one job at a time, so that the manager picks each job to run based on the latest state.
+      dependency_output_cache_count,
+    );
+    let mut worker = WorkerThread::new(
+      manager_to_worker_receiver,
+      worker_to_manager_sender,
+      handler,
+    );
+    let mut job_key_cache = Vec::new();
+    let run_until_idle = move || {
+      loop {
+        // First handle all messages to the manager, then run a job, until there is nothing left to do.
+        if let Some(r#continue) = manager.try_receive(&mut job_key_cache) {
+          if !r#continue { break; }
+        } else if let Some(r#continue) = worker.try_run_job() {
+          if !r#continue { break; }
+        } else {
+          break;
+        }
+      }
+    };
+
+    Self {
+      manager_thread_handle: None,
+      worker_thread_handles: Vec::new(),
+      to_manager: external_to_manager_sender,
+      from_manager: manager_to_external_receiver,
+      run_until_idle: Some(Box::new(run_until_idle)),
+      submitted_job_count,
+      submission_capacity: None,
+      _dependency_key_phantom: PhantomData,
+    }
+  }
+
   fn new_dummy() -> Self {
     let (empty_sender, _) = bounded(0);
     let (_, empty_receiver) = bounded(0);
@@ -93,14 +181,116 @@ impl<JK: JobKey, DK: DepKey, I: In, J: Job<JK, DK, I>, O: Out> JobQueue<JK, DK,
       worker_thread_handles: Vec::new(),
       to_manager: empty_sender,
       from_manager: empty_receiver,
-      _dependency_key_phantom: PhantomData::default(),
+      run_until_idle: None,
+      submitted_job_count: Arc::new(AtomicUsize::new(0)),
+      submission_capacity: None,
+      _dependency_key_phantom: PhantomData,
     }
   }
 
+  /// Sets a soft limit of `submission_capacity` on the number of submitted jobs that have not been added to the job graph
+  /// yet. [`try_add_jobs`](Self::try_add_jobs) only accepts jobs up to this capacity, giving callers a backpressure
+  /// signal. Jobs added with [`try_add_job`](Self::try_add_job) or [`try_add_job_async`](Self::try_add_job_async) are
+  /// always accepted, but count towards the capacity.
+  ///
+  /// This is a soft limit: the channel to the manager thread is unbounded, and the capacity is checked against a counter
+  /// without synchronizing with other submitters, so concurrent calls to `try_add_jobs` may exceed it.
+  #[inline]
+  pub fn with_submission_capacity(mut self, submission_capacity: usize) -> Self {
+    self.submission_capacity = Some(submission_capacity);
+    self
+  }
+
+  /// Keeps the outputs of removed completed jobs in a least recently used cache of at most `byte_budget` bytes, where
+  /// `output_size` returns the size in bytes of an output. When a job with a cached output is added again, it completes
+  /// immediately with the cached output instead of running again, and without adding its dependencies. Therefore,
+  /// invalidating one of those dependencies later does not run that job again.
+  ///
+  /// Cached outputs are keyed by job key only. Outputs that also depend on something else, such as job input that is not
+  /// part of the job key, must not be cached: `output_size` returns `None` for those.
+  ///
+  /// Invalidating a job clears the cache, as cached outputs may (transitively) depend on the output of that job.
+  #[inline]
+  pub fn with_output_cache(self, byte_budget: usize, output_size: impl Fn(&O) -> Option<usize> + Send + 'static) -> Self {
+    // Ignore send errors: they are reported when sending later messages.
+    let _ = self.to_manager.send(FromQueueMessage::SetOutputCache(byte_budget, Box::new(output_size)));
+    self
+  }
 
+  /// Completes added jobs for which `load` returns an output immediately with that output, instead of scheduling them,
+  /// and without adding their dependencies. For example, to read outputs from a persistent cache. `load` is run on the
+  /// manager thread for each added job that is not in the graph or output cache yet, so it should return quickly.
+  ///
+  /// Jobs that are invalidated run again instead of being loaded.
   #[inline]
-  pub fn try_add_job(&self, job: J) -> Result<(), SendError<()>> {
-    self.to_manager.send(FromQueueMessage::TryAddJob(job)).map_err(|_| SendError(()))
+  pub fn with_output_loader(self, load: impl Fn(&J) -> Option<O> + Send + 'static) -> Self {
+    // Ignore send errors: they are reported when sending later messages.
+    let _ = self.to_manager.send(FromQueueMessage::SetOutputLoader(Box::new(load)));
+    self
+  }
+
+  /// Returns `true` if this job queue was created with [`new_inline`](Self::new_inline).
+  #[inline]
+  pub fn is_inline(&self) -> bool { self.run_until_idle.is_some() }
+
+  /// Runs jobs on the caller's thread until all added jobs are completed, failed, or removed. Does nothing if this job
+  /// queue was not created with [`new_inline`](Self::new_inline).
+  #[inline]
+  pub fn run_until_idle(&mut self) {
+    if let Some(run_until_idle) = &mut self.run_until_idle {
+      run_until_idle();
+    }
+  }
+
+
+  /// Tries to add `job` with `priority`. Jobs with lower priority values are run first. Dependencies of jobs are run
+  /// with the lowest priority value of the jobs that depend on them.
+  #[inline]
+  pub fn try_add_job(&self, job: J, priority: Priority) -> Result<(), SendError<()>> {
+    self.submitted_job_count.fetch_add(1, Ordering::Relaxed);
+    self.to_manager.send(FromQueueMessage::TryAddJob(job, priority)).map_err(|_| SendError(()))
+  }
+
+  /// Tries to add `jobs` with their priorities in a single batch, without blocking. If this job queue has a
+  /// [soft submission capacity](Self::with_submission_capacity), jobs are only taken from `jobs` until the capacity is
+  /// reached; the remaining jobs are not taken from the iterator and can be submitted again later. Returns the number of
+  /// accepted jobs.
+  #[inline]
+  pub fn try_add_jobs(&self, jobs: impl IntoIterator<Item=(J, Priority)>) -> Result<usize, SendError<()>> {
+    let available = self.submission_capacity
+      .map_or(usize::MAX, |capacity| capacity.saturating_sub(self.submitted_job_count.load(Ordering::Relaxed)));
+    let jobs: Vec<_> = jobs.into_iter().take(available).collect();
+    let accepted = jobs.len();
+    if accepted == 0 { return Ok(0); }
+    self.submitted_job_count.fetch_add(accepted, Ordering::Relaxed);
+    self.to_manager.send(FromQueueMessage::TryAddJobs(jobs)).map_err(|_| SendError(()))?;
+    Ok(accepted)
+  }
+
+  /// Gets the number of submitted jobs that have not been added to the job graph yet.
+  #[inline]
+  pub fn submitted_job_count(&self) -> usize { self.submitted_job_count.load(Ordering::Relaxed) }
+
+  /// Tries to add `job` with `priority` like [`try_add_job`](Self::try_add_job), returning a future that resolves with
+  /// the outcome of the job: its output when it completes, its error when it fails, or whether it was removed. The job
+  /// is also reported through the message receiver as usual. If the job was already added, the future resolves with
+  /// the outcome of the existing job.
+  ///
+  /// An inline job queue only makes progress when [`run_until_idle`](Self::run_until_idle) is called, so its futures only
+  /// resolve after calling it.
+  #[inline]
+  pub fn try_add_job_async(&self, job: J, priority: Priority) -> Result<JobFuture<JK, O>, SendError<()>> {
+    let (outcome_sender, outcome_receiver) = bounded(1);
+    self.submitted_job_count.fetch_add(1, Ordering::Relaxed);
+    self.to_manager.send(FromQueueMessage::TryAddJobAsync(job, priority, outcome_sender)).map_err(|_| SendError(()))?;
+    Ok(JobFuture::new(outcome_receiver))
+  }
+
+  /// Updates the priority of the job with `job_key` to `priority`, if it has not started running yet. Does nothing if the
+  /// job does not exist.
+  #[inline]
+  pub fn update_priority(&self, job_key: JK, priority: Priority) -> Result<(), SendError<()>> {
+    self.to_manager.send(FromQueueMessage::UpdatePriority(job_key, priority)).map_err(|_| SendError(()))
   }
 
   #[inline]
@@ -108,9 +298,46 @@ impl<JK: JobKey, DK: DepKey, I: In, J: Job<JK, DK, I>, O: Out> JobQueue<JK, DK,
     self.to_manager.send(FromQueueMessage::TryRemoveJobAndOrphanedDependencies(job_key)).map_err(|_| SendError(()))
   }
 
+  /// Tries to remove the jobs with `job_keys` along with their orphaned dependencies in a single batch.
+  #[inline]
+  pub fn try_remove_jobs_and_orphaned_dependencies(&self, job_keys: impl IntoIterator<Item=JK>) -> Result<(), SendError<()>> {
+    let job_keys: Vec<_> = job_keys.into_iter().collect();
+    if job_keys.is_empty() { return Ok(()); }
+    self.to_manager.send(FromQueueMessage::TryRemoveJobsAndOrphanedDependencies(job_keys)).map_err(|_| SendError(()))
+  }
+
+  /// Invalidates the output of the job with `job_key`: discards its output (if any) and runs it again, and then runs
+  /// every job that (transitively) depends on it again with the new output, reusing the existing job graph. Running jobs
+  /// that are invalidated are cancelled. Failed jobs that are invalidated are retried. Does nothing if the job does not
+  /// exist or has not started running yet.
+  ///
+  /// Inputs are only kept until a job starts running, so `create_input` is called on the manager thread to create the
+  /// input of each job that is run again. Jobs for which it returns `None` are not run again and keep their output, and
+  /// their dependers are not run again through them.
+  ///
+  /// Jobs that are run again send a new [`JobQueueMessage::JobCompleted`] (or [`JobQueueMessage::JobFailed`]) message.
+  #[inline]
+  pub fn invalidate(&self, job_key: JK, create_input: impl FnMut(&JK) -> Option<I> + Send + 'static) -> Result<(), SendError<()>> {
+    self.to_manager.send(FromQueueMessage::Invalidate(job_key, Box::new(create_input))).map_err(|_| SendError(()))
+  }
+
+
+  /// Requests a snapshot of the metrics of this job queue, which is sent as a [`JobQueueMessage::Metrics`] message.
+  #[inline]
+  pub fn request_metrics(&self) -> Result<(), SendError<()>> {
+    self.to_manager.send(FromQueueMessage::RequestMetrics).map_err(|_| SendError(()))
+  }
+
+
+  /// Requests a snapshot of the job graph, which is sent as a [`JobQueueMessage::JobGraph`] message.
+  #[inline]
+  pub fn request_job_graph(&self) -> Result<(), SendError<()>> {
+    self.to_manager.send(FromQueueMessage::RequestJobGraph).map_err(|_| SendError(()))
+  }
+
 
   #[inline]
-  pub fn get_message_receiver(&self) -> &Receiver<JobQueueMessage<JK, I, O>> { &self.from_manager }
+  pub fn get_message_receiver(&self) -> &Receiver<JobQueueMessage<JK, DK, I, O>> { &self.from_manager }
 
 
   pub fn stop_and_join(mut self) -> thread::Result<()> {
@@ -154,13 +381,40 @@ pub trait Job<JK: JobKey, DK: DepKey, I: In>: Send + 'static {
 }
 
 
+// Priority
+
+/// Priority of a job. Jobs with lower priority values are run first, and jobs with equal priority values are run in the
+/// order they were added.
+pub type Priority = u32;
+
+
+// Cancellation token
+
+/// Token passed to the handler of a running job, which is cancelled when that job is removed while it is running.
+/// Handlers can check it to stop early; the output of a cancelled job is discarded.
+#[derive(Clone, Default, Debug)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+  #[inline]
+  pub fn is_cancelled(&self) -> bool { self.0.load(Ordering::Relaxed) }
+
+  #[inline]
+  pub(crate) fn cancel(&self) { self.0.store(true, Ordering::Relaxed); }
+
+  /// Returns `true` if `self` and `other` are clones of the same token.
+  #[inline]
+  pub(crate) fn is_same(&self, other: &Self) -> bool { Arc::ptr_eq(&self.0, &other.0) }
+}
+
+
 // Handler
 
 // pub type DependencyOutputs<'a, DK, O> = ;
 
-pub trait Handler<JK, DK, I, O>: FnMut(JK, I, &[(DK, O)]) -> O + Clone + Send + 'static {}
+pub trait Handler<JK, DK, I, O>: FnMut(JK, I, &[(DK, O)], &CancellationToken) -> O + Clone + Send + 'static {}
 
-impl<T, JK, DK, I, O> Handler<JK, DK, I, O> for T where T: FnMut(JK, I, &[(DK, O)]) -> O + Clone + Send + 'static {}
+impl<T, JK, DK, I, O> Handler<JK, DK, I, O> for T where T: FnMut(JK, I, &[(DK, O)], &CancellationToken) -> O + Clone + Send + 'static {}
 
 
 // Trait aliases
@@ -184,3 +438,323 @@ pub trait Out: Send + Clone + 'static {}
 
 impl<T> Out for T where T: Send + Clone + 'static {}
 
+
+
+#[cfg(test)]
+mod tests {
+  use std::future::Future;
+  use std::pin::Pin;
+  use std::sync::{Arc, Mutex};
+  use std::task::{Context, Poll, Waker};
+  use std::time::{Duration, Instant};
+
+  use crate::{CancellationToken, Job, JobError, JobFuture, JobOutcome, JobQueue, JobQueueMessage};
+
+  /// Job whose output is its key plus the sum of the outputs of its dependencies.
+  #[derive(Clone)]
+  struct TestJob {
+    key: u32,
+    dependencies: Vec<TestJob>,
+  }
+
+  impl TestJob {
+    fn new(key: u32) -> Self { Self { key, dependencies: Vec::new() } }
+    fn with_dependencies(key: u32, dependencies: Vec<TestJob>) -> Self { Self { key, dependencies } }
+  }
+
+  impl Job<u32, (), u32> for TestJob {
+    fn key(&self) -> &u32 { &self.key }
+
+    type DependencyIterator = std::iter::Map<std::vec::IntoIter<TestJob>, fn(TestJob) -> ((), TestJob)>;
+    fn into(self) -> (u32, Self::DependencyIterator) {
+      (self.key, self.dependencies.into_iter().map(|job| ((), job)))
+    }
+  }
+
+  type TestJobQueue = JobQueue<u32, (), u32, TestJob, u32>;
+
+  /// Key of jobs whose handler panics.
+  const PANICKING_KEY: u32 = 666;
+
+  /// Creates an inline job queue that records the keys of run jobs into the returned vector.
+  fn create_inline_job_queue() -> (TestJobQueue, Arc<Mutex<Vec<u32>>>) {
+    let run_keys = Arc::new(Mutex::new(Vec::new()));
+    let handler = {
+      let run_keys = run_keys.clone();
+      move |key: u32, input: u32, dependency_outputs: &[((), u32)], _: &CancellationToken| {
+        run_keys.lock().unwrap().push(key);
+        if key == PANICKING_KEY { panic!("Job {} panicked", key); }
+        input + dependency_outputs.iter().map(|(_, output)| output).sum::<u32>()
+      }
+    };
+    (JobQueue::new_inline(16, handler), run_keys)
+  }
+
+  fn describe(message: JobQueueMessage<u32, (), u32, u32>) -> String {
+    use JobQueueMessage::*;
+    match message {
+      JobCompleted(key, output) => format!("JobCompleted({}, {})", key, output),
+      PendingJobRemoved(key, _) => format!("PendingJobRemoved({})", key),
+      RunningJobRemoved(key) => format!("RunningJobRemoved({})", key),
+      CompletedJobRemoved(key, _) => format!("CompletedJobRemoved({})", key),
+      JobFailed(key, JobError::Panicked(_)) => format!("JobFailed({}, Panicked)", key),
+      JobFailed(key, JobError::DependencyFailed(dependency_key)) => format!("JobFailed({}, DependencyFailed({}))", key, dependency_key),
+      FailedJobRemoved(key) => format!("FailedJobRemoved({})", key),
+      QueueEmpty => "QueueEmpty".to_string(),
+      Metrics(_) => "Metrics".to_string(),
+      JobGraph(_) => "JobGraph".to_string(),
+    }
+  }
+
+  fn take_messages(job_queue: &TestJobQueue) -> Vec<String> {
+    job_queue.get_message_receiver().try_iter().map(describe).collect()
+  }
+
+  /// Polls `future` once without an executor, returning its outcome if it resolved.
+  fn poll(future: &mut JobFuture<u32, u32>) -> Option<JobOutcome<u32, u32>> {
+    match Pin::new(future).poll(&mut Context::from_waker(Waker::noop())) {
+      Poll::Ready(outcome) => Some(outcome),
+      Poll::Pending => None,
+    }
+  }
+
+  #[test]
+  fn inline_jobs_run_in_priority_order() {
+    let (mut job_queue, run_keys) = create_inline_job_queue();
+    job_queue.try_add_jobs([(TestJob::new(1), 2), (TestJob::new(2), 0), (TestJob::new(3), 1), (TestJob::new(4), 0)]).unwrap();
+    job_queue.run_until_idle();
+    // Lower priority values first, and jobs with equal priority values in the order they were added.
+    assert_eq!(*run_keys.lock().unwrap(), vec![2, 4, 3, 1]);
+    assert_eq!(take_messages(&job_queue), vec!["JobCompleted(2, 2)", "JobCompleted(4, 4)", "JobCompleted(3, 3)", "JobCompleted(1, 1)", "QueueEmpty"]);
+  }
+
+  #[test]
+  fn dependencies_run_before_dependers() {
+    let (mut job_queue, run_keys) = create_inline_job_queue();
+    let shared = TestJob::new(1);
+    job_queue.try_add_job(TestJob::with_dependencies(10, vec![shared.clone(), TestJob::new(2)]), 0).unwrap();
+    job_queue.try_add_job(TestJob::with_dependencies(20, vec![shared]), 0).unwrap();
+    job_queue.run_until_idle();
+    let run_keys = run_keys.lock().unwrap();
+    // The shared dependency is only run once, before both of its dependers.
+    assert_eq!(run_keys.iter().filter(|key| **key == 1).count(), 1);
+    let position = |key: u32| run_keys.iter().position(|k| *k == key).unwrap();
+    assert!(position(1) < position(10) && position(2) < position(10) && position(1) < position(20));
+    let messages = take_messages(&job_queue);
+    assert!(messages.contains(&"JobCompleted(10, 13)".to_string()), "{:?}", messages);
+    assert!(messages.contains(&"JobCompleted(20, 21)".to_string()), "{:?}", messages);
+    assert_eq!(messages.last().unwrap(), "QueueEmpty");
+  }
+
+  #[test]
+  fn failed_jobs_fail_their_dependers() {
+    let (mut job_queue, run_keys) = create_inline_job_queue();
+    job_queue.try_add_job(TestJob::with_dependencies(10, vec![TestJob::new(PANICKING_KEY)]), 0).unwrap();
+    job_queue.run_until_idle();
+    assert_eq!(*run_keys.lock().unwrap(), vec![PANICKING_KEY]);
+    assert_eq!(take_messages(&job_queue), vec!["JobFailed(666, Panicked)", "JobFailed(10, DependencyFailed(666))", "QueueEmpty"]);
+
+    // Jobs added later that depend on the failed job fail immediately, without running.
+    job_queue.try_add_job(TestJob::with_dependencies(20, vec![TestJob::new(PANICKING_KEY)]), 0).unwrap();
+    job_queue.run_until_idle();
+    assert_eq!(*run_keys.lock().unwrap(), vec![PANICKING_KEY]);
+    assert_eq!(take_messages(&job_queue), vec!["JobFailed(20, DependencyFailed(666))", "QueueEmpty"]);
+
+    // The failed job is only removed once it is orphaned by removing all of its dependers.
+    job_queue.try_remove_jobs_and_orphaned_dependencies([10, 20]).unwrap();
+    job_queue.run_until_idle();
+    assert_eq!(take_messages(&job_queue), vec!["FailedJobRemoved(10)", "FailedJobRemoved(20)", "FailedJobRemoved(666)"]);
+  }
+
+  #[test]
+  fn removing_jobs_removes_orphaned_dependencies() {
+    let (mut job_queue, run_keys) = create_inline_job_queue();
+    job_queue.try_add_job(TestJob::with_dependencies(10, vec![TestJob::new(1), TestJob::new(2)]), 0).unwrap();
+    job_queue.try_add_job(TestJob::with_dependencies(20, vec![TestJob::new(2)]), 0).unwrap();
+    job_queue.try_remove_job_and_orphaned_dependencies(10).unwrap();
+    job_queue.run_until_idle();
+    // Job 1 was already running when job 10 was removed. Job 20 is only added once job 1 stops running, so job 2 is
+    // removed and then added again as a dependency of job 20.
+    assert_eq!(*run_keys.lock().unwrap(), vec![1, 2, 20]);
+    assert_eq!(take_messages(&job_queue), vec!["PendingJobRemoved(10)", "RunningJobRemoved(1)", "PendingJobRemoved(2)", "JobCompleted(2, 2)", "JobCompleted(20, 22)", "QueueEmpty"]);
+  }
+
+  #[test]
+  fn removing_jobs_to_add_empties_the_queue() {
+    let (mut job_queue, run_keys) = create_inline_job_queue();
+    job_queue.try_add_jobs([(TestJob::new(1), 0), (TestJob::new(2), 1)]).unwrap();
+    job_queue.try_remove_job_and_orphaned_dependencies(2).unwrap();
+    job_queue.run_until_idle();
+    assert_eq!(*run_keys.lock().unwrap(), vec![1]);
+    assert_eq!(take_messages(&job_queue), vec!["JobCompleted(1, 1)", "QueueEmpty"]);
+  }
+
+  #[test]
+  fn invalidating_jobs_runs_them_and_their_dependers_again() {
+    let (mut job_queue, run_keys) = create_inline_job_queue();
+    job_queue.try_add_job(TestJob::with_dependencies(10, vec![TestJob::new(1), TestJob::new(2)]), 0).unwrap();
+    job_queue.run_until_idle();
+    take_messages(&job_queue);
+    run_keys.lock().unwrap().clear();
+
+    job_queue.invalidate(1, |key| Some(*key)).unwrap();
+    job_queue.run_until_idle();
+    assert_eq!(*run_keys.lock().unwrap(), vec![1, 10]);
+    assert_eq!(take_messages(&job_queue), vec!["JobCompleted(1, 1)", "JobCompleted(10, 13)", "QueueEmpty"]);
+  }
+
+  #[test]
+  fn invalidating_jobs_fails_dependers_with_other_failed_dependencies() {
+    let (mut job_queue, run_keys) = create_inline_job_queue();
+    job_queue.try_add_job(TestJob::with_dependencies(10, vec![TestJob::new(1), TestJob::new(PANICKING_KEY)]), 0).unwrap();
+    job_queue.run_until_idle();
+    take_messages(&job_queue);
+    run_keys.lock().unwrap().clear();
+
+    // Job 10 is reset along with job 1, but still depends on failed job 666, so it fails again instead of staying
+    // pending forever.
+    job_queue.invalidate(1, |key| Some(*key)).unwrap();
+    job_queue.run_until_idle();
+    assert_eq!(*run_keys.lock().unwrap(), vec![1]);
+    assert_eq!(take_messages(&job_queue), vec!["JobFailed(10, DependencyFailed(666))", "JobCompleted(1, 1)", "QueueEmpty"]);
+
+    // Invalidating job 10 itself fails it again without running anything, and empties the queue.
+    job_queue.invalidate(10, |key| Some(*key)).unwrap();
+    job_queue.run_until_idle();
+    assert_eq!(*run_keys.lock().unwrap(), vec![1]);
+    assert_eq!(take_messages(&job_queue), vec!["JobFailed(10, DependencyFailed(666))", "QueueEmpty"]);
+  }
+
+  #[test]
+  fn invalidating_jobs_only_runs_jobs_with_a_new_input_again() {
+    let (mut job_queue, run_keys) = create_inline_job_queue();
+    job_queue.try_add_job(TestJob::with_dependencies(10, vec![TestJob::new(1), TestJob::new(2)]), 0).unwrap();
+    job_queue.run_until_idle();
+    take_messages(&job_queue);
+    run_keys.lock().unwrap().clear();
+
+    // Job 10 has no new input, so it keeps its output and is not run again.
+    job_queue.invalidate(1, |key| (*key == 1).then_some(100)).unwrap();
+    job_queue.run_until_idle();
+    assert_eq!(*run_keys.lock().unwrap(), vec![1]);
+    assert_eq!(take_messages(&job_queue), vec!["JobCompleted(1, 100)", "QueueEmpty"]);
+
+    // Invalidating a job without a new input does nothing.
+    job_queue.invalidate(2, |_| None).unwrap();
+    job_queue.run_until_idle();
+    assert_eq!(*run_keys.lock().unwrap(), vec![1]);
+    assert!(take_messages(&job_queue).is_empty());
+  }
+
+  #[test]
+  fn removed_jobs_complete_from_the_output_cache_when_cacheable() {
+    let (job_queue, run_keys) = create_inline_job_queue();
+    // Only even outputs are cacheable.
+    let mut job_queue = job_queue.with_output_cache(1024, |output| (output % 2 == 0).then_some(4));
+    job_queue.try_add_jobs([(TestJob::new(1), 0), (TestJob::new(2), 0)]).unwrap();
+    job_queue.run_until_idle();
+    job_queue.try_remove_jobs_and_orphaned_dependencies([1, 2]).unwrap();
+    job_queue.run_until_idle();
+    take_messages(&job_queue);
+    run_keys.lock().unwrap().clear();
+
+    job_queue.try_add_jobs([(TestJob::new(1), 0), (TestJob::new(2), 0)]).unwrap();
+    job_queue.run_until_idle();
+    assert_eq!(*run_keys.lock().unwrap(), vec![1]);
+    assert_eq!(take_messages(&job_queue), vec!["JobCompleted(1, 1)", "JobCompleted(2, 2)", "QueueEmpty"]);
+  }
+
+  #[test]
+  fn submitting_jobs_takes_jobs_up_to_the_submission_capacity() {
+    let (job_queue, run_keys) = create_inline_job_queue();
+    let mut job_queue = job_queue.with_submission_capacity(3);
+    job_queue.try_add_job(TestJob::new(1), 0).unwrap();
+    let mut jobs = (2..=6).map(|key| (TestJob::new(key), 0));
+    assert_eq!(job_queue.try_add_jobs(jobs.by_ref()).unwrap(), 2);
+    assert_eq!(job_queue.submitted_job_count(), 3);
+    assert_eq!(job_queue.try_add_jobs(jobs.by_ref()).unwrap(), 0);
+    job_queue.run_until_idle();
+    assert_eq!(*run_keys.lock().unwrap(), vec![1, 2, 3]);
+    assert_eq!(job_queue.submitted_job_count(), 0);
+
+    // Jobs that were not taken stay in the iterator. Job 4 is taken out here, and the rest are accepted now that the
+    // manager has processed the submitted jobs.
+    assert_eq!(jobs.next().map(|(job, _)| job.key), Some(4));
+    assert_eq!(job_queue.try_add_jobs(jobs.by_ref()).unwrap(), 2);
+    assert!(jobs.next().is_none());
+    // Jobs that already exist count towards the capacity until the manager has processed them as well.
+    job_queue.try_add_job(TestJob::new(1), 0).unwrap();
+    let _future = job_queue.try_add_job_async(TestJob::new(1), 0).unwrap();
+    assert_eq!(job_queue.submitted_job_count(), 4);
+    job_queue.run_until_idle();
+    assert_eq!(*run_keys.lock().unwrap(), vec![1, 2, 3, 5, 6]);
+    assert_eq!(job_queue.submitted_job_count(), 0);
+  }
+
+  #[test]
+  fn removing_running_jobs_cancels_them() {
+    let (started_sender, started_receiver) = flume::bounded(1);
+    let (cancelled_sender, cancelled_receiver) = flume::bounded(1);
+    let handler = move |key: u32, _: u32, _: &[((), u32)], cancellation_token: &CancellationToken| {
+      started_sender.send(()).unwrap();
+      let start = Instant::now();
+      while !cancellation_token.is_cancelled() && start.elapsed() < Duration::from_secs(10) {
+        std::thread::sleep(Duration::from_millis(1));
+      }
+      cancelled_sender.send(cancellation_token.is_cancelled()).unwrap();
+      key
+    };
+    let job_queue: TestJobQueue = JobQueue::new(1, 1, 16, handler).unwrap();
+    job_queue.try_add_job(TestJob::new(1), 0).unwrap();
+    started_receiver.recv_timeout(Duration::from_secs(10)).unwrap();
+    job_queue.try_remove_job_and_orphaned_dependencies(1).unwrap();
+    assert!(cancelled_receiver.recv_timeout(Duration::from_secs(10)).unwrap(), "Running job was not cancelled");
+
+    let message = job_queue.get_message_receiver().recv_timeout(Duration::from_secs(10)).map(describe).unwrap();
+    assert_eq!(message, "RunningJobRemoved(1)");
+    job_queue.stop_and_join().unwrap();
+  }
+
+  #[test]
+  fn async_jobs_resolve_when_they_complete_or_fail() {
+    let (mut job_queue, _) = create_inline_job_queue();
+    let mut completed = job_queue.try_add_job_async(TestJob::with_dependencies(10, vec![TestJob::new(1)]), 0).unwrap();
+    let mut failed = job_queue.try_add_job_async(TestJob::new(PANICKING_KEY), 0).unwrap();
+    assert!(poll(&mut completed).is_none(), "Future resolved before the inline job queue was run");
+    job_queue.run_until_idle();
+    assert!(matches!(poll(&mut completed), Some(JobOutcome::Completed(11))));
+    assert!(matches!(poll(&mut failed), Some(JobOutcome::Failed(JobError::Panicked(_)))));
+  }
+
+  #[test]
+  fn async_jobs_that_already_completed_or_failed_resolve_immediately() {
+    let (mut job_queue, run_keys) = create_inline_job_queue();
+    job_queue.try_add_jobs([(TestJob::new(1), 0), (TestJob::new(PANICKING_KEY), 0)]).unwrap();
+    job_queue.run_until_idle();
+    run_keys.lock().unwrap().clear();
+
+    let mut completed = job_queue.try_add_job_async(TestJob::new(1), 0).unwrap();
+    let mut failed = job_queue.try_add_job_async(TestJob::new(PANICKING_KEY), 0).unwrap();
+    job_queue.run_until_idle();
+    assert!(matches!(poll(&mut completed), Some(JobOutcome::Completed(1))));
+    assert!(matches!(poll(&mut failed), Some(JobOutcome::Failed(JobError::Panicked(_)))));
+    assert!(run_keys.lock().unwrap().is_empty(), "Existing jobs were run again");
+  }
+
+  #[test]
+  fn removed_async_jobs_resolve_as_removed() {
+    let (mut job_queue, _) = create_inline_job_queue();
+    let mut future = job_queue.try_add_job_async(TestJob::new(1), 0).unwrap();
+    job_queue.try_remove_job_and_orphaned_dependencies(1).unwrap();
+    job_queue.run_until_idle();
+    assert!(matches!(poll(&mut future), Some(JobOutcome::Removed)));
+  }
+
+  #[test]
+  fn async_jobs_resolve_as_queue_stopped_when_the_queue_is_dropped() {
+    let (job_queue, _) = create_inline_job_queue();
+    let mut future = job_queue.try_add_job_async(TestJob::new(1), 0).unwrap();
+    drop(job_queue);
+    assert!(matches!(poll(&mut future), Some(JobOutcome::QueueStopped)));
+  }
+}
@@ -1,58 +1,85 @@
 use std::collections::VecDeque;
-use std::hash::BuildHasherDefault;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::thread;
 use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
 
 use flume::{Receiver, Sender};
-use hashlink::LinkedHashMap;
-use hashlink::LinkedHashSet;
 use petgraph::prelude::*;
-use rustc_hash::{FxHasher, FxHashMap, FxHashSet};
+use rustc_hash::{FxHashMap, FxHashSet};
 use tracing::trace;
 
-use crate::{DepKey, In, Job, JobKey, JobQueueMessage, Out};
+use crate::{CancellationToken, DepKey, In, Job, JobError, JobKey, JobOutcome, JobQueueMessage, Out, Priority};
+use crate::graph::{JobGraphNodeStatus, JobGraphSnapshot};
+use crate::metrics::{DurationHistogram, JobQueueMetrics};
+use crate::output_cache::{OutputCache, OutputSize};
+use crate::priority_queue::PriorityQueue;
 
 // Message from queue
 
-pub(crate) enum FromQueueMessage<JK, J> {
-  TryAddJob(J),
+pub(crate) enum FromQueueMessage<JK, I, J, O> {
+  TryAddJob(J, Priority),
+  TryAddJobs(Vec<(J, Priority)>),
+  TryAddJobAsync(J, Priority, Sender<JobOutcome<JK, O>>),
   TryRemoveJobAndOrphanedDependencies(JK),
+  TryRemoveJobsAndOrphanedDependencies(Vec<JK>),
+  UpdatePriority(JK, Priority),
+  Invalidate(JK, InputFactory<JK, I>),
+  SetOutputCache(usize, OutputSize<O>),
+  SetOutputLoader(OutputLoader<J, O>),
+  RequestMetrics,
+  RequestJobGraph,
 }
 
 
 // Manager thread
 
-pub(crate) type FromQueue<JK, J> = FromQueueMessage<JK, J>;
-pub(crate) type FromWorker<JK, DK, O> = (JK, O, Vec<(DK, O)>);
+pub(crate) type FromQueue<JK, I, J, O> = FromQueueMessage<JK, I, J, O>;
+pub(crate) type OutputLoader<J, O> = Box<dyn Fn(&J) -> Option<O> + Send>;
+pub(crate) type InputFactory<JK, I> = Box<dyn FnMut(&JK) -> Option<I> + Send>;
+pub(crate) type FromWorker<JK, DK, O> = (JK, Result<O, JobError<JK>>, Vec<(DK, O)>, CancellationToken, Duration);
 
 pub(super) struct ManagerThread<JK, DK, I, J, O> {
-  from_queue: Receiver<FromQueue<JK, J>>,
+  from_queue: Receiver<FromQueue<JK, I, J, O>>,
   to_worker: Sender<crate::worker::FromManager<JK, DK, I, O>>,
   from_worker: Receiver<FromWorker<JK, DK, O>>,
-  to_queue: Sender<JobQueueMessage<JK, I, O>>,
+  to_queue: Sender<JobQueueMessage<JK, DK, I, O>>,
+  submitted_job_count: Arc<AtomicUsize>,
 
   target_running_job_count: usize,
 
   job_graph: DiGraphMap<JK, DK>,
-  job_key_to_job_status: FxHashMap<JK, JobStatus<DK, I, O>>,
-  jobs_to_add: LinkedHashMap<JK, J, BuildHasherDefault<FxHasher>>,
-  jobs_to_run: LinkedHashSet<JK, BuildHasherDefault<FxHasher>>,
+  job_key_to_job_status: FxHashMap<JK, JobStatus<JK, DK, I, O>>,
+  job_key_to_priority: FxHashMap<JK, Priority>,
+  jobs_to_add: PriorityQueue<JK, J>,
+  jobs_to_run: PriorityQueue<JK, Instant>,
+  job_key_to_outcome_senders: FxHashMap<JK, Vec<Sender<JobOutcome<JK, O>>>>,
 
   dependency_output_cache: Vec<Vec<(DK, O)>>,
+  output_cache: Option<OutputCache<JK, O>>,
+  output_loader: Option<OutputLoader<J, O>>,
   bfs_stack_cache: VecDeque<JK>,
   bfs_discovered_cache: FxHashSet<JK>,
 
   pending_jobs: u32,
   running_jobs: u32,
+  completed_jobs: u32,
+  failed_jobs: u32,
+  dependency_output_cache_hits: u64,
+  dependency_output_cache_misses: u64,
+  queue_wait_histogram: DurationHistogram,
+  run_time_histogram: DurationHistogram,
 }
 
 impl<JK: JobKey, DK: DepKey, I: In, J: Job<JK, DK, I>, O: Out> ManagerThread<JK, DK, I, J, O> {
   #[inline]
   pub(super) fn new(
-    from_queue: Receiver<FromQueue<JK, J>>,
+    from_queue: Receiver<FromQueue<JK, I, J, O>>,
     to_worker: Sender<crate::worker::FromManager<JK, DK, I, O>>,
     from_worker: Receiver<FromWorker<JK, DK, O>>,
-    to_queue: Sender<JobQueueMessage<JK, I, O>>,
+    to_queue: Sender<JobQueueMessage<JK, DK, I, O>>,
+    submitted_job_count: Arc<AtomicUsize>,
     target_running_job_count: usize,
     dependency_output_cache_count: usize,
   ) -> Self {
@@ -61,20 +88,31 @@ impl<JK: JobKey, DK: DepKey, I: In, J: Job<JK, DK, I>, O: Out> ManagerThread<JK,
       to_worker,
       from_worker,
       to_queue,
+      submitted_job_count,
 
       target_running_job_count,
 
       job_graph: DiGraphMap::new(),
       job_key_to_job_status: FxHashMap::default(),
-      jobs_to_add: LinkedHashMap::default(),
-      jobs_to_run: LinkedHashSet::default(),
+      job_key_to_priority: FxHashMap::default(),
+      jobs_to_add: PriorityQueue::default(),
+      jobs_to_run: PriorityQueue::default(),
+      job_key_to_outcome_senders: FxHashMap::default(),
 
       dependency_output_cache: Vec::with_capacity(dependency_output_cache_count),
+      output_cache: None,
+      output_loader: None,
       bfs_stack_cache: VecDeque::default(),
       bfs_discovered_cache: FxHashSet::default(),
 
       pending_jobs: 0,
       running_jobs: 0,
+      completed_jobs: 0,
+      failed_jobs: 0,
+      dependency_output_cache_hits: 0,
+      dependency_output_cache_misses: 0,
+      queue_wait_histogram: DurationHistogram::default(),
+      run_time_histogram: DurationHistogram::default(),
     }
   }
 
@@ -97,6 +135,19 @@ impl<JK: JobKey, DK: DepKey, I: In, J: Job<JK, DK, I>, O: Out> ManagerThread<JK,
     trace!("Stopped job queue manager thread");
   }
 
+  /// Handles a message from the queue or a worker if one is available, without blocking. Returns `None` if no message
+  /// was handled, `Some(false)` if this manager should stop, and `Some(true)` otherwise.
+  #[inline]
+  pub(super) fn try_receive(&mut self, job_key_cache_1: &mut Vec<JK>) -> Option<bool> {
+    if let Ok(message) = self.from_queue.try_recv() {
+      Some(self.handle_from_queue(message))
+    } else if let Ok((job_key, output, dependency_outputs, cancellation_token, run_time)) = self.from_worker.try_recv() {
+      Some(self.handle_from_worker(job_key, output, dependency_outputs, cancellation_token, run_time, job_key_cache_1))
+    } else {
+      None
+    }
+  }
+
   #[inline]
   fn receive(&mut self, job_key_cache_1: &mut Vec<JK>) -> bool {
     let selected = flume::Selector::new()
@@ -117,32 +168,60 @@ impl<JK: JobKey, DK: DepKey, I: In, J: Job<JK, DK, I>, O: Out> ManagerThread<JK,
       .wait();
     match selected {
       Some(SelectedReceiver::FromQueue(message)) => self.handle_from_queue(message),
-      Some(SelectedReceiver::FromWorker((job_key, output, dependency_outputs))) => self.handle_from_worker(job_key, output, dependency_outputs, job_key_cache_1),
+      Some(SelectedReceiver::FromWorker((job_key, output, dependency_outputs, cancellation_token, run_time))) => self.handle_from_worker(job_key, output, dependency_outputs, cancellation_token, run_time, job_key_cache_1),
       None => false,
     }
   }
 
 
   #[inline]
-  fn handle_from_queue(&mut self, message: FromQueueMessage<JK, J>) -> bool {
+  fn handle_from_queue(&mut self, message: FromQueueMessage<JK, I, J, O>) -> bool {
     use FromQueueMessage::*;
     match message {
-      TryAddJob(job) => self.try_add_job(job),
+      TryAddJob(job, priority) => self.try_add_job(job, priority),
+      TryAddJobs(jobs) => self.try_add_jobs(jobs),
+      TryAddJobAsync(job, priority, outcome_sender) => self.try_add_job_async(job, priority, outcome_sender),
       TryRemoveJobAndOrphanedDependencies(job_key) => self.try_remove_job_and_orphaned_dependencies(job_key),
+      TryRemoveJobsAndOrphanedDependencies(job_keys) => job_keys.into_iter().all(|job_key| self.try_remove_job_and_orphaned_dependencies(job_key)),
+      UpdatePriority(job_key, priority) => self.update_priority(job_key, priority),
+      Invalidate(job_key, create_input) => self.invalidate(job_key, create_input),
+      SetOutputCache(byte_budget, output_size) => {
+        self.output_cache = Some(OutputCache::new(byte_budget, output_size));
+        true
+      }
+      SetOutputLoader(load) => {
+        self.output_loader = Some(load);
+        true
+      }
+      RequestMetrics => self.to_queue.send(JobQueueMessage::Metrics(Box::new(self.create_metrics()))).is_ok(),
+      RequestJobGraph => self.to_queue.send(JobQueueMessage::JobGraph(Box::new(self.create_job_graph_snapshot()))).is_ok(),
     }
   }
 
   #[profiling::function]
   #[inline]
-  fn handle_from_worker(&mut self, job_key: JK, output: O, dependency_outputs: Vec<(DK, O)>, job_key_cache: &mut Vec<JK>) -> bool {
+  fn handle_from_worker(&mut self, job_key: JK, output: Result<O, JobError<JK>>, dependency_outputs: Vec<(DK, O)>, cancellation_token: CancellationToken, run_time: Duration, job_key_cache: &mut Vec<JK>) -> bool {
     self.reclaim_dependency_outputs(dependency_outputs);
+    self.run_time_histogram.record(run_time);
     use JobStatus::*;
-    match self.job_key_to_job_status.get(&job_key) {
-      Some(Pending(_, _)) => return true, // Job was removed, added, and not scheduled while it was running -> don't complete it.
-      Some(Completed(_)) => return true, // Job was removed, added, scheduled, and completed while it was running -> don't complete it.
-      None => return true, // Job was removed while it was running -> don't complete it.
-      _ => {} // Otherwise: continue.
+    let discard = match self.job_key_to_job_status.get(&job_key) {
+      Some(Pending(_, _)) => true, // Job was removed or invalidated, and not scheduled again while it was running -> don't complete it.
+      Some(Running(running_cancellation_token)) if !running_cancellation_token.is_same(&cancellation_token) => true, // Job was removed, added, and scheduled while it was running -> don't complete it.
+      Some(Completed(_)) => true, // Job was removed, added, scheduled, and completed while it was running -> don't complete it.
+      Some(Failed(_)) => true, // Job was removed, added, and failed while it was running -> don't complete it.
+      None => true, // Job was removed while it was running -> don't complete it.
+      _ => false, // Otherwise: continue.
+    };
+    if discard { // The worker is free again: run and add jobs up to the target.
+      return self.run_and_add_jobs_until_target();
     }
+    let output = match output {
+      Ok(output) => output,
+      Err(error) => {
+        if !self.fail_job_and_dependers(job_key, error) { return false; }
+        return self.run_and_add_jobs_until_target();
+      }
+    };
     // Try to make dependent jobs ready to run.
     job_key_cache.clear();
     job_key_cache.extend(self.job_graph.neighbors_directed(job_key, Incoming));
@@ -158,43 +237,139 @@ impl<JK: JobKey, DK: DepKey, I: In, J: Job<JK, DK, I>, O: Out> ManagerThread<JK,
 
   #[profiling::function]
   #[inline]
-  fn try_add_job(&mut self, job: J) -> bool {
-    let job_key = job.key();
-    if self.job_key_to_job_status.contains_key(job_key) { return true; } // Job already exists in graph: done
-    if self.jobs_to_add.contains_key(job_key) { return true; } // Job already exists in jobs to add map: done.
-    self.jobs_to_add.insert(*job_key, job);
+  fn try_add_job(&mut self, job: J, priority: Priority) -> bool {
+    self.insert_job_to_add(job, priority);
+    self.run_and_add_jobs_until_target()
+  }
+
+  #[profiling::function]
+  #[inline]
+  fn try_add_jobs(&mut self, jobs: Vec<(J, Priority)>) -> bool {
+    for (job, priority) in jobs {
+      self.insert_job_to_add(job, priority);
+    }
     self.run_and_add_jobs_until_target()
   }
 
   #[inline]
-  fn force_add_job_and_dependencies(&mut self, job: J) -> Option<O> {
+  fn insert_job_to_add(&mut self, job: J, priority: Priority) {
+    let job_key = job.key();
+    if self.job_key_to_job_status.contains_key(job_key) || self.jobs_to_add.contains_key(job_key) {
+      // Job already exists in graph or in jobs to add map: done.
+      self.submitted_job_count.fetch_sub(1, Ordering::Relaxed);
+      return;
+    }
+    self.jobs_to_add.insert(*job_key, job, priority);
+  }
+
+  /// Removes job `job_key` from `jobs_to_add`, returning its job if it was there.
+  #[inline]
+  fn remove_job_to_add(&mut self, job_key: &JK) -> Option<J> {
+    let job = self.jobs_to_add.remove(job_key)?;
+    self.submitted_job_count.fetch_sub(1, Ordering::Relaxed);
+    Some(job)
+  }
+
+  #[profiling::function]
+  #[inline]
+  fn try_add_job_async(&mut self, job: J, priority: Priority, outcome_sender: Sender<JobOutcome<JK, O>>) -> bool {
+    let job_key = *job.key();
+    match self.job_key_to_job_status.get(&job_key) {
+      Some(JobStatus::Completed(output)) => { // Job already completed: send its output.
+        let _ = outcome_sender.send(JobOutcome::Completed(output.clone()));
+        self.submitted_job_count.fetch_sub(1, Ordering::Relaxed);
+        return true;
+      }
+      Some(JobStatus::Failed(error)) => { // Job already failed: send its error.
+        let _ = outcome_sender.send(JobOutcome::Failed(error.clone()));
+        self.submitted_job_count.fetch_sub(1, Ordering::Relaxed);
+        return true;
+      }
+      _ => {}
+    }
+    self.job_key_to_outcome_senders.entry(job_key).or_default().push(outcome_sender);
+    self.try_add_job(job, priority)
+  }
+
+  /// Adds `job` and its dependencies to the graph if they do not exist yet. Returns `Ok(Some(output))` if the job is
+  /// completed, `Ok(None)` if it is not completed yet, and `Err(())` if it failed.
+  #[inline]
+  fn force_add_job_and_dependencies(&mut self, job: J, priority: Priority) -> Result<Option<O>, ()> {
     let job_key = job.key();
     if let Some(job_status) = self.job_key_to_job_status.get(job_key) { // Job already exists.
-      return job_status.clone_output_if_completed();
+      if let JobStatus::Failed(_) = job_status { return Err(()); }
+      let output = job_status.clone_output_if_completed();
+      if output.is_none() && priority < self.job_key_to_priority[job_key] {
+        self.set_priority_and_propagate(*job_key, priority);
+      }
+      return Ok(output);
     }
-    self.jobs_to_add.remove(job_key); // Remove from jobs_to_add, as we are force adding it.
+    if let Some(output) = self.output_cache.as_mut().and_then(|output_cache| output_cache.take(job_key)) {
+      return Ok(Some(self.resurrect_job(job, priority, output)));
+    }
+    if let Some(output) = self.output_loader.as_ref().and_then(|load| load(&job)) {
+      return Ok(Some(self.resurrect_job(job, priority, output)));
+    }
+    self.remove_job_to_add(job_key); // Remove from jobs_to_add, as we are force adding it.
     let job_key = *job_key;
     let (input, dependencies) = job.into();
     self.job_graph.add_node(job_key);
+    self.job_key_to_priority.insert(job_key, priority);
     trace!("Added job {:?}", job_key);
     let mut dependency_outputs = self.create_dependency_outputs();
     let mut can_run = true;
+    let mut failed_dependency_job_key = None;
     for (dependency_key, dependency_job) in dependencies {
       let dependency_job_key = *dependency_job.key();
-      let dependency_output = self.force_add_job_and_dependencies(dependency_job);
+      let dependency_output = self.force_add_job_and_dependencies(dependency_job, priority);
       self.add_dependency_edge(job_key, dependency_job_key, dependency_key);
-      if let Some(dependency_output) = dependency_output {
-        dependency_outputs.push((dependency_key, dependency_output));
-      } else {
-        can_run = false;
+      match dependency_output {
+        Ok(Some(dependency_output)) => dependency_outputs.push((dependency_key, dependency_output)),
+        Ok(None) => can_run = false,
+        Err(()) => failed_dependency_job_key = Some(dependency_job_key),
       }
     }
+    if let Some(failed_dependency_job_key) = failed_dependency_job_key { // Fail immediately as a dependency has failed.
+      self.fail_added_job(job_key, dependency_outputs, failed_dependency_job_key);
+      return Err(());
+    }
     self.job_key_to_job_status.insert(job_key, JobStatus::Pending(input, dependency_outputs));
     self.pending_jobs += 1;
     if can_run {
-      self.jobs_to_run.insert(job_key);
+      self.jobs_to_run.insert(job_key, Instant::now(), priority);
     }
-    None
+    Ok(None)
+  }
+
+  /// Adds `job` as completed with `output` from the output cache or output loader, without adding its dependencies.
+  /// Returns the output.
+  /// Kept out of `force_add_job_and_dependencies` to keep its stack frame small.
+  #[inline(never)]
+  fn resurrect_job(&mut self, job: J, priority: Priority, output: O) -> O {
+    let job_key = *job.key();
+    self.remove_job_to_add(&job_key); // Remove from jobs_to_add, as we are force adding it.
+    self.job_graph.add_node(job_key);
+    self.job_key_to_priority.insert(job_key, priority);
+    self.job_key_to_job_status.insert(job_key, JobStatus::Completed(output.clone()));
+    self.completed_jobs += 1;
+    trace!("Completed job {:?} with a cached or loaded output", job_key);
+    Self::send_outcome(&mut self.job_key_to_outcome_senders, job_key, || JobOutcome::Completed(output.clone()));
+    let _ = self.to_queue.send(JobQueueMessage::JobCompleted(job_key, output.clone())); // Send errors are handled when running jobs.
+    output
+  }
+
+  /// Fails just added job `job_key` because dependency `failed_dependency_job_key` has failed. Kept out of
+  /// `force_add_job_and_dependencies` to keep its stack frame small, as it recurses for each level of dependencies.
+  #[cold]
+  #[inline(never)]
+  fn fail_added_job(&mut self, job_key: JK, dependency_outputs: Vec<(DK, O)>, failed_dependency_job_key: JK) {
+    self.reclaim_dependency_outputs(dependency_outputs);
+    let error = JobError::DependencyFailed(failed_dependency_job_key);
+    self.job_key_to_job_status.insert(job_key, JobStatus::Failed(error.clone()));
+    self.failed_jobs += 1;
+    trace!("Failed job {:?} due to failed dependency {:?}", job_key, failed_dependency_job_key);
+    Self::send_outcome(&mut self.job_key_to_outcome_senders, job_key, || JobOutcome::Failed(error.clone()));
+    let _ = self.to_queue.send(JobQueueMessage::JobFailed(job_key, error));
   }
 
   #[inline]
@@ -207,51 +382,190 @@ impl<JK: JobKey, DK: DepKey, I: In, J: Job<JK, DK, I>, O: Out> ManagerThread<JK,
   #[profiling::function]
   #[inline]
   fn try_remove_job_and_orphaned_dependencies(&mut self, job_key: JK) -> bool {
-    if let Some(_) = self.jobs_to_add.remove(&job_key) { return true; } // Job was not added to the graph yet: done.
+    if self.remove_job_to_add(&job_key).is_some() { // Job was not added to the graph yet: done.
+      Self::send_outcome(&mut self.job_key_to_outcome_senders, job_key, || JobOutcome::Removed);
+      return self.send_queue_empty_if_applicable();
+    }
     if !self.job_key_to_job_status.contains_key(&job_key) { return true; } // Job does not exist: done.
     self.bfs_stack_cache.clear();
-    self.bfs_discovered_cache.clear();
     self.bfs_stack_cache.push_back(job_key);
-    self.bfs_discovered_cache.insert(job_key);
     trace!("Try to remove job {:?} along with orphaned dependencies", job_key);
     while let Some(job_key) = self.bfs_stack_cache.pop_front() {
+      if !self.job_key_to_job_status.contains_key(&job_key) {
+        continue; // Job was already removed, as it was reached through multiple removed dependers.
+      }
       if self.job_graph.neighbors_directed(job_key, Incoming).next().is_some() {
         continue; // Job has incoming dependencies, can't remove it.
       }
+      // Visit dependencies (again) after removing this job, as they may have become orphaned. Must be collected before
+      // removing the node, as that also removes its edges.
+      self.bfs_stack_cache.extend(self.job_graph.neighbors_directed(job_key, Outgoing));
       self.job_graph.remove_node(job_key);
       // NOTE: no need to remove from `jobs_to_add`, as either the job is in `jobs_to_add` or it is in `job_graph`, and
       //       since we are discovering the job in `job_graph` here, it cannot be in `jobs_to_add`.
       self.jobs_to_run.remove(&job_key);
+      self.job_key_to_priority.remove(&job_key);
+      Self::send_outcome(&mut self.job_key_to_outcome_senders, job_key, || JobOutcome::Removed);
       trace!("Removed job {:?}", job_key);
       let job_status = self.job_key_to_job_status.remove(&job_key).unwrap(); // Unwrap OK: mapping must exist.
       let send_success = match job_status {
         JobStatus::Pending(input, dependency_outputs) => {
           self.reclaim_dependency_outputs(dependency_outputs);
           let send_success = self.to_queue.send(JobQueueMessage::PendingJobRemoved(job_key, input)).is_ok();
-          let send_success = self.decrement_pending_jobs_and_send_queue_empty_if_applicable() | send_success;
-          send_success
+          self.decrement_pending_jobs_and_send_queue_empty_if_applicable() | send_success
         }
-        JobStatus::Running => {
+        JobStatus::Running(cancellation_token) => {
+          cancellation_token.cancel();
           let send_success = self.to_queue.send(JobQueueMessage::RunningJobRemoved(job_key)).is_ok();
-          let send_success = self.decrement_running_jobs_and_send_queue_empty_if_applicable() | send_success;
-          send_success
+          self.decrement_running_jobs_and_send_queue_empty_if_applicable() | send_success
         }
         JobStatus::Completed(output) => {
-          let send_success = self.to_queue.send(JobQueueMessage::CompletedJobRemoved(job_key, output)).is_ok();
-          send_success
+          self.completed_jobs -= 1;
+          if let Some(output_cache) = &mut self.output_cache {
+            output_cache.insert(job_key, output.clone());
+          }
+          self.to_queue.send(JobQueueMessage::CompletedJobRemoved(job_key, output)).is_ok()
+        }
+        JobStatus::Failed(_) => {
+          self.failed_jobs -= 1;
+          self.to_queue.send(JobQueueMessage::FailedJobRemoved(job_key)).is_ok()
         }
       };
       if !send_success { return false; }
+    }
+    true
+  }
+
+
+  #[profiling::function]
+  #[inline]
+  fn update_priority(&mut self, job_key: JK, priority: Priority) -> bool {
+    if self.jobs_to_add.update_priority(&job_key, priority) { return true; } // Job was not added to the graph yet: done.
+    if !self.job_key_to_priority.contains_key(&job_key) { return true; } // Job does not exist: done.
+    self.set_priority_and_propagate(job_key, priority);
+    true
+  }
+
+  /// Sets the priority of job `job_key` to `priority`, and then updates the priority of each (transitive) dependency to
+  /// the lowest priority of the jobs that depend on it.
+  fn set_priority_and_propagate(&mut self, job_key: JK, priority: Priority) {
+    self.job_key_to_priority.insert(job_key, priority);
+    self.jobs_to_run.update_priority(&job_key, priority);
+    self.bfs_stack_cache.clear();
+    self.bfs_stack_cache.push_back(job_key);
+    while let Some(job_key) = self.bfs_stack_cache.pop_front() {
       for dependency_job_key in self.job_graph.neighbors_directed(job_key, Outgoing) {
-        if !self.bfs_discovered_cache.contains(&dependency_job_key) {
-          self.bfs_discovered_cache.insert(dependency_job_key);
+        if let Some(JobStatus::Completed(_)) = self.job_key_to_job_status.get(&dependency_job_key) { continue; }
+        let priority = self.job_graph.neighbors_directed(dependency_job_key, Incoming)
+          .map(|depender_job_key| self.job_key_to_priority[&depender_job_key])
+          .min()
+          .unwrap(); // Unwrap OK: `dependency_job_key` has at least `job_key` as incoming neighbor.
+        if self.job_key_to_priority.insert(dependency_job_key, priority) != Some(priority) {
+          self.jobs_to_run.update_priority(&dependency_job_key, priority);
           self.bfs_stack_cache.push_back(dependency_job_key);
         }
       }
     }
+  }
+
+
+  #[profiling::function]
+  #[inline]
+  fn invalidate(&mut self, job_key: JK, mut create_input: InputFactory<JK, I>) -> bool {
+    match self.job_key_to_job_status.get(&job_key) {
+      None => return true, // Job does not exist or was not added to the graph yet: done.
+      Some(JobStatus::Pending(_, _)) => return true, // Job has not run yet: done.
+      _ => {}
+    }
+    let Some(input) = create_input(&job_key) else { return true; }; // Job cannot be run again: done.
+    trace!("Invalidating job {:?} along with its dependers", job_key);
+    if let Some(output_cache) = &mut self.output_cache {
+      output_cache.clear(); // Cached outputs may depend on the invalidated output, but their dependencies are unknown.
+    }
+    self.bfs_stack_cache.clear();
+    self.bfs_discovered_cache.clear();
+    self.bfs_stack_cache.push_back(job_key);
+    self.bfs_discovered_cache.insert(job_key);
+    let mut input = Some(input); // Input of `job_key`, which is reset first.
+    while let Some(job_key) = self.bfs_stack_cache.pop_front() {
+      let was_pending = matches!(self.job_key_to_job_status[&job_key], JobStatus::Pending(_, _));
+      let input = if was_pending { None } else {
+        let Some(input) = input.take().or_else(|| create_input(&job_key)) else { continue; }; // Job cannot be run again: keep its output.
+        Some(input)
+      };
+      self.reset_job_to_pending(job_key, input);
+      if was_pending { continue; } // Dependers of a pending job are pending as well and do not have its output.
+      for depender_job_key in self.job_graph.neighbors_directed(job_key, Incoming) {
+        if self.bfs_discovered_cache.insert(depender_job_key) {
+          self.bfs_stack_cache.push_back(depender_job_key);
+        }
+      }
+    }
+    // Reset jobs that (still) depend on a failed job will never run: fail them along with their dependers. Must be done
+    // after all jobs are reset, as a failed dependency may be reset later on in the traversal.
+    let discovered = std::mem::take(&mut self.bfs_discovered_cache);
+    let mut failed_any = false;
+    for &job_key in &discovered {
+      if !matches!(self.job_key_to_job_status[&job_key], JobStatus::Pending(_, _)) { continue; } // Already failed, or not reset.
+      let failed_dependency_job_key = self.job_graph.neighbors_directed(job_key, Outgoing)
+        .find(|dependency_job_key| matches!(self.job_key_to_job_status[dependency_job_key], JobStatus::Failed(_)));
+      if let Some(failed_dependency_job_key) = failed_dependency_job_key {
+        if !self.fail_pending_job_and_dependers(job_key, JobError::DependencyFailed(failed_dependency_job_key)) { return false; }
+        failed_any = true;
+      }
+    }
+    self.bfs_discovered_cache = discovered; // Keep the allocation.
+    if !self.run_and_add_jobs_until_target() { return false; }
+    if failed_any { // Failing jobs may have emptied the queue.
+      return self.send_queue_empty_if_applicable();
+    }
     true
   }
 
+  /// Resets job `job_key` to pending with `input`, cancelling it if it is running, and discarding its output if it is
+  /// completed. A job that is already pending keeps its input, and `input` must be `None` for it. Its dependency outputs
+  /// are collected again from its completed dependencies, and it is made ready to run if all its dependencies are
+  /// completed.
+  fn reset_job_to_pending(&mut self, job_key: JK, input: Option<I>) {
+    let job_status = self.job_key_to_job_status.remove(&job_key).unwrap(); // Unwrap OK: job must exist when reset_job_to_pending is called.
+    let (input, mut dependency_outputs) = match (job_status, input) {
+      (JobStatus::Pending(input, dependency_outputs), None) => {
+        self.jobs_to_run.remove(&job_key);
+        (input, dependency_outputs)
+      }
+      (JobStatus::Running(cancellation_token), Some(input)) => {
+        cancellation_token.cancel();
+        self.running_jobs -= 1;
+        self.pending_jobs += 1;
+        (input, self.create_dependency_outputs())
+      }
+      (JobStatus::Completed(_), Some(input)) => {
+        self.completed_jobs -= 1;
+        self.pending_jobs += 1;
+        (input, self.create_dependency_outputs())
+      }
+      (JobStatus::Failed(_), Some(input)) => {
+        self.failed_jobs -= 1;
+        self.pending_jobs += 1;
+        (input, self.create_dependency_outputs())
+      }
+      _ => panic!("Attempt to reset job {:?} to pending without an input, or with an input while it is pending", job_key),
+    };
+    trace!("Reset job {:?} to pending", job_key);
+    dependency_outputs.clear();
+    let mut can_run = true;
+    for (_, dependency_job_key, dependency_key) in self.job_graph.edges_directed(job_key, Outgoing) {
+      match self.job_key_to_job_status.get(&dependency_job_key) {
+        Some(JobStatus::Completed(output)) => dependency_outputs.push((*dependency_key, output.clone())),
+        _ => can_run = false,
+      }
+    }
+    self.job_key_to_job_status.insert(job_key, JobStatus::Pending(input, dependency_outputs));
+    if can_run {
+      self.jobs_to_run.insert(job_key, Instant::now(), self.job_key_to_priority[&job_key]);
+    }
+  }
+
 
   #[inline]
   fn try_make_job_ready_to_run(&mut self, depender_job_key: JK, dependee_job_key: JK, dependee_job_output: &O) -> bool {
@@ -260,7 +574,7 @@ impl<JK: JobKey, DK: DepKey, I: In, J: Job<JK, DK, I>, O: Out> ManagerThread<JK,
       let dependency_key = self.job_graph[(depender_job_key, dependee_job_key)];
       dependency_outputs.push((dependency_key, dependee_job_output.clone()));
       if self.job_graph.neighbors_directed(depender_job_key, Outgoing).count() == dependency_outputs.len() {
-        self.jobs_to_run.insert(depender_job_key);
+        self.jobs_to_run.insert(depender_job_key, Instant::now(), self.job_key_to_priority[&depender_job_key]);
       }
     }
     true
@@ -269,11 +583,12 @@ impl<JK: JobKey, DK: DepKey, I: In, J: Job<JK, DK, I>, O: Out> ManagerThread<JK,
   #[inline]
   fn run_pending_job(&mut self, job_key: JK) -> bool {
     let job_status = self.job_key_to_job_status.get_mut(&job_key).unwrap(); // Unwrap OK: job must exist when `run_pending_job` is called.
-    if let JobStatus::Pending(input, dependency_outputs) = std::mem::replace(job_status, JobStatus::Running) {
+    let cancellation_token = CancellationToken::default();
+    if let JobStatus::Pending(input, dependency_outputs) = std::mem::replace(job_status, JobStatus::Running(cancellation_token.clone())) {
       trace!("Running job {:?}", job_key);
       self.pending_jobs -= 1;
       self.running_jobs += 1;
-      if !self.to_worker.send((job_key, input, dependency_outputs)).is_ok() { return false; }
+      if !self.to_worker.send((job_key, input, dependency_outputs, cancellation_token)).is_ok() { return false; }
     }
     true
   }
@@ -282,7 +597,8 @@ impl<JK: JobKey, DK: DepKey, I: In, J: Job<JK, DK, I>, O: Out> ManagerThread<JK,
   #[inline]
   fn run_jobs_until_target(&mut self) -> bool {
     while !self.jobs_to_run.is_empty() && self.to_worker.len() < self.target_running_job_count {
-      let job_key = self.jobs_to_run.pop_front().unwrap(); // Unwrap OK: `jobs_to_run` is not empty.
+      let (job_key, ready_instant, _) = self.jobs_to_run.pop_front().unwrap(); // Unwrap OK: `jobs_to_run` is not empty.
+      self.queue_wait_histogram.record(ready_instant.elapsed());
       if !self.run_pending_job(job_key) { return false; }
     }
     true
@@ -293,50 +609,123 @@ impl<JK: JobKey, DK: DepKey, I: In, J: Job<JK, DK, I>, O: Out> ManagerThread<JK,
     // First run jobs until target, to give jobs that are ready to run priority.
     if !self.run_jobs_until_target() { return false; }
     // Then add and run jobs until target.
+    let mut added_any = false;
     while !self.jobs_to_add.is_empty() && self.to_worker.len() < self.target_running_job_count {
-      let (_, job) = self.jobs_to_add.pop_front().unwrap(); // Unwrap OK: `jobs_to_add` is not empty.
-      self.force_add_job_and_dependencies(job);
+      let (_, job, priority) = self.jobs_to_add.pop_front().unwrap(); // Unwrap OK: `jobs_to_add` is not empty.
+      self.submitted_job_count.fetch_sub(1, Ordering::Relaxed);
+      let _ = self.force_add_job_and_dependencies(job, priority); // Failure is reported through `JobQueueMessage::JobFailed`.
       if !self.run_jobs_until_target() { return false; }
+      added_any = true;
+    }
+    if added_any { // Added jobs may have completed from the output cache or failed immediately, emptying the queue.
+      return self.send_queue_empty_if_applicable();
     }
     true
   }
 
 
+  /// Fails running job `job_key` with `error`, and then transitively fails all pending jobs that depend on it.
+  fn fail_job_and_dependers(&mut self, job_key: JK, error: JobError<JK>) -> bool {
+    trace!("Failing job {:?}: {}", job_key, error);
+    *self.job_key_to_job_status.get_mut(&job_key).unwrap() = JobStatus::Failed(error.clone()); // Unwrap OK: job must exist when fail_job_and_dependers is called.
+    self.failed_jobs += 1;
+    Self::send_outcome(&mut self.job_key_to_outcome_senders, job_key, || JobOutcome::Failed(error.clone()));
+    if self.to_queue.send(JobQueueMessage::JobFailed(job_key, error)).is_err() { return false; }
+    if !self.fail_pending_dependers(job_key) { return false; }
+    self.decrement_running_jobs_and_send_queue_empty_if_applicable()
+  }
+
+  /// Fails pending job `job_key` with `error`, and then transitively fails all pending jobs that depend on it. Does not
+  /// send [`JobQueueMessage::QueueEmpty`]; that is up to the caller.
+  fn fail_pending_job_and_dependers(&mut self, job_key: JK, error: JobError<JK>) -> bool {
+    trace!("Failing pending job {:?}: {}", job_key, error);
+    let Some(JobStatus::Pending(_, dependency_outputs)) = self.job_key_to_job_status.remove(&job_key) else {
+      panic!("Attempt to fail pending job {:?} that is not pending", job_key);
+    };
+    self.reclaim_dependency_outputs(dependency_outputs);
+    self.jobs_to_run.remove(&job_key);
+    self.job_key_to_job_status.insert(job_key, JobStatus::Failed(error.clone()));
+    self.pending_jobs -= 1;
+    self.failed_jobs += 1;
+    Self::send_outcome(&mut self.job_key_to_outcome_senders, job_key, || JobOutcome::Failed(error.clone()));
+    if self.to_queue.send(JobQueueMessage::JobFailed(job_key, error)).is_err() { return false; }
+    self.fail_pending_dependers(job_key)
+  }
+
+  /// Transitively fails all pending jobs that depend on failed job `job_key`. Does not send
+  /// [`JobQueueMessage::QueueEmpty`]; that is up to the caller.
+  fn fail_pending_dependers(&mut self, job_key: JK) -> bool {
+    self.bfs_stack_cache.clear();
+    self.bfs_stack_cache.push_back(job_key);
+    while let Some(dependency_job_key) = self.bfs_stack_cache.pop_front() {
+      for depender_job_key in self.job_graph.neighbors_directed(dependency_job_key, Incoming) {
+        if !matches!(self.job_key_to_job_status[&depender_job_key], JobStatus::Pending(_, _)) { continue; }
+        let job_status = self.job_key_to_job_status.get_mut(&depender_job_key).unwrap(); // Unwrap OK: job exists.
+        if let JobStatus::Pending(_, dependency_outputs) = std::mem::replace(job_status, JobStatus::Failed(JobError::DependencyFailed(dependency_job_key))) {
+          if self.dependency_output_cache.len() < self.dependency_output_cache.capacity() { // Inlined `reclaim_dependency_outputs` as `job_graph` is borrowed.
+            let mut dependency_outputs = dependency_outputs;
+            dependency_outputs.clear();
+            self.dependency_output_cache.push(dependency_outputs);
+          }
+        }
+        self.jobs_to_run.remove(&depender_job_key);
+        trace!("Failing job {:?} due to failed dependency {:?}", depender_job_key, dependency_job_key);
+        Self::send_outcome(&mut self.job_key_to_outcome_senders, depender_job_key, || JobOutcome::Failed(JobError::DependencyFailed(dependency_job_key)));
+        if self.to_queue.send(JobQueueMessage::JobFailed(depender_job_key, JobError::DependencyFailed(dependency_job_key))).is_err() { return false; }
+        self.pending_jobs -= 1;
+        self.failed_jobs += 1;
+        self.bfs_stack_cache.push_back(depender_job_key);
+      }
+    }
+    true
+  }
+
   #[inline]
   fn complete_job(&mut self, job_key: JK, output: O) -> bool {
     trace!("Completing job {:?}", job_key);
     *self.job_key_to_job_status.get_mut(&job_key).unwrap() = JobStatus::Completed(output.clone()); // Unwrap OK: job must exist when complete_job is called.
+    self.completed_jobs += 1;
+    Self::send_outcome(&mut self.job_key_to_outcome_senders, job_key, || JobOutcome::Completed(output.clone()));
     if self.to_queue.send(JobQueueMessage::JobCompleted(job_key, output)).is_err() { return false; }
     self.decrement_running_jobs_and_send_queue_empty_if_applicable()
   }
 
 
+  /// Sends the outcome created by `create_outcome` to each future waiting on job `job_key`, if any. Futures that were
+  /// dropped are ignored.
   #[inline]
-  fn decrement_pending_jobs_and_send_queue_empty_if_applicable(&mut self) -> bool {
-    if self.pending_jobs > 0 {
-      self.pending_jobs -= 1;
-      if self.pending_jobs == 0 && self.running_jobs == 0 {
-        return self.to_queue.send(JobQueueMessage::QueueEmpty).is_ok();
+  fn send_outcome(job_key_to_outcome_senders: &mut FxHashMap<JK, Vec<Sender<JobOutcome<JK, O>>>>, job_key: JK, create_outcome: impl Fn() -> JobOutcome<JK, O>) {
+    if job_key_to_outcome_senders.is_empty() { return; }
+    if let Some(outcome_senders) = job_key_to_outcome_senders.remove(&job_key) {
+      for outcome_sender in outcome_senders {
+        let _ = outcome_sender.send(create_outcome());
       }
-    } else {
-      panic!("Attempt to decrement pending jobs while pending jobs is 0");
     }
-    true
   }
 
+  /// Sends [`JobQueueMessage::QueueEmpty`] if there are no pending or running jobs, and no jobs waiting to be added.
   #[inline]
-  fn decrement_running_jobs_and_send_queue_empty_if_applicable(&mut self) -> bool {
-    if self.running_jobs > 0 {
-      self.running_jobs -= 1;
-      if self.running_jobs == 0 && self.pending_jobs == 0 {
-        return self.to_queue.send(JobQueueMessage::QueueEmpty).is_ok();
-      }
-    } else {
-      panic!("Attempt to decrement running jobs while running jobs is 0");
+  fn send_queue_empty_if_applicable(&mut self) -> bool {
+    if self.pending_jobs == 0 && self.running_jobs == 0 && self.jobs_to_add.is_empty() {
+      return self.to_queue.send(JobQueueMessage::QueueEmpty).is_ok();
     }
     true
   }
 
+  #[inline]
+  fn decrement_pending_jobs_and_send_queue_empty_if_applicable(&mut self) -> bool {
+    assert!(self.pending_jobs > 0, "Attempt to decrement pending jobs while pending jobs is 0");
+    self.pending_jobs -= 1;
+    self.send_queue_empty_if_applicable()
+  }
+
+  #[inline]
+  fn decrement_running_jobs_and_send_queue_empty_if_applicable(&mut self) -> bool {
+    assert!(self.running_jobs > 0, "Attempt to decrement running jobs while running jobs is 0");
+    self.running_jobs -= 1;
+    self.send_queue_empty_if_applicable()
+  }
+
 
   #[inline]
   fn reclaim_dependency_outputs(&mut self, mut dependency_outputs: Vec<(DK, O)>) {
@@ -348,20 +737,67 @@ impl<JK: JobKey, DK: DepKey, I: In, J: Job<JK, DK, I>, O: Out> ManagerThread<JK,
 
   #[inline]
   fn create_dependency_outputs(&mut self) -> Vec<(DK, O)> {
-    self.dependency_output_cache.pop().unwrap_or_else(|| Vec::new())
+    if let Some(dependency_outputs) = self.dependency_output_cache.pop() {
+      self.dependency_output_cache_hits += 1;
+      dependency_outputs
+    } else {
+      self.dependency_output_cache_misses += 1;
+      Vec::new()
+    }
+  }
+
+
+  #[inline]
+  fn create_metrics(&self) -> JobQueueMetrics {
+    JobQueueMetrics {
+      jobs_to_add: self.jobs_to_add.len(),
+      pending_jobs: self.pending_jobs as usize,
+      ready_jobs: self.jobs_to_run.len(),
+      running_jobs: self.running_jobs as usize,
+      completed_jobs: self.completed_jobs as usize,
+      failed_jobs: self.failed_jobs as usize,
+      job_graph_nodes: self.job_graph.node_count(),
+      job_graph_edges: self.job_graph.edge_count(),
+      dependency_output_cache_hits: self.dependency_output_cache_hits,
+      dependency_output_cache_misses: self.dependency_output_cache_misses,
+      output_cache_entries: self.output_cache.as_ref().map_or(0, |output_cache| output_cache.len()),
+      output_cache_bytes: self.output_cache.as_ref().map_or(0, |output_cache| output_cache.bytes()),
+      output_cache_byte_budget: self.output_cache.as_ref().map_or(0, |output_cache| output_cache.byte_budget()),
+      output_cache_hits: self.output_cache.as_ref().map_or(0, |output_cache| output_cache.hits()),
+      output_cache_misses: self.output_cache.as_ref().map_or(0, |output_cache| output_cache.misses()),
+      output_cache_evictions: self.output_cache.as_ref().map_or(0, |output_cache| output_cache.evictions()),
+      queue_wait: self.queue_wait_histogram,
+      run_time: self.run_time_histogram,
+    }
+  }
+
+  fn create_job_graph_snapshot(&self) -> JobGraphSnapshot<JK, DK> {
+    let nodes = self.job_graph.nodes().map(|job_key| {
+      let status = match &self.job_key_to_job_status[&job_key] {
+        JobStatus::Pending(_, _) if self.jobs_to_run.contains_key(&job_key) => JobGraphNodeStatus::Ready,
+        JobStatus::Pending(_, _) => JobGraphNodeStatus::Pending,
+        JobStatus::Running(_) => JobGraphNodeStatus::Running,
+        JobStatus::Completed(_) => JobGraphNodeStatus::Completed,
+        JobStatus::Failed(_) => JobGraphNodeStatus::Failed,
+      };
+      (job_key, status)
+    }).collect();
+    let edges = self.job_graph.all_edges().map(|(depender_job_key, dependee_job_key, dependency_key)| (depender_job_key, dependee_job_key, *dependency_key)).collect();
+    JobGraphSnapshot { nodes, edges }
   }
 }
 
 
 // Job status
 
-pub(super) enum JobStatus<DK, I, O> {
+pub(super) enum JobStatus<JK, DK, I, O> {
   Pending(I, Vec<(DK, O)>),
-  Running,
+  Running(CancellationToken),
   Completed(O),
+  Failed(JobError<JK>),
 }
 
-impl<DK, I, O: Out> JobStatus<DK, I, O> {
+impl<JK, DK, I, O: Out> JobStatus<JK, DK, I, O> {
   #[inline]
   fn clone_output_if_completed(&self) -> Option<O> {
     match self {
@@ -373,7 +809,7 @@ impl<DK, I, O: Out> JobStatus<DK, I, O> {
 
 // Selected receiver
 
-enum SelectedReceiver<JK, DK, J, O> {
-  FromQueue(FromQueue<JK, J>),
+enum SelectedReceiver<JK, DK, I, J, O> {
+  FromQueue(FromQueue<JK, I, J, O>),
   FromWorker(FromWorker<JK, DK, O>),
 }
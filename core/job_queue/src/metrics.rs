@@ -0,0 +1,129 @@
+use std::time::Duration;
+
+// Metrics
+
+/// Snapshot of job queue metrics, sent as [`JobQueueMessage::Metrics`](crate::JobQueueMessage::Metrics) in response to
+/// [`JobQueue::request_metrics`](crate::JobQueue::request_metrics).
+#[derive(Copy, Clone, Default, Debug)]
+pub struct JobQueueMetrics {
+  /// Number of jobs that have been added to the queue, but not yet to the job graph.
+  pub jobs_to_add: usize,
+  /// Number of jobs in the job graph that have not started running yet.
+  pub pending_jobs: usize,
+  /// Number of pending jobs whose dependencies are completed, waiting to be sent to a worker.
+  pub ready_jobs: usize,
+  /// Number of jobs that have been sent to a worker, but have not completed yet.
+  pub running_jobs: usize,
+  /// Number of completed jobs in the job graph.
+  pub completed_jobs: usize,
+  /// Number of failed jobs in the job graph.
+  pub failed_jobs: usize,
+
+  /// Number of jobs in the job graph.
+  pub job_graph_nodes: usize,
+  /// Number of dependencies between jobs in the job graph.
+  pub job_graph_edges: usize,
+
+  /// Number of times a dependency output buffer was reused from the cache.
+  pub dependency_output_cache_hits: u64,
+  /// Number of times a dependency output buffer had to be allocated because the cache was empty.
+  pub dependency_output_cache_misses: u64,
+
+  /// Number of outputs of removed jobs in the output cache.
+  pub output_cache_entries: usize,
+  /// Total size in bytes of outputs in the output cache.
+  pub output_cache_bytes: usize,
+  /// Maximum total size in bytes of outputs in the output cache, or 0 if there is no output cache.
+  pub output_cache_byte_budget: usize,
+  /// Number of added jobs that were completed with an output from the output cache.
+  pub output_cache_hits: u64,
+  /// Number of added jobs that were not in the output cache.
+  pub output_cache_misses: u64,
+  /// Number of outputs evicted from the output cache to stay within its byte budget.
+  pub output_cache_evictions: u64,
+
+  /// Time between a job becoming ready to run and being sent to a worker.
+  pub queue_wait: DurationHistogram,
+  /// Time a worker took to run a job.
+  pub run_time: DurationHistogram,
+}
+
+impl JobQueueMetrics {
+  /// Returns the ratio (0-1) of dependency output buffers that were reused from the cache, or 0 if none were requested.
+  #[inline]
+  pub fn dependency_output_cache_hit_rate(&self) -> f64 {
+    let total = self.dependency_output_cache_hits + self.dependency_output_cache_misses;
+    if total == 0 { 0.0 } else { self.dependency_output_cache_hits as f64 / total as f64 }
+  }
+
+  /// Returns the ratio (0-1) of added jobs that were completed from the output cache, or 0 if none were added.
+  #[inline]
+  pub fn output_cache_hit_rate(&self) -> f64 {
+    let total = self.output_cache_hits + self.output_cache_misses;
+    if total == 0 { 0.0 } else { self.output_cache_hits as f64 / total as f64 }
+  }
+}
+
+
+// Duration histogram
+
+/// Number of buckets in a [`DurationHistogram`].
+pub const DURATION_HISTOGRAM_BUCKET_COUNT: usize = 28;
+
+/// Histogram of durations with exponential buckets: bucket `i` counts durations shorter than `2^i` microseconds (and at
+/// least `2^(i-1)` microseconds), except for the last bucket, which counts all longer durations as well.
+#[derive(Copy, Clone, Default, Debug)]
+pub struct DurationHistogram {
+  buckets: [u64; DURATION_HISTOGRAM_BUCKET_COUNT],
+  count: u64,
+  total: Duration,
+  max: Duration,
+}
+
+impl DurationHistogram {
+  #[inline]
+  pub fn record(&mut self, duration: Duration) {
+    let micros = duration.as_micros();
+    let bucket = if micros == 0 { 0 } else { (u128::BITS - micros.leading_zeros()) as usize };
+    self.buckets[bucket.min(DURATION_HISTOGRAM_BUCKET_COUNT - 1)] += 1;
+    self.count += 1;
+    self.total += duration;
+    self.max = self.max.max(duration);
+  }
+
+  #[inline]
+  pub fn buckets(&self) -> &[u64; DURATION_HISTOGRAM_BUCKET_COUNT] { &self.buckets }
+
+  /// Gets the exclusive upper bound of the durations counted in bucket `index`, or `None` for the last bucket.
+  #[inline]
+  pub fn bucket_upper_bound(index: usize) -> Option<Duration> {
+    (index < DURATION_HISTOGRAM_BUCKET_COUNT - 1).then(|| Duration::from_micros(1 << index))
+  }
+
+  #[inline]
+  pub fn count(&self) -> u64 { self.count }
+  #[inline]
+  pub fn total(&self) -> Duration { self.total }
+  #[inline]
+  pub fn max(&self) -> Duration { self.max }
+
+  #[inline]
+  pub fn mean(&self) -> Duration {
+    if self.count == 0 { Duration::ZERO } else { Duration::from_secs_f64(self.total.as_secs_f64() / self.count as f64) }
+  }
+
+  /// Estimates the `quantile` (0-1) of recorded durations, as the upper bound of the bucket containing it (or the
+  /// maximum duration for the last bucket).
+  pub fn quantile(&self, quantile: f64) -> Duration {
+    if self.count == 0 { return Duration::ZERO; }
+    let target = ((quantile.clamp(0.0, 1.0) * self.count as f64).ceil() as u64).max(1);
+    let mut accumulated = 0;
+    for (index, count) in self.buckets.iter().enumerate() {
+      accumulated += count;
+      if accumulated >= target {
+        return Self::bucket_upper_bound(index).map_or(self.max, |bound| bound.min(self.max));
+      }
+    }
+    self.max
+  }
+}
@@ -0,0 +1,84 @@
+use std::hash::{BuildHasherDefault, Hash};
+
+use hashlink::LruCache;
+use rustc_hash::FxHasher;
+
+/// Gets the size in bytes of an output, or `None` if the output is not cacheable.
+pub(crate) type OutputSize<O> = Box<dyn Fn(&O) -> Option<usize> + Send>;
+
+/// Least recently used cache of outputs of removed completed jobs, bounded by a byte budget. Outputs are taken out of the
+/// cache when their job is added again, so that the job completes immediately instead of running again. Outputs without
+/// a size are not cacheable, and are never inserted.
+pub(crate) struct OutputCache<JK, O> {
+  entries: LruCache<JK, (O, usize), BuildHasherDefault<FxHasher>>,
+  byte_budget: usize,
+  bytes: usize,
+  output_size: OutputSize<O>,
+
+  hits: u64,
+  misses: u64,
+  evictions: u64,
+}
+
+impl<JK: Copy + Eq + Hash, O> OutputCache<JK, O> {
+  #[inline]
+  pub fn new(byte_budget: usize, output_size: OutputSize<O>) -> Self {
+    Self {
+      entries: LruCache::with_hasher(usize::MAX, BuildHasherDefault::default()),
+      byte_budget,
+      bytes: 0,
+      output_size,
+      hits: 0,
+      misses: 0,
+      evictions: 0,
+    }
+  }
+
+  #[inline]
+  pub fn len(&self) -> usize { self.entries.len() }
+  #[inline]
+  pub fn bytes(&self) -> usize { self.bytes }
+  #[inline]
+  pub fn byte_budget(&self) -> usize { self.byte_budget }
+  #[inline]
+  pub fn hits(&self) -> u64 { self.hits }
+  #[inline]
+  pub fn misses(&self) -> u64 { self.misses }
+  #[inline]
+  pub fn evictions(&self) -> u64 { self.evictions }
+
+  /// Inserts `output` of job `job_key` as the most recently used output, evicting least recently used outputs until the
+  /// cache fits its byte budget. Outputs that are not cacheable or larger than the byte budget are not inserted.
+  pub fn insert(&mut self, job_key: JK, output: O) {
+    let Some(size) = (self.output_size)(&output) else { return; };
+    if size > self.byte_budget { return; }
+    if let Some((_, prev_size)) = self.entries.insert(job_key, (output, size)) {
+      self.bytes -= prev_size;
+    }
+    self.bytes += size;
+    while self.bytes > self.byte_budget {
+      let Some((_, (_, size))) = self.entries.remove_lru() else { break; };
+      self.bytes -= size;
+      self.evictions += 1;
+    }
+  }
+
+  /// Takes the output of job `job_key` out of the cache, if it is cached.
+  #[inline]
+  pub fn take(&mut self, job_key: &JK) -> Option<O> {
+    if let Some((output, size)) = self.entries.remove(job_key) {
+      self.bytes -= size;
+      self.hits += 1;
+      Some(output)
+    } else {
+      self.misses += 1;
+      None
+    }
+  }
+
+  #[inline]
+  pub fn clear(&mut self) {
+    self.entries.clear();
+    self.bytes = 0;
+  }
+}
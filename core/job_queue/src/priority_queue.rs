@@ -0,0 +1,77 @@
+use std::collections::BTreeMap;
+use std::hash::Hash;
+
+use rustc_hash::FxHashMap;
+
+use crate::Priority;
+
+/// Queue of keys with associated values, popped in order of lowest priority first, and in insertion order for keys with
+/// equal priority. Supports removing keys and updating their priority.
+pub(crate) struct PriorityQueue<K, V> {
+  order: BTreeMap<(Priority, u64), K>,
+  entries: FxHashMap<K, Entry<V>>,
+  next_sequence: u64,
+}
+
+struct Entry<V> {
+  priority: Priority,
+  sequence: u64,
+  value: V,
+}
+
+impl<K: Copy + Eq + Hash, V> Default for PriorityQueue<K, V> {
+  #[inline]
+  fn default() -> Self {
+    Self { order: BTreeMap::default(), entries: FxHashMap::default(), next_sequence: 0 }
+  }
+}
+
+impl<K: Copy + Eq + Hash, V> PriorityQueue<K, V> {
+  #[inline]
+  pub fn len(&self) -> usize { self.entries.len() }
+
+  #[inline]
+  pub fn is_empty(&self) -> bool { self.entries.is_empty() }
+
+  #[inline]
+  pub fn contains_key(&self, key: &K) -> bool { self.entries.contains_key(key) }
+
+  /// Inserts `key` with `value` and `priority`. Does nothing and returns `false` if `key` is already in the queue.
+  #[inline]
+  pub fn insert(&mut self, key: K, value: V, priority: Priority) -> bool {
+    if self.entries.contains_key(&key) { return false; }
+    let sequence = self.next_sequence;
+    self.next_sequence += 1;
+    self.order.insert((priority, sequence), key);
+    self.entries.insert(key, Entry { priority, sequence, value });
+    true
+  }
+
+  #[inline]
+  pub fn remove(&mut self, key: &K) -> Option<V> {
+    let entry = self.entries.remove(key)?;
+    self.order.remove(&(entry.priority, entry.sequence));
+    Some(entry.value)
+  }
+
+  /// Removes and returns the key with the lowest priority, along with its value and priority.
+  #[inline]
+  pub fn pop_front(&mut self) -> Option<(K, V, Priority)> {
+    let (_, key) = self.order.pop_first()?;
+    let entry = self.entries.remove(&key).unwrap(); // Unwrap OK: `order` and `entries` are kept in sync.
+    Some((key, entry.value, entry.priority))
+  }
+
+  /// Updates the priority of `key` to `priority`, keeping its insertion order relative to keys with equal priority.
+  /// Returns `false` if `key` is not in the queue.
+  #[inline]
+  pub fn update_priority(&mut self, key: &K, priority: Priority) -> bool {
+    let Some(entry) = self.entries.get_mut(key) else { return false; };
+    if entry.priority != priority {
+      self.order.remove(&(entry.priority, entry.sequence));
+      entry.priority = priority;
+      self.order.insert((priority, entry.sequence), *key);
+    }
+    true
+  }
+}
@@ -1,12 +1,15 @@
+use std::any::Any;
+use std::panic::{self, AssertUnwindSafe};
 use std::thread;
 use std::thread::JoinHandle;
+use std::time::Instant;
 
 use flume::{Receiver, Sender};
 use tracing::trace;
 
-use crate::{DepKey, Handler, In, JobKey, Out};
+use crate::{CancellationToken, DepKey, Handler, In, JobError, JobKey, Out};
 
-pub(crate) type FromManager<JK, DK, I, O> = (JK, I, Vec<(DK, O)>);
+pub(crate) type FromManager<JK, DK, I, O> = (JK, I, Vec<(DK, O)>, CancellationToken);
 
 pub(super) struct WorkerThread<JK, DK, I, O, H> {
   from_manager: Receiver<FromManager<JK, DK, I, O>>,
@@ -40,10 +43,8 @@ impl<JK: JobKey, DK: DepKey, I: In, O: Out, H: Handler<JK, DK, I, O>> WorkerThre
     profiling::register_thread!();
     trace!("Started job queue worker thread {}", thread_index);
     loop {
-      if let Ok((job_key, input, dependency_outputs)) = self.from_manager.recv() {
-        trace!("Running job {:?}", job_key);
-        let output = (self.handler)(job_key, input, &dependency_outputs);
-        if self.to_manager.send((job_key, output, dependency_outputs)).is_err() {
+      if let Ok(message) = self.from_manager.recv() {
+        if !self.run_job(message) {
           break; // Manager has disconnected; stop this thread.
         }
       } else {
@@ -52,4 +53,33 @@ impl<JK: JobKey, DK: DepKey, I: In, O: Out, H: Handler<JK, DK, I, O>> WorkerThre
     }
     trace!("Stopped job queue worker thread {}", thread_index);
   }
+
+  /// Runs a job if one was sent by the manager, without blocking. Returns `None` if no job was run, `Some(false)` if
+  /// the manager has disconnected, and `Some(true)` otherwise.
+  #[inline]
+  pub(super) fn try_run_job(&mut self) -> Option<bool> {
+    let message = self.from_manager.try_recv().ok()?;
+    Some(self.run_job(message))
+  }
+
+  #[inline]
+  fn run_job(&mut self, (job_key, input, dependency_outputs, cancellation_token): FromManager<JK, DK, I, O>) -> bool {
+    trace!("Running job {:?}", job_key);
+    let start = Instant::now();
+    // Catch panics so that a job with a panicking handler fails without stopping this thread.
+    let output = panic::catch_unwind(AssertUnwindSafe(|| (self.handler)(job_key, input, &dependency_outputs, &cancellation_token)))
+      .map_err(|payload| JobError::Panicked(panic_message(payload)));
+    let run_time = start.elapsed();
+    self.to_manager.send((job_key, output, dependency_outputs, cancellation_token, run_time)).is_ok()
+  }
+}
+
+fn panic_message(payload: Box<dyn Any + Send>) -> String {
+  if let Some(message) = payload.downcast_ref::<&str>() {
+    message.to_string()
+  } else if let Ok(message) = payload.downcast::<String>() {
+    *message
+  } else {
+    "Box<dyn Any>".to_string()
+  }
 }
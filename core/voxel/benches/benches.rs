@@ -1,6 +1,8 @@
 use criterion::{BatchSize, black_box, Criterion, criterion_group, criterion_main};
 use ultraviolet::{Isometry3, UVec3, Vec3};
 
+use job_queue::CancellationToken;
+
 use voxel::chunk::mesh::ChunkMesh;
 use voxel::chunk::size::{ChunkSize, ChunkSize16, ChunkSize32};
 use voxel::lod::aabb::Aabb;
@@ -15,7 +17,7 @@ use voxel::transvoxel::Transvoxel;
 use voxel::volume::{Sphere, SphereSettings, Volume};
 
 pub fn sphere_benchmark(c: &mut Criterion) {
-  let sphere = Sphere::new(SphereSettings { radius: 16.0 });
+  let sphere = Sphere::new(SphereSettings { radius: 16.0, ..SphereSettings::default() });
   let start = UVec3::new(0, 0, 0);
   let step = 1;
   c.bench_function("Volume-Sphere-Sample-16", |b| b.iter(|| {
@@ -28,7 +30,7 @@ pub fn sphere_benchmark(c: &mut Criterion) {
     }
   }));
   c.bench_function("Volume-Sphere-Sample-Chunk-16", |b| b.iter(|| {
-    black_box(sphere.sample_chunk::<ChunkSize16>(start, step));
+    black_box(sphere.sample_chunk::<ChunkSize16>(start, step, &CancellationToken::default()));
   }));
 }
 
@@ -36,11 +38,11 @@ type C16 = ChunkSize16;
 type C32 = ChunkSize32;
 
 pub fn marching_cubes_benchmark(c: &mut Criterion) {
-  let sphere = Sphere::new(SphereSettings { radius: 16.0 });
+  let sphere = Sphere::new(SphereSettings { radius: 16.0, ..SphereSettings::default() });
   let marching_cubes = MarchingCubes::<C16>::new();
   let start = UVec3::new(0, 0, 0);
   let step = 1;
-  let chunk_samples = sphere.sample_chunk(start, step);
+  let chunk_samples = sphere.sample_chunk(start, step, &CancellationToken::default());
   c.bench_function("MarchingCubes-Sphere-16", |b| b.iter_batched(
     || preallocate_chunk_vertices::<C16>(),
     |mut chunk_mesh| marching_cubes.extract_chunk(start, step, &chunk_samples, &mut chunk_mesh),
@@ -50,7 +52,7 @@ pub fn marching_cubes_benchmark(c: &mut Criterion) {
 
 pub fn transvoxel_benchmark(c: &mut Criterion) {
   let root_size = 64;
-  let sphere = Sphere::new(SphereSettings { radius: root_size as f32 });
+  let sphere = Sphere::new(SphereSettings { radius: root_size as f32, ..SphereSettings::default() });
   let transvoxel = Transvoxel::<C16>::new();
 
   let aabb = Aabb::root();
@@ -63,10 +65,10 @@ pub fn transvoxel_benchmark(c: &mut Criterion) {
   let hires_step = 1;
   let hires_chunk_mins = side.subdivided_face_of_side_minimums(lores_aabb.with_size(root_size));
   let hires_chunk_samples = [
-    sphere.sample_chunk(hires_chunk_mins[0], hires_step),
-    sphere.sample_chunk(hires_chunk_mins[1], hires_step),
-    sphere.sample_chunk(hires_chunk_mins[2], hires_step),
-    sphere.sample_chunk(hires_chunk_mins[3], hires_step),
+    sphere.sample_chunk(hires_chunk_mins[0], hires_step, &CancellationToken::default()),
+    sphere.sample_chunk(hires_chunk_mins[1], hires_step, &CancellationToken::default()),
+    sphere.sample_chunk(hires_chunk_mins[2], hires_step, &CancellationToken::default()),
+    sphere.sample_chunk(hires_chunk_mins[3], hires_step, &CancellationToken::default()),
   ];
   c.bench_function("Transvoxel-LoZ-Sphere-64", |b| b.iter_batched(
     || preallocate_chunk_vertices::<C16>(),
@@ -79,9 +81,9 @@ pub fn surface_nets_benchmark(c: &mut Criterion) {
   let step = 1;
   let start = UVec3::new(0, 0, 0);
   {
-    let sphere = Sphere::new(SphereSettings { radius: 16.0 });
+    let sphere = Sphere::new(SphereSettings { radius: 16.0, ..SphereSettings::default() });
     let surface_nets = SurfaceNets::<C16>::new();
-    let chunk_samples = sphere.sample_chunk(start, step);
+    let chunk_samples = sphere.sample_chunk(start, step, &CancellationToken::default());
     c.bench_function("SurfaceNets-Sphere-16", |b| b.iter_batched(
       || preallocate_chunk_vertices::<C16>(),
       |mut chunk_mesh| surface_nets.extract_chunk_from_maybe_compressed_samples(start, step, &chunk_samples, &mut chunk_mesh),
@@ -89,9 +91,9 @@ pub fn surface_nets_benchmark(c: &mut Criterion) {
     ));
   }
   {
-    let sphere = Sphere::new(SphereSettings { radius: 32.0 });
+    let sphere = Sphere::new(SphereSettings { radius: 32.0, ..SphereSettings::default() });
     let surface_nets = SurfaceNets::<C32>::new();
-    let chunk_samples = sphere.sample_chunk(start, step);
+    let chunk_samples = sphere.sample_chunk(start, step, &CancellationToken::default());
     c.bench_function("SurfaceNets-Sphere-32", |b| b.iter_batched(
       || preallocate_chunk_vertices::<C32>(),
       |mut chunk_mesh| surface_nets.extract_chunk_from_maybe_compressed_samples(start, step, &chunk_samples, &mut chunk_mesh),
@@ -101,23 +103,23 @@ pub fn surface_nets_benchmark(c: &mut Criterion) {
 }
 
 pub fn surface_nets_borders_benchmark(c: &mut Criterion) {
-  let sphere = Sphere::new(SphereSettings { radius: 32.0 });
+  let sphere = Sphere::new(SphereSettings { radius: 32.0, ..SphereSettings::default() });
   let surface_nets_lod = SurfaceNetsLod::<C16>::new();
   let step = 1;
   let min = UVec3::new(0, 0, 0);
-  let chunk_samples = sphere.sample_chunk(min, step);
+  let chunk_samples = sphere.sample_chunk(min, step, &CancellationToken::default());
   let min_x = UVec3::new(16, 0, 0);
-  let chunk_samples_x = sphere.sample_chunk(min_x, step);
+  let chunk_samples_x = sphere.sample_chunk(min_x, step, &CancellationToken::default());
   let min_y = UVec3::new(0, 16, 0);
-  let chunk_samples_y = sphere.sample_chunk(min_y, step);
+  let chunk_samples_y = sphere.sample_chunk(min_y, step, &CancellationToken::default());
   let min_z = UVec3::new(0, 0, 16);
-  let chunk_samples_z = sphere.sample_chunk(min_z, step);
+  let chunk_samples_z = sphere.sample_chunk(min_z, step, &CancellationToken::default());
   let min_xy = UVec3::new(16, 16, 0);
-  let chunk_samples_xy = sphere.sample_chunk(min_xy, step);
+  let chunk_samples_xy = sphere.sample_chunk(min_xy, step, &CancellationToken::default());
   let min_yz = UVec3::new(0, 16, 16);
-  let chunk_samples_yz = sphere.sample_chunk(min_yz, step);
+  let chunk_samples_yz = sphere.sample_chunk(min_yz, step, &CancellationToken::default());
   let min_xz = UVec3::new(16, 0, 16);
-  let chunk_samples_xz = sphere.sample_chunk(min_xz, step);
+  let chunk_samples_xz = sphere.sample_chunk(min_xz, step, &CancellationToken::default());
   c.bench_function("SurfaceNets-Border-X-Sphere-16", |b| b.iter_batched(
     || preallocate_chunk_vertices::<C16>(),
     |mut chunk_mesh| surface_nets_lod.extract_border_x(step, min, &chunk_samples, min_x, &chunk_samples_x, &mut chunk_mesh),
@@ -158,7 +160,7 @@ fn preallocate_chunk_vertices<C: ChunkSize>() -> ChunkMesh {
 pub fn octree_benchmark(c: &mut Criterion) {
   let total_size = 4096;
   let transform = Isometry3::identity();
-  let volume = Sphere::new(SphereSettings { radius: total_size as f32 });
+  let volume = Sphere::new(SphereSettings { radius: total_size as f32, ..SphereSettings::default() });
   let extractor = TransvoxelExtractor::default();
 
   let mut group = c.benchmark_group("Octree-Sphere-Transvoxel");
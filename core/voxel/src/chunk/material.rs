@@ -0,0 +1,82 @@
+use std::mem::size_of;
+
+use crate::chunk::array::Index;
+use crate::chunk::index::VoxelIndex;
+
+/// Identifier of the material of a voxel, e.g., rock, sand, or snow. Renderers use it to index into their material
+/// palette or texture layers.
+pub type MaterialId = u8;
+
+/// Materials of all voxels in a chunk.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub enum ChunkMaterials {
+  /// All voxels in the chunk have the contained material.
+  Uniform(MaterialId),
+  /// Voxels in the chunk have mixed materials, indexed by voxel index.
+  Mixed(Box<[MaterialId]>),
+}
+
+impl ChunkMaterials {
+  /// Creates chunk materials from `materials` indexed by voxel index, compressing them if all materials are equal.
+  pub fn from_materials(materials: Vec<MaterialId>) -> Self {
+    match materials.first() {
+      Some(first) if materials.iter().any(|material| material != first) => Self::Mixed(materials.into_boxed_slice()),
+      Some(first) => Self::Uniform(*first),
+      None => Self::default(),
+    }
+  }
+
+  #[inline]
+  pub fn material_index(&self, voxel_index: VoxelIndex) -> MaterialId {
+    match self {
+      Self::Uniform(material) => *material,
+      Self::Mixed(materials) => materials[voxel_index.into_usize()],
+    }
+  }
+
+  /// Gets the number of bytes allocated on the heap for these materials.
+  #[inline]
+  pub fn heap_size_in_bytes(&self) -> usize {
+    match self {
+      Self::Uniform(_) => 0,
+      Self::Mixed(materials) => materials.len() * size_of::<MaterialId>(),
+    }
+  }
+
+  /// Appends a binary encoding of these materials to `bytes`.
+  pub fn encode(&self, bytes: &mut Vec<u8>) {
+    match self {
+      Self::Uniform(material) => {
+        bytes.push(0);
+        bytes.push(*material);
+      }
+      Self::Mixed(materials) => {
+        bytes.push(1);
+        bytes.extend_from_slice(materials);
+      }
+    }
+  }
+
+  /// Decodes `len` materials encoded with [`encode`](Self::encode) from the start of `bytes`, returning the materials
+  /// and the remaining bytes.
+  pub fn decode(bytes: &[u8], len: usize) -> Option<(Self, &[u8])> {
+    let (tag, bytes) = bytes.split_first()?;
+    match tag {
+      0 => {
+        let (material, bytes) = bytes.split_first()?;
+        Some((Self::Uniform(*material), bytes))
+      }
+      1 if bytes.len() >= len => {
+        let (materials, bytes) = bytes.split_at(len);
+        Some((Self::Mixed(materials.into()), bytes))
+      }
+      _ => None,
+    }
+  }
+}
+
+impl Default for ChunkMaterials {
+  #[inline]
+  fn default() -> Self { Self::Uniform(0) }
+}
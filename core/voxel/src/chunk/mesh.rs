@@ -5,6 +5,8 @@ use wgpu::{BufferAddress, VertexAttribute, VertexBufferLayout, VertexStepMode};
 
 use bytemuck::{Pod, Zeroable};
 
+use crate::chunk::material::MaterialId;
+
 // Mesh
 
 #[derive(Clone, Default, Debug)]
@@ -28,11 +30,22 @@ impl ChunkMesh {
   #[inline]
   pub fn is_empty(&self) -> bool { self.vertices.is_empty() && self.indices.is_empty() }
 
+  /// Gets the number of bytes allocated on the heap for the vertices and indices of this mesh.
+  #[inline]
+  pub fn heap_size_in_bytes(&self) -> usize {
+    self.vertices.capacity() * size_of::<Vertex>() + self.indices.capacity() * size_of::<u16>()
+  }
+
   #[inline]
   pub fn vertices(&self) -> &[Vertex] {
     &self.vertices
   }
 
+  #[inline]
+  pub fn vertices_mut(&mut self) -> &mut [Vertex] {
+    &mut self.vertices
+  }
+
   #[inline]
   pub fn indices(&self) -> &[u16] {
     &self.indices
@@ -70,12 +83,25 @@ impl ChunkMesh {
 #[derive(Copy, Clone, Debug, Pod, Zeroable)]
 pub struct Vertex {
   pub position: Vec3,
+  /// Material of the vertex, as a `u32` to keep the vertex free of padding.
+  pub material: u32,
+  /// Ambient occlusion of the vertex, from `0.0` (not occluded) to `1.0` (fully occluded).
+  pub occlusion: f32,
+  /// Position of the vertex at the parent LOD, which the vertex is morphed towards as its parent LOD is approached.
+  pub morph_position: Vec3,
+  /// Size of the LOD chunk of the vertex, which determines the distance at which it is morphed. `0.0` disables
+  /// morphing.
+  pub lod_size: f32,
 }
 
 impl Vertex {
   pub fn buffer_layout() -> VertexBufferLayout<'static> {
     const ATTRIBUTES: &[VertexAttribute] = &wgpu::vertex_attr_array![
       0 => Float32x3,
+      1 => Uint32,
+      2 => Float32,
+      3 => Float32x3,
+      4 => Float32,
     ];
     VertexBufferLayout {
       array_stride: size_of::<Vertex>() as BufferAddress,
@@ -85,7 +111,7 @@ impl Vertex {
   }
 
   #[inline]
-  pub fn new(position: Vec3) -> Self {
-    Self { position }
+  pub fn new(position: Vec3, material: MaterialId) -> Self {
+    Self { position, material: material as u32, occlusion: 0.0, morph_position: position, lod_size: 0.0 }
   }
 }
@@ -3,7 +3,10 @@ pub mod array;
 pub mod shape;
 pub mod index;
 pub mod sample;
+pub mod material;
 pub mod mesh;
+pub mod occlusion;
+pub mod morph;
 
 // Value trait
 
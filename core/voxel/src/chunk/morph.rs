@@ -0,0 +1,89 @@
+use std::marker::PhantomData;
+
+use ultraviolet::{UVec3, Vec3};
+
+use crate::chunk::mesh::ChunkMesh;
+use crate::chunk::sample::ChunkSamples;
+use crate::chunk::size::ChunkSize;
+
+// Geomorph
+
+/// Computes the position of each vertex at the parent LOD, for geomorphing between LOD levels. The parent LOD samples
+/// the volume at every other sample of the chunk, so its surface is approximated by the isosurface of the trilinear
+/// interpolation of those samples, which each vertex is projected onto with a Newton step.
+///
+/// Vertices on a face of the chunk are only moved along that face, using only the samples on that face, so that
+/// vertices shared with neighboring chunks of the same LOD are morphed to the same position.
+#[derive(Copy, Clone, Default)]
+pub struct Geomorph<C: ChunkSize> {
+  _phantom: PhantomData<C>,
+}
+
+impl<C: ChunkSize> Geomorph<C> {
+  #[inline]
+  pub fn new() -> Self { Self::default() }
+
+  /// Sets the morph position and LOD size of all vertices in `chunk_mesh`, which was extracted from `chunk_samples`
+  /// with minimum point `min` and `step`.
+  #[profiling::function]
+  pub fn apply<CS: ChunkSamples<C>>(&self, min: UVec3, step: u32, chunk_samples: &CS, chunk_mesh: &mut ChunkMesh) {
+    let min = Vec3::from(min);
+    let step = step as f32;
+    let lod_size = step * C::CELLS_IN_CHUNK_ROW_F32;
+    for vertex in chunk_mesh.vertices_mut() {
+      let position = (vertex.position - min) / step;
+      vertex.morph_position = min + morph_position::<C, CS>(position, chunk_samples) * step;
+      vertex.lod_size = lod_size;
+    }
+  }
+}
+
+/// Maximum distance in cells that a vertex is moved, which is the size of a cell at the parent LOD.
+const MAX_MORPH_DISTANCE: f32 = 2.0;
+
+#[inline]
+fn morph_position<C: ChunkSize, CS: ChunkSamples<C>>(position: Vec3, chunk_samples: &CS) -> Vec3 {
+  let mut gradient = parent_gradient::<C, CS>(position, chunk_samples);
+  // Do not move vertices on a face of the chunk away from that face.
+  for axis in 0..3 {
+    if position[axis] <= 0.0 || position[axis] >= C::CELLS_IN_CHUNK_ROW_F32 {
+      gradient[axis] = 0.0;
+    }
+  }
+  let gradient_mag_sq = gradient.mag_sq();
+  if gradient_mag_sq <= f32::EPSILON {
+    return position;
+  }
+  let offset = gradient * (-parent_trilinear_sample::<C, CS>(position, chunk_samples) / gradient_mag_sq);
+  let offset_mag = offset.mag();
+  let offset = if offset_mag > MAX_MORPH_DISTANCE { offset * (MAX_MORPH_DISTANCE / offset_mag) } else { offset };
+  position + offset
+}
+
+#[inline]
+fn parent_gradient<C: ChunkSize, CS: ChunkSamples<C>>(position: Vec3, chunk_samples: &CS) -> Vec3 {
+  const H: f32 = 1.0;
+  let sample = |offset: Vec3| parent_trilinear_sample::<C, CS>(position + offset, chunk_samples);
+  Vec3::new(
+    sample(Vec3::new(H, 0.0, 0.0)) - sample(Vec3::new(-H, 0.0, 0.0)),
+    sample(Vec3::new(0.0, H, 0.0)) - sample(Vec3::new(0.0, -H, 0.0)),
+    sample(Vec3::new(0.0, 0.0, H)) - sample(Vec3::new(0.0, 0.0, -H)),
+  ) / (2.0 * H)
+}
+
+/// Trilinearly interpolates the samples of the parent LOD (every other sample of the chunk) at `position` in cells.
+#[inline]
+fn parent_trilinear_sample<C: ChunkSize, CS: ChunkSamples<C>>(position: Vec3, chunk_samples: &CS) -> f32 {
+  let max = C::CELLS_IN_CHUNK_ROW_F32;
+  let position = position.clamped(Vec3::zero(), Vec3::broadcast(max)) / 2.0;
+  let max_lo = C::CELLS_IN_CHUNK_ROW_DIV_TWO - 1;
+  let lo = UVec3::new(position.x as u32, position.y as u32, position.z as u32).min_by_component(UVec3::broadcast(max_lo));
+  let t = position - Vec3::from(lo);
+  let sample = |x: u32, y: u32, z: u32| chunk_samples.sample((lo + UVec3::new(x, y, z)) * 2);
+  let lerp = |a: f32, b: f32, t: f32| a + (b - a) * t;
+  let x00 = lerp(sample(0, 0, 0), sample(1, 0, 0), t.x);
+  let x10 = lerp(sample(0, 1, 0), sample(1, 1, 0), t.x);
+  let x01 = lerp(sample(0, 0, 1), sample(1, 0, 1), t.x);
+  let x11 = lerp(sample(0, 1, 1), sample(1, 1, 1), t.x);
+  lerp(lerp(x00, x10, t.y), lerp(x01, x11, t.y), t.z)
+}
@@ -0,0 +1,111 @@
+use ultraviolet::Vec3;
+
+use crate::chunk::mesh::ChunkMesh;
+
+// Settings
+
+#[derive(Copy, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct AmbientOcclusionSettings {
+  pub enabled: bool,
+  /// Number of directions sampled in the hemisphere around the normal of each vertex.
+  pub quality: u8,
+  /// Distance in cells that directions are sampled up to.
+  pub radius: f32,
+}
+
+impl Default for AmbientOcclusionSettings {
+  #[inline]
+  fn default() -> Self {
+    Self {
+      enabled: false,
+      quality: 8,
+      radius: 2.0,
+    }
+  }
+}
+
+
+// Ambient occlusion
+
+/// Number of samples taken along each direction.
+const STEPS_PER_DIRECTION: u32 = 4;
+
+/// Estimates per-vertex ambient occlusion by sampling the volume along directions in the hemisphere around the normal
+/// of each vertex and checking for solid (positive) samples. The volume is sampled instead of the samples of the chunk,
+/// so that occlusion is continuous across chunk borders.
+#[derive(Clone)]
+pub struct AmbientOcclusion {
+  settings: AmbientOcclusionSettings,
+  directions: Option<Vec<Vec3>>,
+}
+
+impl AmbientOcclusion {
+  #[inline]
+  pub fn new(settings: AmbientOcclusionSettings) -> Self {
+    let directions = (settings.enabled && settings.quality > 0).then(|| hemisphere_directions(settings.quality as u32));
+    Self { settings, directions }
+  }
+
+  #[inline]
+  pub fn is_enabled(&self) -> bool { self.directions.is_some() }
+
+  /// Sets the occlusion of all vertices in `chunk_mesh`, which was extracted from a chunk with `step`, where `sample`
+  /// samples the volume at a position in the space of the vertices. Does nothing if ambient occlusion is disabled.
+  #[profiling::function]
+  pub fn apply(&self, step: u32, sample: impl Fn(Vec3) -> f32, chunk_mesh: &mut ChunkMesh) {
+    let Some(directions) = &self.directions else { return; };
+    let step = step as f32;
+    for vertex in chunk_mesh.vertices_mut() {
+      vertex.occlusion = self.occlusion(vertex.position, step, directions, &sample);
+    }
+  }
+
+  fn occlusion(&self, position: Vec3, step: f32, directions: &[Vec3], sample: impl Fn(Vec3) -> f32) -> f32 {
+    // Samples are positive inside the volume, so the gradient points inwards.
+    let normal = -gradient(position, step, &sample);
+    if normal.mag_sq() <= f32::EPSILON {
+      return 0.0;
+    }
+    let normal = normal.normalized();
+    let tangent = if normal.x.abs() > 0.9 { Vec3::unit_y() } else { Vec3::unit_x() }.cross(normal).normalized();
+    let bitangent = normal.cross(tangent);
+    let mut occlusion = 0.0;
+    for direction in directions {
+      let direction = tangent * direction.x + bitangent * direction.y + normal * direction.z;
+      for i in 1..=STEPS_PER_DIRECTION {
+        let distance = self.settings.radius * step * i as f32 / STEPS_PER_DIRECTION as f32;
+        if sample(position + direction * distance) > 0.0 {
+          // Nearby occluders occlude more than far away ones.
+          occlusion += 1.0 - (i - 1) as f32 / STEPS_PER_DIRECTION as f32;
+          break;
+        }
+      }
+    }
+    occlusion / directions.len() as f32
+  }
+}
+
+/// Returns `count` directions evenly distributed over the hemisphere around the positive Z axis, using a Fibonacci
+/// lattice.
+fn hemisphere_directions(count: u32) -> Vec<Vec3> {
+  let golden_angle = std::f32::consts::PI * (3.0 - 5.0f32.sqrt());
+  (0..count).map(|i| {
+    let z = 1.0 - (i as f32 + 0.5) / count as f32;
+    let radius = (1.0 - z * z).sqrt();
+    let (sin, cos) = (i as f32 * golden_angle).sin_cos();
+    Vec3::new(radius * cos, radius * sin, z)
+  }).collect()
+}
+
+#[inline]
+fn gradient(position: Vec3, step: f32, sample: impl Fn(Vec3) -> f32) -> Vec3 {
+  let h = 0.5 * step;
+  let sample = |offset: Vec3| sample(position + offset);
+  Vec3::new(
+    sample(Vec3::new(h, 0.0, 0.0)) - sample(Vec3::new(-h, 0.0, 0.0)),
+    sample(Vec3::new(0.0, h, 0.0)) - sample(Vec3::new(0.0, -h, 0.0)),
+    sample(Vec3::new(0.0, 0.0, h)) - sample(Vec3::new(0.0, 0.0, -h)),
+  )
+}
@@ -1,9 +1,11 @@
 use std::marker::PhantomData;
+use std::mem::size_of;
 
 use ultraviolet::UVec3;
 
-use crate::chunk::array::{Array, ArrayIndex, ArraySlice, ArraySliceMut};
+use crate::chunk::array::{Array, ArrayIndex, ArraySlice, ArraySliceMut, Index};
 use crate::chunk::index::VoxelIndex;
+use crate::chunk::material::{ChunkMaterials, MaterialId};
 use crate::chunk::shape::Shape;
 use crate::chunk::size::ChunkSize;
 
@@ -16,6 +18,15 @@ pub trait ChunkSamples<C: ChunkSize> {
     let voxel_index = C::VoxelChunkShape::index_from_pos(position);
     self.sample_index(voxel_index)
   }
+
+  /// Returns the material of the voxel at `voxel_index`. Samples without materials return material `0`.
+  #[inline]
+  fn material_index(&self, _voxel_index: VoxelIndex) -> MaterialId { 0 }
+  #[inline]
+  fn material(&self, position: UVec3) -> MaterialId {
+    let voxel_index = C::VoxelChunkShape::index_from_pos(position);
+    self.material_index(voxel_index)
+  }
 }
 
 pub trait ChunkSamplesMut<C: ChunkSize>: ChunkSamples<C> {
@@ -47,10 +58,26 @@ pub enum MaybeCompressedChunkSamples<CS> {
   Positive,
   /// All sampled values in the chunk are negative (i.e., `f32::is_sign_negative() == true`).
   Negative,
+  /// All sampled values in the chunk are exactly the contained value.
+  Uniform(f32),
+  /// Sampled values in the chunk are mixed, and quantized to 8 bits.
+  Quantized8(QuantizedChunkSamples<i8>),
+  /// Sampled values in the chunk are mixed, and quantized to 16 bits.
+  Quantized16(QuantizedChunkSamples<i16>),
   /// Sampled values in the chunk are mixed.
   Mixed(CS),
 }
 
+impl<CS> MaybeCompressedChunkSamples<CS> {
+  /// Returns `true` if sampled values in the chunk are mixed (at full precision or quantized), meaning that the chunk
+  /// may contain a surface. Returns `false` if all sampled values have the same sign.
+  #[inline]
+  pub fn is_mixed(&self) -> bool {
+    use MaybeCompressedChunkSamples::*;
+    matches!(self, Quantized8(_) | Quantized16(_) | Mixed(_))
+  }
+}
+
 impl<C: ChunkSize, CS: ChunkSamples<C>> ChunkSamples<C> for MaybeCompressedChunkSamples<CS> {
   #[inline]
   fn sample_index(&self, voxel_index: VoxelIndex) -> f32 {
@@ -59,6 +86,9 @@ impl<C: ChunkSize, CS: ChunkSamples<C>> ChunkSamples<C> for MaybeCompressedChunk
       Zero => 0.0,
       Positive => 1.0,
       Negative => -1.0,
+      Uniform(value) => *value,
+      Quantized8(inner) => ChunkSamples::<C>::sample_index(inner, voxel_index),
+      Quantized16(inner) => ChunkSamples::<C>::sample_index(inner, voxel_index),
       Mixed(inner) => inner.sample_index(voxel_index)
     }
   }
@@ -69,12 +99,237 @@ impl<C: ChunkSize, CS: ChunkSamples<C>> ChunkSamples<C> for MaybeCompressedChunk
       Zero => 0.0,
       Positive => 1.0,
       Negative => -1.0,
+      Uniform(value) => *value,
+      Quantized8(inner) => ChunkSamples::<C>::sample(inner, position),
+      Quantized16(inner) => ChunkSamples::<C>::sample(inner, position),
       Mixed(inner) => inner.sample(position)
     }
   }
+  #[inline]
+  fn material_index(&self, voxel_index: VoxelIndex) -> MaterialId {
+    use MaybeCompressedChunkSamples::*;
+    match self {
+      Quantized8(inner) => ChunkSamples::<C>::material_index(inner, voxel_index),
+      Quantized16(inner) => ChunkSamples::<C>::material_index(inner, voxel_index),
+      Mixed(inner) => inner.material_index(voxel_index),
+      _ => 0, // All samples have the same sign: no surface, so materials are never needed.
+    }
+  }
+}
+
+/// Maybe compressed chunk sample array. Full precision samples are boxed so that compressed chunks do not take up the
+/// size of an entire chunk sample array.
+pub type MaybeCompressedChunkSampleArray<C> = MaybeCompressedChunkSamples<Box<ChunkSampleArray<C>>>;
+
+impl<C: ChunkSize> MaybeCompressedChunkSampleArray<C> {
+  /// Gets the number of bytes used by these samples, including heap allocations.
+  #[inline]
+  pub fn size_in_bytes(&self) -> usize {
+    use MaybeCompressedChunkSamples::*;
+    let heap_size_in_bytes = match self {
+      Quantized8(inner) => inner.heap_size_in_bytes(),
+      Quantized16(inner) => inner.heap_size_in_bytes(),
+      Mixed(array) => size_of::<ChunkSampleArray<C>>() + array.materials.heap_size_in_bytes(),
+      _ => 0,
+    };
+    size_of::<Self>() + heap_size_in_bytes
+  }
+
+  /// Quantizes full precision mixed samples with `quantization`, leaving other kinds of samples untouched.
+  #[profiling::function]
+  pub fn quantize(self, quantization: ChunkSampleQuantization) -> Self {
+    match (self, quantization) {
+      (MaybeCompressedChunkSamples::Mixed(array), ChunkSampleQuantization::Bits8) =>
+        MaybeCompressedChunkSamples::Quantized8(QuantizedChunkSamples::from_samples(&array.array[..], array.materials)),
+      (MaybeCompressedChunkSamples::Mixed(array), ChunkSampleQuantization::Bits16) =>
+        MaybeCompressedChunkSamples::Quantized16(QuantizedChunkSamples::from_samples(&array.array[..], array.materials)),
+      (samples, _) => samples,
+    }
+  }
+
+  /// Appends a little-endian binary encoding of these samples to `bytes`.
+  pub fn encode(&self, bytes: &mut Vec<u8>) {
+    use MaybeCompressedChunkSamples::*;
+    match self {
+      Zero => bytes.push(0),
+      Positive => bytes.push(1),
+      Negative => bytes.push(2),
+      Uniform(value) => {
+        bytes.push(3);
+        bytes.extend_from_slice(&value.to_le_bytes());
+      }
+      Quantized8(quantized) => {
+        bytes.push(4);
+        quantized.encode(bytes);
+      }
+      Quantized16(quantized) => {
+        bytes.push(5);
+        quantized.encode(bytes);
+      }
+      Mixed(array) => {
+        bytes.push(6);
+        for sample in array.array[..].iter() {
+          bytes.extend_from_slice(&sample.to_le_bytes());
+        }
+        array.materials.encode(bytes);
+      }
+    }
+  }
+
+  /// Decodes samples encoded with [`encode`](Self::encode). Returns `None` if `bytes` is not a valid encoding of
+  /// samples for chunk size `C`.
+  pub fn decode(bytes: &[u8]) -> Option<Self> {
+    use MaybeCompressedChunkSamples::*;
+    let (tag, bytes) = bytes.split_first()?;
+    let samples = match tag {
+      0 if bytes.is_empty() => Zero,
+      1 if bytes.is_empty() => Positive,
+      2 if bytes.is_empty() => Negative,
+      3 => Uniform(f32::from_le_bytes(bytes.try_into().ok()?)),
+      4 => Quantized8(QuantizedChunkSamples::decode(bytes, C::VOXELS_IN_CHUNK_USIZE)?),
+      5 => Quantized16(QuantizedChunkSamples::decode(bytes, C::VOXELS_IN_CHUNK_USIZE)?),
+      6 => {
+        let mut array = C::VoxelChunkArray::new(0.0);
+        if bytes.len() < C::VOXELS_IN_CHUNK_USIZE * 4 { return None; }
+        let (samples, bytes) = bytes.split_at(C::VOXELS_IN_CHUNK_USIZE * 4);
+        for (sample, chunk) in array[..].iter_mut().zip(samples.chunks_exact(4)) {
+          *sample = f32::from_le_bytes(chunk.try_into().unwrap()); // Unwrap OK: chunks are exactly 4 bytes.
+        }
+        let (materials, bytes) = ChunkMaterials::decode(bytes, C::VOXELS_IN_CHUNK_USIZE)?;
+        if !bytes.is_empty() { return None; }
+        Mixed(Box::new(ChunkSampleArray::with_materials(array, materials)))
+      }
+      _ => return None,
+    };
+    Some(samples)
+  }
+}
+
+impl<C: ChunkSize, CS: ChunkSamples<C> + ?Sized> ChunkSamples<C> for Box<CS> {
+  #[inline]
+  fn sample_index(&self, voxel_index: VoxelIndex) -> f32 { (**self).sample_index(voxel_index) }
+  #[inline]
+  fn sample(&self, position: UVec3) -> f32 { (**self).sample(position) }
+  #[inline]
+  fn material_index(&self, voxel_index: VoxelIndex) -> MaterialId { (**self).material_index(voxel_index) }
+}
+
+
+// Quantized chunk samples
+
+/// How full precision mixed chunk samples are quantized. Chunks whose samples all have the same sign are always
+/// compressed (to 48 bytes with [`ChunkSize16`](crate::chunk::size::ChunkSize16)) regardless of quantization, so
+/// quantization only shrinks the chunks that contain a surface, which are the ones that are kept in memory and cached.
+/// Sizes below are of mixed samples of a [`ChunkSize16`](crate::chunk::size::ChunkSize16) chunk with a single material.
+#[derive(Default, Copy, Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub enum ChunkSampleQuantization {
+  /// Keep samples at full (32-bit) precision: 19720 bytes.
+  #[default]
+  None,
+  /// Quantize samples to 16 bits: 9874 bytes, half of full precision. Samples are rounded to steps of 1/32767th of the
+  /// largest sample magnitude of the chunk.
+  Bits16,
+  /// Quantize samples to 8 bits: 4961 bytes, a quarter of full precision. Samples are rounded to steps of 1/127th of
+  /// the largest sample magnitude of the chunk.
+  Bits8,
+}
+
+/// Integer type that samples can be quantized into.
+pub trait Quantized: Copy + Send + Sync + 'static {
+  /// Maximum magnitude of a quantized value.
+  const MAX: f32;
+  /// Size of a quantized value in bytes.
+  const BYTES: usize;
+  fn from_f32(value: f32) -> Self;
+  fn into_f32(self) -> f32;
+  fn write_le_bytes(self, bytes: &mut Vec<u8>);
+  fn read_le_bytes(bytes: &[u8]) -> Self;
+}
+
+macro_rules! impl_quantized {
+  ($t:ty) => {
+    impl Quantized for $t {
+      const MAX: f32 = <$t>::MAX as f32;
+      const BYTES: usize = std::mem::size_of::<$t>();
+      #[inline]
+      fn from_f32(value: f32) -> Self { value as $t }
+      #[inline]
+      fn into_f32(self) -> f32 { self as f32 }
+      #[inline]
+      fn write_le_bytes(self, bytes: &mut Vec<u8>) { bytes.extend_from_slice(&self.to_le_bytes()) }
+      #[inline]
+      fn read_le_bytes(bytes: &[u8]) -> Self { <$t>::from_le_bytes(bytes.try_into().unwrap()) }
+    }
+  };
+}
+
+impl_quantized!(i8);
+impl_quantized!(i16);
+
+/// Chunk samples quantized to `Q`, scaled into the per-chunk range `-scale * Q::MAX..=scale * Q::MAX`, which is
+/// determined by the sample with the largest magnitude.
+///
+/// Signs are preserved (i.e., `f32::is_sign_negative()` of a dequantized sample equals that of the original sample),
+/// so that meshers produce the same cases as with full precision samples. Only the positions of vertices on edges that
+/// cross the surface are affected by quantization.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub struct QuantizedChunkSamples<Q> {
+  samples: Box<[Q]>,
+  scale: f32,
+  materials: ChunkMaterials,
+}
+
+impl<Q: Quantized> QuantizedChunkSamples<Q> {
+  pub fn from_samples(samples: &[f32], materials: ChunkMaterials) -> Self {
+    let max_magnitude = samples.iter().fold(0.0f32, |max, sample| max.max(sample.abs()));
+    let scale = if max_magnitude > 0.0 { max_magnitude / Q::MAX } else { 1.0 };
+    let inverse_scale = 1.0 / scale;
+    let samples = samples.iter().map(|sample| {
+      let quantized = (sample * inverse_scale).round().clamp(-Q::MAX, Q::MAX);
+      // Round tiny negative samples away from zero to preserve their sign.
+      let quantized = if sample.is_sign_negative() { quantized.min(-1.0) } else { quantized };
+      Q::from_f32(quantized)
+    }).collect();
+    Self { samples, scale, materials }
+  }
+
+  #[inline]
+  pub fn scale(&self) -> f32 { self.scale }
+
+  /// Gets the number of bytes allocated on the heap for these samples.
+  #[inline]
+  pub fn heap_size_in_bytes(&self) -> usize {
+    self.samples.len() * Q::BYTES + self.materials.heap_size_in_bytes()
+  }
+
+  fn encode(&self, bytes: &mut Vec<u8>) {
+    bytes.extend_from_slice(&self.scale.to_le_bytes());
+    for sample in self.samples.iter() {
+      sample.write_le_bytes(bytes);
+    }
+    self.materials.encode(bytes);
+  }
+
+  fn decode(bytes: &[u8], len: usize) -> Option<Self> {
+    if bytes.len() < 4 + len * Q::BYTES { return None; }
+    let (scale, bytes) = bytes.split_at(4);
+    let (samples, bytes) = bytes.split_at(len * Q::BYTES);
+    let scale = f32::from_le_bytes(scale.try_into().unwrap()); // Unwrap OK: length checked above.
+    let samples = samples.chunks_exact(Q::BYTES).map(Q::read_le_bytes).collect();
+    let (materials, bytes) = ChunkMaterials::decode(bytes, len)?;
+    if !bytes.is_empty() { return None; }
+    Some(Self { samples, scale, materials })
+  }
 }
 
-pub type MaybeCompressedChunkSampleArray<C> = MaybeCompressedChunkSamples<ChunkSampleArray<C>>;
+impl<C: ChunkSize, Q: Quantized> ChunkSamples<C> for QuantizedChunkSamples<Q> {
+  #[inline]
+  fn sample_index(&self, voxel_index: VoxelIndex) -> f32 { self.samples[voxel_index.into_usize()].into_f32() * self.scale }
+  #[inline]
+  fn material_index(&self, voxel_index: VoxelIndex) -> MaterialId { self.materials.material_index(voxel_index) }
+}
 
 
 // Chunk sample array
@@ -83,11 +338,14 @@ pub type MaybeCompressedChunkSampleArray<C> = MaybeCompressedChunkSamples<ChunkS
 #[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
 pub struct ChunkSampleArray<C: ChunkSize> {
   array: C::VoxelChunkArray<f32>,
+  materials: ChunkMaterials,
 }
 
 impl<C: ChunkSize> ChunkSampleArray<C> {
   #[inline]
-  pub fn new(array: C::VoxelChunkArray<f32>) -> Self { Self { array } }
+  pub fn new(array: C::VoxelChunkArray<f32>) -> Self { Self::with_materials(array, ChunkMaterials::default()) }
+  #[inline]
+  pub fn with_materials(array: C::VoxelChunkArray<f32>, materials: ChunkMaterials) -> Self { Self { array, materials } }
   #[inline]
   pub fn new_with(default: f32) -> Self { Self::new(C::VoxelChunkArray::new(default)) }
   #[inline]
@@ -95,6 +353,11 @@ impl<C: ChunkSize> ChunkSampleArray<C> {
   #[inline]
   pub fn new_negative_zeroed() -> Self { Self::new_with(-0.0) }
 
+  #[inline]
+  pub fn materials(&self) -> &ChunkMaterials { &self.materials }
+  #[inline]
+  pub fn set_materials(&mut self, materials: ChunkMaterials) { self.materials = materials; }
+
   #[inline]
   pub fn slice<'a, CC: ChunkSize, Idx: ArrayIndex<f32, VoxelIndex, Output=[f32]>>(&'a self, index: Idx) -> ChunkSampleSlice<'a, CC> {
     ChunkSampleSlice::<'a, CC>::new(self.array.slice(index))
@@ -116,6 +379,8 @@ impl<C: ChunkSize> ChunkSampleArray<C> {
 impl<C: ChunkSize> ChunkSamples<C> for ChunkSampleArray<C> {
   #[inline]
   fn sample_index(&self, voxel_index: VoxelIndex) -> f32 { self.array[voxel_index] }
+  #[inline]
+  fn material_index(&self, voxel_index: VoxelIndex) -> MaterialId { self.materials.material_index(voxel_index) }
 }
 
 impl<C: ChunkSize> ChunkSamplesMut<C> for ChunkSampleArray<C> {
@@ -216,6 +481,13 @@ impl<'a, CS: ChunkSize, S: ChunkSamples<CS>, CT: ChunkSize> ChunkSamples<CT> for
     let index = CS::VoxelChunkShape::index_from_pos(position);
     self.samples.sample_index(index)
   }
+  #[inline]
+  fn material_index(&self, voxel_index: VoxelIndex) -> MaterialId {
+    let voxel_position = CT::VoxelChunkShape::index_into_pos(voxel_index);
+    let position = self.offset + voxel_position * self.step;
+    let index = CS::VoxelChunkShape::index_from_pos(position);
+    self.samples.material_index(index)
+  }
 }
 
 impl<'a, CS: ChunkSize, S: ChunkSamples<CS>, CT: ChunkSize> ChunkSampleOffset<'a, CS, S, CT> {
@@ -247,6 +519,10 @@ impl<'a, CS: ChunkSize, S: ChunkSamplesMut<CS>, CT: ChunkSize> ChunkSamples<CT>
   fn sample_index(&self, voxel_index: VoxelIndex) -> f32 {
     self.samples.sample_index(self.offset_index(voxel_index))
   }
+  #[inline]
+  fn material_index(&self, voxel_index: VoxelIndex) -> MaterialId {
+    self.samples.material_index(self.offset_index(voxel_index))
+  }
 }
 
 impl<'a, CS: ChunkSize, S: ChunkSamplesMut<CS>, CT: ChunkSize> ChunkSamplesMut<CT> for ChunkSampleOffsetMut<'a, CS, S, CT> {
@@ -296,3 +572,115 @@ impl<'a, CS: ChunkSize, S: ChunkSamplesMut<CS>, CT: ChunkSize> ChunkSampleOffset
     CS::VoxelChunkShape::index_from_pos(position)
   }
 }
+
+
+#[cfg(test)]
+mod tests {
+  use ultraviolet::UVec3;
+
+  use crate::chunk::material::ChunkMaterials;
+  use crate::chunk::sample::{ChunkSampleArray, ChunkSampleQuantization, ChunkSamples, ChunkSamplesMut, MaybeCompressedChunkSampleArray, MaybeCompressedChunkSamples};
+  use crate::chunk::size::{ChunkSize, ChunkSize16};
+
+  type C16 = ChunkSize16;
+
+  fn positions() -> impl Iterator<Item=UVec3> {
+    let row = C16::VOXELS_IN_CHUNK_ROW;
+    (0..row).flat_map(move |z| (0..row).flat_map(move |y| (0..row).map(move |x| UVec3::new(x, y, z))))
+  }
+
+  /// Mixed samples of a slanted plane, with tiny negative samples near the surface, and mixed materials.
+  fn mixed_samples() -> MaybeCompressedChunkSampleArray<C16> {
+    let mut array = ChunkSampleArray::<C16>::new_positive_zeroed();
+    for position in positions() {
+      let sample = 7.3 - position.x as f32 - 0.37 * position.y as f32;
+      let sample = if sample < 0.0 && sample > -0.2 { -1e-6 } else { sample };
+      array.set(position.x, position.y, position.z, sample);
+    }
+    let materials = (0..C16::VOXELS_IN_CHUNK_USIZE).map(|index| (index % 3) as u8).collect();
+    array.set_materials(ChunkMaterials::from_materials(materials));
+    MaybeCompressedChunkSamples::Mixed(Box::new(array))
+  }
+
+  fn assert_equal_samples(a: &MaybeCompressedChunkSampleArray<C16>, b: &MaybeCompressedChunkSampleArray<C16>) {
+    for position in positions() {
+      assert_eq!(ChunkSamples::<C16>::sample(a, position).to_bits(), ChunkSamples::<C16>::sample(b, position).to_bits(), "Samples at {:?} differ", position);
+      assert_eq!(ChunkSamples::<C16>::material(a, position), ChunkSamples::<C16>::material(b, position), "Materials at {:?} differ", position);
+    }
+  }
+
+  #[test]
+  fn default_quantization_is_lossless() {
+    assert_eq!(ChunkSampleQuantization::default(), ChunkSampleQuantization::None);
+    let samples = mixed_samples().quantize(ChunkSampleQuantization::default());
+    assert!(matches!(samples, MaybeCompressedChunkSamples::Mixed(_)));
+    assert_equal_samples(&samples, &mixed_samples());
+  }
+
+  #[test]
+  fn quantized_samples_keep_signs_and_materials() {
+    let original = mixed_samples();
+    for quantization in [ChunkSampleQuantization::Bits16, ChunkSampleQuantization::Bits8] {
+      let quantized = mixed_samples().quantize(quantization);
+      let scale = match &quantized {
+        MaybeCompressedChunkSamples::Quantized8(inner) if quantization == ChunkSampleQuantization::Bits8 => inner.scale(),
+        MaybeCompressedChunkSamples::Quantized16(inner) if quantization == ChunkSampleQuantization::Bits16 => inner.scale(),
+        _ => panic!("Samples were not quantized with {:?}", quantization),
+      };
+      for position in positions() {
+        let sample = ChunkSamples::<C16>::sample(&original, position);
+        let dequantized = ChunkSamples::<C16>::sample(&quantized, position);
+        assert_eq!(dequantized.is_sign_negative(), sample.is_sign_negative(), "Sign of {} at {:?} changed to {} with {:?}", sample, position, dequantized, quantization);
+        // Tiny negative samples are rounded away from zero by one step, other samples to the nearest step.
+        assert!((dequantized - sample).abs() <= scale, "Sample {} at {:?} dequantized to {} with {:?}", sample, position, dequantized, quantization);
+        assert_eq!(ChunkSamples::<C16>::material(&quantized, position), ChunkSamples::<C16>::material(&original, position));
+      }
+    }
+  }
+
+  #[test]
+  fn encoded_samples_decode_to_equal_samples() {
+    let all_samples = [
+      MaybeCompressedChunkSamples::Zero,
+      MaybeCompressedChunkSamples::Positive,
+      MaybeCompressedChunkSamples::Negative,
+      MaybeCompressedChunkSamples::Uniform(-0.25),
+      mixed_samples().quantize(ChunkSampleQuantization::Bits8),
+      mixed_samples().quantize(ChunkSampleQuantization::Bits16),
+      mixed_samples(),
+    ];
+    for samples in all_samples {
+      let mut bytes = Vec::new();
+      samples.encode(&mut bytes);
+      let decoded = MaybeCompressedChunkSampleArray::<C16>::decode(&bytes).expect("Failed to decode samples");
+      assert_eq!(std::mem::discriminant(&decoded), std::mem::discriminant(&samples));
+      assert_equal_samples(&decoded, &samples);
+      assert_eq!(decoded.size_in_bytes(), samples.size_in_bytes());
+      // Truncated or extended encodings are rejected.
+      assert!(MaybeCompressedChunkSampleArray::<C16>::decode(&bytes[..bytes.len() - 1]).is_none());
+      bytes.push(0);
+      assert!(MaybeCompressedChunkSampleArray::<C16>::decode(&bytes).is_none());
+    }
+  }
+
+  #[test]
+  fn quantized_samples_are_smaller() {
+    let size = mixed_samples().size_in_bytes();
+    let size_16 = mixed_samples().quantize(ChunkSampleQuantization::Bits16).size_in_bytes();
+    let size_8 = mixed_samples().quantize(ChunkSampleQuantization::Bits8).size_in_bytes();
+    assert!(size_8 < size_16 && size_16 < size, "Sizes {} (8 bits), {} (16 bits), {} (32 bits)", size_8, size_16, size);
+  }
+
+  #[test]
+  fn quantized_sizes_match_documentation() {
+    let mut samples = mixed_samples();
+    if let MaybeCompressedChunkSamples::Mixed(array) = &mut samples {
+      array.set_materials(ChunkMaterials::default());
+    }
+    let size_of = |quantization| samples.clone().quantize(quantization).size_in_bytes();
+    assert_eq!(size_of(ChunkSampleQuantization::None), 19720);
+    assert_eq!(size_of(ChunkSampleQuantization::Bits16), 9874);
+    assert_eq!(size_of(ChunkSampleQuantization::Bits8), 4961);
+    assert_eq!(MaybeCompressedChunkSampleArray::<C16>::Positive.size_in_bytes(), 48);
+  }
+}
@@ -1,20 +1,34 @@
-use std::num::NonZeroU32;
+use std::num::NonZeroU64;
 use std::ops::Index;
 
-use ultraviolet::{UVec3, Vec3};
+use ultraviolet::{DVec3, IVec3, UVec3, Vec3};
 
 use crate::chunk::size::ChunkSize;
 
 #[repr(transparent)]
 #[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug)]
-pub struct Aabb(NonZeroU32);
+pub struct Aabb(NonZeroU64);
 
 impl Aabb {
+  /// Maximum depth of an AABB. Locational codes use 3 bits per depth level, plus a marker bit and the user bit.
+  pub const MAX_DEPTH: u8 = 20;
+
   #[inline]
-  pub fn root() -> Self { Self(unsafe { NonZeroU32::new_unchecked(2) }) }
+  pub fn root() -> Self { Self(unsafe { NonZeroU64::new_unchecked(2) }) }
 
+  /// Creates an AABB from locational `code`, returning `None` if `code` is not a valid locational code.
+  #[inline]
+  pub fn from_code(code: u64) -> Option<Self> {
+    let marker_bit = 63u32.checked_sub((code & !1).leading_zeros())?;
+    if marker_bit % 3 != 1 { return None; }
+    Some(unsafe { Self::new_unchecked(code) })
+  }
+  /// Returns the locational code of this AABB.
   #[inline]
-  pub fn depth(&self) -> u8 { ((31 - self.0.leading_zeros()) / 3) as u8 }
+  pub fn code(&self) -> u64 { self.0.get() }
+
+  #[inline]
+  pub fn depth(&self) -> u8 { ((63 - self.0.leading_zeros()) / 3) as u8 }
 
   #[inline]
   pub fn size(&self, root_size: u32) -> u32 { Self::size_internal(root_size, self.depth()) }
@@ -38,6 +52,15 @@ impl Aabb {
     let size = self.size(root_size);
     Self::maximum_point_internal(minimum_point, size)
   }
+  /// Gets the minimum and maximum point.
+  #[inline]
+  pub fn bounds(&self, root_size: u32) -> (Vec3, Vec3) {
+    let depth = self.depth();
+    let size = Self::size_internal(root_size, depth);
+    let minimum_point = Self::minimum_point_internal(depth, size, self.0.get());
+    let maximum_point = Self::maximum_point_internal(minimum_point, size);
+    (minimum_point.into(), maximum_point.into())
+  }
   #[inline]
   pub fn step<C: ChunkSize>(&self, root_size: u32) -> u32 { self.size(root_size) / C::CELLS_IN_CHUNK_ROW }
   #[inline]
@@ -54,6 +77,33 @@ impl Aabb {
     let closest_point = self.closest_point(root_size, point);
     (closest_point - point).mag()
   }
+
+  /// Double precision version of [`minimum_point`](Self::minimum_point), for large octrees where `f32` points lose
+  /// precision.
+  #[inline]
+  pub fn minimum_point_f64(&self, root_size: u32) -> DVec3 { uvec3_to_dvec3(self.minimum_point(root_size)) }
+  /// Double precision version of [`center_point`](Self::center_point).
+  #[inline]
+  pub fn center_point_f64(&self, root_size: u32) -> DVec3 { uvec3_to_dvec3(self.center_point(root_size)) }
+  /// Double precision version of [`maximum_point`](Self::maximum_point).
+  #[inline]
+  pub fn maximum_point_f64(&self, root_size: u32) -> DVec3 { uvec3_to_dvec3(self.maximum_point(root_size)) }
+  /// Double precision version of [`closest_point`](Self::closest_point).
+  #[inline]
+  pub fn closest_point_f64(&self, root_size: u32, mut point: DVec3) -> DVec3 {
+    let depth = self.depth();
+    let size = Self::size_internal(root_size, depth);
+    let minimum_point = Self::minimum_point_internal(depth, size, self.0.get());
+    let maximum_point = Self::maximum_point_internal(minimum_point, size);
+    point.clamp(uvec3_to_dvec3(minimum_point), uvec3_to_dvec3(maximum_point));
+    point
+  }
+  /// Double precision version of [`distance_from`](Self::distance_from).
+  #[inline]
+  pub fn distance_from_f64(&self, root_size: u32, point: DVec3) -> f64 {
+    let closest_point = self.closest_point_f64(root_size, point);
+    (closest_point - point).mag()
+  }
   #[inline]
   pub fn with_size(&self, root_size: u32) -> AabbWithSize {
     AabbWithSize { root_size, inner: *self }
@@ -100,32 +150,102 @@ impl Aabb {
   }
 
   #[inline]
-  pub fn sibling_positive_x(&self) -> Option<Self> { self.positive_sibling::<0>() }
+  pub fn sibling_positive_x(&self) -> Option<Self> { self.neighbor(IVec3::new(1, 0, 0)) }
   #[inline]
-  pub fn sibling_positive_y(&self) -> Option<Self> { self.positive_sibling::<1>() }
+  pub fn sibling_positive_y(&self) -> Option<Self> { self.neighbor(IVec3::new(0, 1, 0)) }
   #[inline]
-  pub fn sibling_positive_z(&self) -> Option<Self> { self.positive_sibling::<2>() }
+  pub fn sibling_positive_z(&self) -> Option<Self> { self.neighbor(IVec3::new(0, 0, 1)) }
   #[inline]
-  pub fn sibling_positive_xy(&self) -> Option<Self> { self.sibling_positive_x().and_then(|aabb| aabb.sibling_positive_y()) }
+  pub fn sibling_positive_xy(&self) -> Option<Self> { self.neighbor(IVec3::new(1, 1, 0)) }
   #[inline]
-  pub fn sibling_positive_yz(&self) -> Option<Self> { self.sibling_positive_y().and_then(|aabb| aabb.sibling_positive_z()) }
+  pub fn sibling_positive_yz(&self) -> Option<Self> { self.neighbor(IVec3::new(0, 1, 1)) }
   #[inline]
-  pub fn sibling_positive_xz(&self) -> Option<Self> { self.sibling_positive_x().and_then(|aabb| aabb.sibling_positive_z()) }
+  pub fn sibling_positive_xz(&self) -> Option<Self> { self.neighbor(IVec3::new(1, 0, 1)) }
+
+  /// Gets the neighbor of this AABB at the same depth, in direction `offset` where each component is -1, 0, or 1. One
+  /// non-zero component selects a face neighbor, two an edge neighbor, and three a corner neighbor. Returns `None` if
+  /// the neighbor lies outside of the root, or if `offset` is zero. The user bit is preserved.
+  #[inline]
+  pub fn neighbor(&self, offset: IVec3) -> Option<Self> {
+    if offset == IVec3::zero() { return None; }
+    let (root_offset, neighbor) = self.neighbor_across_roots(offset);
+    if root_offset == IVec3::zero() { Some(neighbor) } else { None }
+  }
+  /// Gets the neighbor of this AABB at the same depth, in direction `offset` where each component is -1, 0, or 1, as if
+  /// the root is surrounded by a grid of roots of the same size. Returns the offset of the root that the neighbor is in
+  /// (zero if it is in the same root), along with the neighbor in that root. If `offset` is zero, returns this AABB.
+  /// The user bit is preserved.
   #[inline]
-  fn positive_sibling<const O: u8>(&self) -> Option<Self> {
+  pub fn neighbor_across_roots(&self, offset: IVec3) -> (IVec3, Self) {
+    debug_assert!(offset.x.abs() <= 1 && offset.y.abs() <= 1 && offset.z.abs() <= 1, "BUG: offset {:?} is not in -1..=1", offset);
     let depth = self.depth();
-    let mut code = self.0.get();
+    let code = self.0.get();
+    let (code, x_wrapped) = Self::step_along::<0>(code, depth, offset.x);
+    let (code, y_wrapped) = Self::step_along::<1>(code, depth, offset.y);
+    let (code, z_wrapped) = Self::step_along::<2>(code, depth, offset.z);
+    let root_offset = IVec3::new(
+      if x_wrapped { offset.x } else { 0 },
+      if y_wrapped { offset.y } else { 0 },
+      if z_wrapped { offset.z } else { 0 },
+    );
+    (root_offset, unsafe { Self::new_unchecked(code) })
+  }
+  /// Gets the face neighbor of this AABB at the same depth in the negative or positive direction along `axis` (0 = x,
+  /// 1 = y, 2 = z).
+  #[inline]
+  pub fn face_neighbor(&self, axis: usize, positive: bool) -> Option<Self> {
+    let mut offset = IVec3::zero();
+    offset[axis] = if positive { 1 } else { -1 };
+    self.neighbor(offset)
+  }
+  /// Gets the neighbor of this AABB at the depth of its parent, in direction `offset` where each component is -1, 0,
+  /// or 1. That is, the parent of the same-depth neighbor, which is only adjacent to this AABB if it is not this
+  /// AABB's own parent. Returns `None` if the neighbor lies outside of the root, or if it is inside this AABB's parent.
+  #[inline]
+  pub fn parent_neighbor(&self, offset: IVec3) -> Option<Self> {
+    let parent = self.neighbor(offset)?.parent()?;
+    if Some(parent) == self.parent() { None } else { Some(parent) }
+  }
+
+  /// Gets the parent of this AABB, or `None` if this is the root. The user bit is preserved.
+  #[inline]
+  pub fn parent(&self) -> Option<Self> {
+    if self.depth() == 0 { return None; }
+    let code = self.0.get();
+    let user_bit_set = code & 1;
+    let code = (code >> 3) & !1; // Unset the lowest octant bit, which was shifted into the user bit.
+    Some(unsafe { Self::new_unchecked(code | user_bit_set) })
+  }
+  /// Gets the ancestor of this AABB at `depth`, or `None` if `depth` is deeper than this AABB. The user bit is
+  /// preserved.
+  #[inline]
+  pub fn ancestor(&self, depth: u8) -> Option<Self> {
+    let levels = self.depth().checked_sub(depth)?;
+    let code = self.0.get();
+    let user_bit_set = code & 1;
+    let code = (code >> (levels as u32 * 3)) & !1; // Unset the lowest octant bit, which was shifted into the user bit.
+    Some(unsafe { Self::new_unchecked(code | user_bit_set) })
+  }
+
+  /// Steps locational `code` at `depth` one AABB along axis `O` in the direction of `sign`, by incrementing or
+  /// decrementing the bits of that axis, which are interleaved with the bits of the other axes. Also returns whether
+  /// the step overflowed, which happens when stepping outside of the root, in which case the code wraps around to the
+  /// opposite side of the root.
+  #[inline]
+  fn step_along<const O: u8>(mut code: u64, depth: u8, sign: i32) -> (u64, bool) {
+    if sign == 0 { return (code, false); }
+    let positive = sign > 0;
     for d in 0..depth {
-      let bit = 1 << ((d * 3) + O + 1); // + 1 to skip user bit
-      if (code & bit) == 0 { // If bit is unset, set it to go to the positive sibling; and we're done.
-        unsafe { return Some(Self::new_unchecked(code | bit)); }
-      } else { // Otherwise unset the bit to go to the negative sibling and continue.
-        code = code & !bit;
+      let bit = 1 << ((d as u32 * 3) + O as u32 + 1); // + 1 to skip user bit
+      let bit_set = (code & bit) != 0;
+      if bit_set != positive { // Bit can be flipped without carry or borrow; and we're done.
+        return (code ^ bit, false);
+      } else { // Otherwise flip the bit and carry or borrow into the parent.
+        code ^= bit;
       }
     }
-    None // No parent was found with the bit set, so we couldn't go to a positive sibling anywhere.
+    (code, true) // Carried or borrowed out of the root.
   }
-
   #[inline]
   pub fn is_user_bit_set(&self) -> bool { self.0.get() & 1 != 0 }
   #[inline]
@@ -138,22 +258,22 @@ impl Aabb {
   pub fn with_user_bit_unset(&self) -> Self { unsafe { Self::new_unchecked(self.0.get() & (!1)) } }
 
   #[inline]
-  unsafe fn new_unchecked(code: u32) -> Self {
+  unsafe fn new_unchecked(code: u64) -> Self {
     debug_assert!(code != 0);
-    Self(NonZeroU32::new_unchecked(code))
+    Self(NonZeroU64::new_unchecked(code))
   }
   #[inline]
-  unsafe fn update_unchecked(&mut self, code: u32) {
+  unsafe fn update_unchecked(&mut self, code: u64) {
     debug_assert!(code != 0);
-    self.0 = NonZeroU32::new_unchecked(code);
+    self.0 = NonZeroU64::new_unchecked(code);
   }
   #[inline]
-  fn child_code(&self) -> u32 {
+  fn child_code(&self) -> u64 {
     let code = self.0.get();
     let user_bit_set = code & 1 != 0;
     let code = code & (!1); // Unset user bit to prevent it from shifting.
     let code = code << 3;
-    code | user_bit_set as u32 // Set the user bit again if it was set.
+    code | user_bit_set as u64 // Set the user bit again if it was set.
   }
 
   #[inline]
@@ -168,7 +288,7 @@ impl Aabb {
   }
 
   #[inline]
-  fn minimum_point_internal(depth: u8, mut size: u32, mut code: u32) -> UVec3 {
+  fn minimum_point_internal(depth: u8, mut size: u32, mut code: u64) -> UVec3 {
     let mut minimum_point = UVec3::zero();
     for _ in 0..depth {
       let octant = code as u8 & 0b111_0;
@@ -204,6 +324,12 @@ impl Aabb {
 }
 
 
+#[inline]
+fn uvec3_to_dvec3(vec: UVec3) -> DVec3 { DVec3::new(vec.x as f64, vec.y as f64, vec.z as f64) }
+#[inline]
+pub(crate) fn vec3_to_dvec3(vec: Vec3) -> DVec3 { DVec3::new(vec.x as f64, vec.y as f64, vec.z as f64) }
+
+
 // Sized AABB
 
 #[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug)]
@@ -232,6 +358,16 @@ impl AabbWithSize {
   pub fn closest_point(&self, point: Vec3) -> Vec3 { self.inner.closest_point(self.root_size, point) }
   #[inline]
   pub fn distance_from(&self, point: Vec3) -> f32 { self.inner.distance_from(self.root_size, point) }
+  #[inline]
+  pub fn minimum_point_f64(&self) -> DVec3 { self.inner.minimum_point_f64(self.root_size) }
+  #[inline]
+  pub fn center_point_f64(&self) -> DVec3 { self.inner.center_point_f64(self.root_size) }
+  #[inline]
+  pub fn maximum_point_f64(&self) -> DVec3 { self.inner.maximum_point_f64(self.root_size) }
+  #[inline]
+  pub fn closest_point_f64(&self, point: DVec3) -> DVec3 { self.inner.closest_point_f64(self.root_size, point) }
+  #[inline]
+  pub fn distance_from_f64(&self, point: DVec3) -> f64 { self.inner.distance_from_f64(self.root_size, point) }
 
   #[inline]
   pub fn subdivide(&self) -> AabbSubdivideIter { self.inner.subdivide_iter() }
@@ -250,6 +386,21 @@ impl AabbWithSize {
   pub fn sibling_positive_yz(&self) -> Option<Self> { self.inner.sibling_positive_yz().map(|inner| self.wrap(inner)) }
   #[inline]
   pub fn sibling_positive_xz(&self) -> Option<Self> { self.inner.sibling_positive_xz().map(|inner| self.wrap(inner)) }
+  #[inline]
+  pub fn neighbor(&self, offset: IVec3) -> Option<Self> { self.inner.neighbor(offset).map(|inner| self.wrap(inner)) }
+  #[inline]
+  pub fn neighbor_across_roots(&self, offset: IVec3) -> (IVec3, Self) {
+    let (root_offset, inner) = self.inner.neighbor_across_roots(offset);
+    (root_offset, self.wrap(inner))
+  }
+  #[inline]
+  pub fn face_neighbor(&self, axis: usize, positive: bool) -> Option<Self> { self.inner.face_neighbor(axis, positive).map(|inner| self.wrap(inner)) }
+  #[inline]
+  pub fn parent_neighbor(&self, offset: IVec3) -> Option<Self> { self.inner.parent_neighbor(offset).map(|inner| self.wrap(inner)) }
+  #[inline]
+  pub fn parent(&self) -> Option<Self> { self.inner.parent().map(|inner| self.wrap(inner)) }
+  #[inline]
+  pub fn ancestor(&self, depth: u8) -> Option<Self> { self.inner.ancestor(depth).map(|inner| self.wrap(inner)) }
 
   #[inline]
   pub fn is_user_bit_set(&self) -> bool { self.inner.is_user_bit_set() }
@@ -403,7 +554,7 @@ pub type AabbSubdivide = PerAabbSubdivide<Aabb>;
 // Subdivide iterator
 
 pub struct AabbSubdivideIter {
-  code: u32,
+  code: u64,
   octant: u8,
 }
 
@@ -417,7 +568,7 @@ impl Iterator for AabbSubdivideIter {
   #[inline]
   fn next(&mut self) -> Option<Self::Item> {
     if self.octant > 7 { return None; }
-    let aabb = unsafe { Aabb::new_unchecked(self.code | (self.octant as u32) << 1) };
+    let aabb = unsafe { Aabb::new_unchecked(self.code | (self.octant as u64) << 1) };
     self.octant += 1;
     Some(aabb)
   }
@@ -430,7 +581,7 @@ impl Iterator for AabbSubdivideIter {
 mod tests {
   use std::mem::size_of;
 
-  use ultraviolet::{UVec3, Vec3};
+  use ultraviolet::{DVec3, IVec3, UVec3, Vec3};
 
   use crate::lod::aabb::{Aabb, AabbSubdivide, AabbWithSize};
 
@@ -698,6 +849,148 @@ mod tests {
     assert_eq!(Some(yz_3_yz_2_xyz_1), xyz_3_xyz_2_yz_1.sibling_positive_x());
   }
 
+  fn aabbs_at_depth(depth: u8) -> Vec<Aabb> {
+    let mut aabbs = vec![Aabb::root()];
+    for _ in 0..depth {
+      aabbs = aabbs.into_iter().flat_map(|aabb| aabb.subdivide_iter()).collect();
+    }
+    aabbs
+  }
+
+  fn offsets() -> impl Iterator<Item=IVec3> {
+    (-1..=1).flat_map(|x| (-1..=1).flat_map(move |y| (-1..=1).map(move |z| IVec3::new(x, y, z))))
+  }
+
+  fn to_ivec3(point: UVec3) -> IVec3 { IVec3::new(point.x as i32, point.y as i32, point.z as i32) }
+
+  /// Returns whether `aabb` contains `point` in its interior.
+  fn contains(aabb: Aabb, root_size: u32, point: IVec3) -> bool {
+    let min = to_ivec3(aabb.minimum_point(root_size));
+    let max = to_ivec3(aabb.maximum_point(root_size));
+    (0..3).all(|i| min[i] < point[i] && point[i] < max[i])
+  }
+
+  #[test]
+  fn neighbors_match_geometry() {
+    let root_size = 16;
+    for depth in 0..=3 {
+      let aabbs = aabbs_at_depth(depth);
+      for &aabb in &aabbs {
+        let min = to_ivec3(aabb.minimum_point(root_size));
+        let max = to_ivec3(aabb.maximum_point(root_size));
+        let mut num_neighbors = 0;
+        for &other in &aabbs {
+          if other == aabb { continue; }
+          let other_min = to_ivec3(other.minimum_point(root_size));
+          let other_max = to_ivec3(other.maximum_point(root_size));
+          // Boxes of the same size that are not the same box touch if their closed intervals overlap on all axes.
+          let touching = (0..3).all(|i| min[i] <= other_max[i] && other_min[i] <= max[i]);
+          let offset = IVec3::new((other_min.x - min.x).signum(), (other_min.y - min.y).signum(), (other_min.z - min.z).signum());
+          if touching {
+            num_neighbors += 1;
+            assert_eq!(Some(other), aabb.neighbor(offset), "{:?} does not have {:?} as neighbor in direction {:?}", aabb, other, offset);
+            assert_eq!(Some(other.with_user_bit_set()), aabb.with_user_bit_set().neighbor(offset));
+          } else if let Some(neighbor) = aabb.neighbor(offset) {
+            assert_ne!(other, neighbor, "{:?} has non-touching {:?} as neighbor in direction {:?}", aabb, other, offset);
+          }
+        }
+        assert_eq!(num_neighbors, offsets().filter_map(|offset| aabb.neighbor(offset)).count());
+        assert_eq!(None, aabb.neighbor(IVec3::zero()));
+      }
+    }
+  }
+
+  #[test]
+  fn neighbors_outside_root() {
+    let root_size = 16;
+    for depth in 0..=3 {
+      for aabb in aabbs_at_depth(depth) {
+        let size = aabb.size(root_size) as i32;
+        let min = to_ivec3(aabb.minimum_point(root_size));
+        for offset in offsets() {
+          let neighbor_min = min + offset * size;
+          let inside = (0..3).all(|i| neighbor_min[i] >= 0 && neighbor_min[i] < root_size as i32);
+          let neighbor = aabb.neighbor(offset);
+          assert_eq!(inside && offset != IVec3::zero(), neighbor.is_some(), "{:?} in direction {:?}", aabb, offset);
+          if let Some(neighbor) = neighbor {
+            assert_eq!(depth, neighbor.depth());
+            assert_eq!(neighbor_min, to_ivec3(neighbor.minimum_point(root_size)));
+          }
+        }
+        for axis in 0..3 {
+          let mut offset = IVec3::zero();
+          offset[axis] = 1;
+          assert_eq!(aabb.neighbor(offset), aabb.face_neighbor(axis, true));
+          offset[axis] = -1;
+          assert_eq!(aabb.neighbor(offset), aabb.face_neighbor(axis, false));
+        }
+      }
+    }
+  }
+
+  #[test]
+  fn neighbors_across_roots_match_geometry() {
+    let root_size = 16;
+    for depth in 0..=3 {
+      for aabb in aabbs_at_depth(depth) {
+        let size = aabb.size(root_size) as i32;
+        let min = to_ivec3(aabb.minimum_point(root_size));
+        for offset in offsets() {
+          let neighbor_min = min + offset * size;
+          let root_offset = IVec3::new(neighbor_min.x.div_euclid(16), neighbor_min.y.div_euclid(16), neighbor_min.z.div_euclid(16));
+          let (actual_root_offset, neighbor) = aabb.with_user_bit_set().neighbor_across_roots(offset);
+          assert_eq!(root_offset, actual_root_offset, "{:?} in direction {:?}", aabb, offset);
+          assert_eq!(depth, neighbor.depth());
+          assert!(neighbor.is_user_bit_set());
+          assert_eq!(neighbor_min - root_offset * 16, to_ivec3(neighbor.minimum_point(root_size)));
+        }
+      }
+    }
+  }
+
+  #[test]
+  fn parent_neighbors_match_geometry() {
+    let root_size = 16;
+    for depth in 1..=3 {
+      let parents = aabbs_at_depth(depth - 1);
+      for aabb in aabbs_at_depth(depth) {
+        let size = aabb.size(root_size) as i32;
+        let center = to_ivec3(aabb.center_point(root_size));
+        for offset in offsets() {
+          // The parent-depth neighbor contains the center of the same-depth neighbor, but not the center of this AABB.
+          let point = center + offset * size;
+          let expected = parents.iter().copied()
+            .find(|parent| contains(*parent, root_size, point) && !contains(*parent, root_size, center));
+          assert_eq!(expected, aabb.parent_neighbor(offset), "{:?} in direction {:?}", aabb, offset);
+          assert_eq!(expected.map(|a| a.with_user_bit_set()), aabb.with_user_bit_set().parent_neighbor(offset));
+        }
+      }
+    }
+  }
+
+  #[test]
+  fn parent_and_ancestors() {
+    let root_size = 16;
+    assert_eq!(None, Aabb::root().parent());
+    assert_eq!(Some(Aabb::root()), Aabb::root().ancestor(0));
+    assert_eq!(None, Aabb::root().ancestor(1));
+    for depth in 1..=3 {
+      for aabb in aabbs_at_depth(depth) {
+        let parent = aabb.parent().unwrap();
+        assert_eq!(depth - 1, parent.depth());
+        assert!(parent.subdivide_iter().any(|child| child == aabb));
+        assert_eq!(Some(parent.with_user_bit_set()), aabb.with_user_bit_set().parent());
+        assert_eq!(Some(aabb), aabb.ancestor(depth));
+        assert_eq!(None, aabb.ancestor(depth + 1));
+        for ancestor_depth in 0..depth {
+          let ancestor = aabb.ancestor(ancestor_depth).unwrap();
+          assert_eq!(ancestor_depth, ancestor.depth());
+          assert!(contains(ancestor, root_size, to_ivec3(aabb.center_point(root_size))));
+        }
+      }
+    }
+  }
+
   #[test]
   fn user_bit() {
     let mut root = Aabb::root();
@@ -782,7 +1075,44 @@ mod tests {
 
   #[test]
   fn size() {
-    assert_eq!(4, size_of::<Aabb>());
-    assert_eq!(4, size_of::<Option<Aabb>>());
+    assert_eq!(8, size_of::<Aabb>());
+    assert_eq!(8, size_of::<Option<Aabb>>());
+  }
+
+  #[test]
+  fn max_depth() {
+    // Earth-scale root with 1 unit sized cells in chunks with 16 cells per row.
+    let root_size = 1 << (Aabb::MAX_DEPTH as u32 + 4);
+    let mut aabb = Aabb::root().with_user_bit_set();
+    let mut minimum_point = UVec3::zero();
+    for depth in 1..=Aabb::MAX_DEPTH {
+      // Alternate between octants to exercise all bits of the locational code.
+      let octant = depth % 8;
+      aabb = aabb.subdivide()[octant];
+      let size = root_size >> depth as u32;
+      minimum_point += UVec3::new((octant & 1) as u32, ((octant >> 1) & 1) as u32, ((octant >> 2) & 1) as u32) * size;
+      assert_eq!(depth, aabb.depth());
+      assert_eq!(size, aabb.size(root_size));
+      assert_eq!(minimum_point, aabb.minimum_point(root_size));
+      assert_eq!(true, aabb.is_user_bit_set());
+      assert_eq!(Some(aabb), Aabb::from_code(aabb.code()));
+    }
+    assert_eq!(16, aabb.size(root_size));
+    assert_eq!(None, Aabb::from_code(aabb.code() << 1));
+    assert_eq!(None, Aabb::from_code(aabb.code() << 2));
+  }
+
+  #[test]
+  fn closest_point_and_distance_f64() {
+    let root_size = 1 << (Aabb::MAX_DEPTH as u32 + 4);
+    let aabb = Aabb::root().subdivide().xyz.with_size(root_size);
+    let half_size = (root_size / 2) as f64;
+    assert_eq!(DVec3::broadcast(half_size), aabb.minimum_point_f64());
+    assert_eq!(DVec3::broadcast(half_size * 1.5), aabb.center_point_f64());
+    assert_eq!(DVec3::broadcast(half_size * 2.0), aabb.maximum_point_f64());
+    // A point that `f32` cannot represent exactly at this scale.
+    let point = DVec3::new(half_size - 0.25, half_size + 1.0, half_size + 1.0);
+    assert_eq!(DVec3::new(half_size, half_size + 1.0, half_size + 1.0), aabb.closest_point_f64(point));
+    assert_eq!(0.25, aabb.distance_from_f64(point));
   }
 }
@@ -1,4 +1,6 @@
 use std::marker::PhantomData;
+use std::path::PathBuf;
+use std::sync::Arc;
 
 use ultraviolet::{Isometry3, Mat4};
 
@@ -7,28 +9,36 @@ use gfx::Gfx;
 use crate::chunk::size::ChunkSize;
 use crate::lod::extract::LodExtractor;
 use crate::lod::octmap::{LodOctmap, LodOctmapSettings};
+use crate::lod::planet::{LodPlanet, LodPlanetSettings};
 use crate::lod::render::{LodRenderDataManager, SimpleLodRenderDataManager};
+use crate::lod::sample_cache::ChunkSampleCache;
 use crate::volume::Volume;
 
 pub struct LodManagerBuilder<C, V, E> {
   chunk_size: PhantomData<C>,
   volume: V,
   extractor: E,
+  sample_cache_directory: Option<PathBuf>,
 }
 
 impl LodManagerBuilder<(), (), ()> {
   pub fn new<C: ChunkSize>() -> LodManagerBuilder<C, (), ()> {
-    LodManagerBuilder { chunk_size: PhantomData::default(), volume: (), extractor: () }
+    LodManagerBuilder { chunk_size: PhantomData::default(), volume: (), extractor: (), sample_cache_directory: None }
   }
 }
 
 impl<C: ChunkSize, V, E> LodManagerBuilder<C, V, E> {
   pub fn with_volume<VV: Volume>(self, volume: VV) -> LodManagerBuilder<C, VV, E> {
-    LodManagerBuilder { chunk_size: self.chunk_size, volume, extractor: self.extractor }
+    LodManagerBuilder { chunk_size: self.chunk_size, volume, extractor: self.extractor, sample_cache_directory: self.sample_cache_directory }
   }
 
   pub fn with_extractor<EE: LodExtractor<C>>(self, extractor: EE) -> LodManagerBuilder<C, V, EE> {
-    LodManagerBuilder { chunk_size: self.chunk_size, volume: self.volume, extractor }
+    LodManagerBuilder { chunk_size: self.chunk_size, volume: self.volume, extractor, sample_cache_directory: self.sample_cache_directory }
+  }
+
+  /// Persistently cache sampled chunks in a subdirectory of `directory`.
+  pub fn with_sample_cache_directory(self, directory: impl Into<PathBuf>) -> Self {
+    LodManagerBuilder { sample_cache_directory: Some(directory.into()), ..self }
   }
 }
 
@@ -40,7 +50,10 @@ impl<C: ChunkSize, V: Volume, E: LodExtractor<C>> LodManagerBuilder<C, V, E> {
     transform: Isometry3,
     view_projection_matrix: Mat4,
   ) -> SimpleLodRenderDataManager<LodOctmap<C, V, E>> {
-    let lod_octmap = LodOctmap::new(lod_octmap_settings, transform, self.volume, self.extractor);
+    let sample_cache = self.sample_cache_directory.map(|directory| {
+      Arc::new(ChunkSampleCache::new(directory, &self.volume, lod_octmap_settings.root_size, lod_octmap_settings.sample_quantization))
+    });
+    let lod_octmap = LodOctmap::with_sample_cache(lod_octmap_settings, transform, self.volume, self.extractor, sample_cache);
     SimpleLodRenderDataManager::new(gfx, lod_octmap, view_projection_matrix)
   }
 
@@ -53,4 +66,26 @@ impl<C: ChunkSize, V: Volume, E: LodExtractor<C>> LodManagerBuilder<C, V, E> {
   ) -> Box<dyn LodRenderDataManager<C>> {
     Box::new(self.build(gfx, lod_octmap_settings, transform, view_projection_matrix))
   }
+
+  /// Builds a [`LodPlanet`]. Sampled chunks are not cached on disk, as the sample cache only supports octmaps.
+  pub fn build_planet(
+    self,
+    gfx: &Gfx,
+    lod_planet_settings: LodPlanetSettings,
+    transform: Isometry3,
+    view_projection_matrix: Mat4,
+  ) -> SimpleLodRenderDataManager<LodPlanet<C, V, E>> {
+    let lod_planet = LodPlanet::new(lod_planet_settings, transform, self.volume, self.extractor);
+    SimpleLodRenderDataManager::new(gfx, lod_planet, view_projection_matrix)
+  }
+
+  pub fn build_planet_boxed(
+    self,
+    gfx: &Gfx,
+    lod_planet_settings: LodPlanetSettings,
+    transform: Isometry3,
+    view_projection_matrix: Mat4,
+  ) -> Box<dyn LodRenderDataManager<C>> {
+    Box::new(self.build_planet(gfx, lod_planet_settings, transform, view_projection_matrix))
+  }
 }
@@ -2,8 +2,10 @@ use std::sync::Arc;
 
 use ultraviolet::{Isometry3, Vec3};
 
+use job_queue::JobQueueMetrics;
+
+use crate::chunk::mesh::ChunkMesh;
 use crate::chunk::size::ChunkSize;
-use crate::lod::aabb::Aabb;
 use crate::lod::extract::LodExtractor;
 
 /// LOD chunk mesh.
@@ -11,6 +13,28 @@ pub trait LodChunkMesh: Default + Send + Sync + 'static {
   fn is_empty(&self) -> bool;
 
   fn clear(&mut self);
+
+  /// Gets the number of bytes allocated on the heap for this chunk mesh.
+  fn heap_size_in_bytes(&self) -> usize;
+
+  /// Calls `f` with each chunk mesh of this LOD chunk mesh, for example to transform its vertices.
+  fn for_each_chunk_mesh_mut(&mut self, f: impl FnMut(&mut ChunkMesh));
+}
+
+/// Observer that LOD is determined for, such as a camera, or an area of interest around an entity.
+#[derive(Copy, Clone, Debug)]
+pub struct LodObserver {
+  pub position: Vec3,
+  /// LOD factor of this observer, overriding the LOD factor of the manager when set.
+  pub lod_factor: Option<f32>,
+}
+
+impl LodObserver {
+  #[inline]
+  pub fn new(position: Vec3) -> Self { Self { position, lod_factor: None } }
+
+  #[inline]
+  pub fn with_lod_factor(position: Vec3, lod_factor: f32) -> Self { Self { position, lod_factor: Some(lod_factor) } }
 }
 
 /// Transforms a volume into chunk meshes while taking into account level of detail (LOD).
@@ -18,7 +42,17 @@ pub trait LodChunkMeshManager<C: ChunkSize>: LodChunkMeshManagerParameters {
   type Extractor: LodExtractor<C>;
   fn get_extractor(&self) -> &Self::Extractor;
 
-  fn update(&mut self, position: Vec3) -> (u32, Isometry3, Box<dyn Iterator<Item=(&Aabb, &Arc<<<Self as LodChunkMeshManager<C>>::Extractor as LodExtractor<C>>::Chunk>)> + '_>);
+  /// Updates the chunk meshes for the union of the nodes that `observers` require, with each node at the finest LOD
+  /// that any observer requires. Returns the transform into world space, and the active chunks along with the offset of
+  /// the local space of their root in the space of that transform (zero for a single root), and the minimum and maximum
+  /// point of a box in the space of that transform that contains them, for culling.
+  fn update_observers(&mut self, observers: &[LodObserver]) -> (Isometry3, Box<dyn Iterator<Item=(Vec3, (Vec3, Vec3), &Arc<<<Self as LodChunkMeshManager<C>>::Extractor as LodExtractor<C>>::Chunk>)> + '_>);
+
+  /// Updates the chunk meshes for a single observer at `position`.
+  #[inline]
+  fn update(&mut self, position: Vec3) -> (Isometry3, Box<dyn Iterator<Item=(Vec3, (Vec3, Vec3), &Arc<<<Self as LodChunkMeshManager<C>>::Extractor as LodExtractor<C>>::Chunk>)> + '_>) {
+    self.update_observers(&[LodObserver::new(position)])
+  }
 }
 
 /// Parameters for transformation, in a separate trait as they do not depend on the kind of chunks.
@@ -28,8 +62,35 @@ pub trait LodChunkMeshManagerParameters {
   fn get_lod_factor(&self) -> f32;
   fn get_lod_factor_mut(&mut self) -> &mut f32;
 
+  fn get_lod_hysteresis(&self) -> f32;
+  fn get_lod_hysteresis_mut(&mut self) -> &mut f32;
+
+  /// Sets the perspective projection that the viewer passed to `update` uses, for LOD metrics that depend on it.
+  fn set_projection(&mut self, _vertical_fov_radians: f32, _viewport_height: f32) {}
+
+  /// Gets the distance from an observer with `lod_factor` (the LOD factor of this manager when `None`) at which nodes
+  /// are replaced by their parent, relative to the size of the parent, taking LOD hysteresis into account. Used to
+  /// geomorph vertices towards their parent LOD before the parent replaces them. 0 when the LOD does not depend on the
+  /// distance to the observer.
+  fn get_geomorph_distance_per_size(&self, _lod_factor: Option<f32>) -> f32 { 0.0 }
+
+  /// Gets the transform from the local space of chunk meshes into world space.
+  fn get_transform(&self) -> Isometry3;
+  /// Sets the transform from the local space of chunk meshes into world space, which may change every frame without
+  /// invalidating any chunk meshes.
+  fn set_transform(&mut self, transform: Isometry3);
+
   fn get_fixed_lod_level(&self) -> Option<u8>;
   fn get_fixed_lod_level_mut(&mut self) -> &mut Option<u8>;
+
+  /// Gets the latest metrics of the job queue used to create chunks, if any.
+  fn get_job_queue_metrics(&self) -> Option<&JobQueueMetrics> { None }
+
+  /// Requests a snapshot of the job graph of the job queue used to create chunks (if any), which can be taken as a
+  /// Graphviz DOT string with [`take_job_graph_dot`](Self::take_job_graph_dot) after a later update.
+  fn request_job_graph_dot(&mut self) {}
+  /// Takes the latest requested job graph snapshot as a Graphviz DOT string, if it has been received.
+  fn take_job_graph_dot(&mut self) -> Option<String> { None }
 }
 
 // Box forwarders
@@ -42,7 +103,7 @@ impl<C: ChunkSize, E: LodExtractor<C>, T> LodChunkMeshManager<C> for Box<T> wher
   fn get_extractor(&self) -> &E { (**self).get_extractor() }
 
   #[inline]
-  fn update(&mut self, position: Vec3) -> (u32, Isometry3, Box<dyn Iterator<Item=(&Aabb, &Arc<<<Self as LodChunkMeshManager<C>>::Extractor as LodExtractor<C>>::Chunk>)> + '_>) { (**self).update(position) }
+  fn update_observers(&mut self, observers: &[LodObserver]) -> (Isometry3, Box<dyn Iterator<Item=(Vec3, (Vec3, Vec3), &Arc<<<Self as LodChunkMeshManager<C>>::Extractor as LodExtractor<C>>::Chunk>)> + '_>) { (**self).update_observers(observers) }
 }
 
 impl<T: LodChunkMeshManagerParameters + ?Sized> LodChunkMeshManagerParameters for Box<T> {
@@ -54,8 +115,32 @@ impl<T: LodChunkMeshManagerParameters + ?Sized> LodChunkMeshManagerParameters fo
   #[inline]
   fn get_lod_factor_mut(&mut self) -> &mut f32 { (**self).get_lod_factor_mut() }
 
+  #[inline]
+  fn get_lod_hysteresis(&self) -> f32 { (**self).get_lod_hysteresis() }
+  #[inline]
+  fn get_lod_hysteresis_mut(&mut self) -> &mut f32 { (**self).get_lod_hysteresis_mut() }
+
+  #[inline]
+  fn set_projection(&mut self, vertical_fov_radians: f32, viewport_height: f32) { (**self).set_projection(vertical_fov_radians, viewport_height) }
+
+  #[inline]
+  fn get_geomorph_distance_per_size(&self, lod_factor: Option<f32>) -> f32 { (**self).get_geomorph_distance_per_size(lod_factor) }
+
+  #[inline]
+  fn get_transform(&self) -> Isometry3 { (**self).get_transform() }
+  #[inline]
+  fn set_transform(&mut self, transform: Isometry3) { (**self).set_transform(transform) }
+
   #[inline]
   fn get_fixed_lod_level(&self) -> Option<u8> { (**self).get_fixed_lod_level() }
   #[inline]
   fn get_fixed_lod_level_mut(&mut self) -> &mut Option<u8> { (**self).get_fixed_lod_level_mut() }
+
+  #[inline]
+  fn get_job_queue_metrics(&self) -> Option<&JobQueueMetrics> { (**self).get_job_queue_metrics() }
+
+  #[inline]
+  fn request_job_graph_dot(&mut self) { (**self).request_job_graph_dot() }
+  #[inline]
+  fn take_job_graph_dot(&mut self) -> Option<String> { (**self).take_job_graph_dot() }
 }
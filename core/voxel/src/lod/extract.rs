@@ -1,6 +1,7 @@
-use job_queue::{DepKey, In};
+use job_queue::{CancellationToken, DepKey, In};
 
-use crate::chunk::mesh::Vertex;
+use crate::chunk::mesh::{ChunkMesh, Vertex};
+use crate::chunk::occlusion::AmbientOcclusionSettings;
 use crate::chunk::sample::MaybeCompressedChunkSampleArray;
 use crate::chunk::size::ChunkSize;
 use crate::lod::aabb::AabbWithSize;
@@ -24,12 +25,24 @@ pub trait LodExtractor<C: ChunkSize>: Clone + Send + Sync + 'static {
     empty_lod_chunk_mesh: Self::Chunk,
   ) -> (Self::JobInput, Self::DependenciesIterator<V>);
 
+  /// Creates the input of a job that extracts the chunk at `aabb` into `empty_lod_chunk_mesh` again, for example after
+  /// the samples it depends on are invalidated. The dependencies of the job already exist.
+  fn create_job_input(&self, aabb: AabbWithSize, empty_lod_chunk_mesh: Self::Chunk) -> Self::JobInput;
+
+  /// Extracts the chunk of `input` from the outputs of its dependencies. Stops early when `cancellation_token` is
+  /// cancelled, returning a partially extracted chunk that is meant to be discarded.
   fn run_job(
     &self,
     input: Self::JobInput,
     dependency_outputs: &[(Self::DependencyKey, LodJobOutput<MaybeCompressedChunkSampleArray<C>, Self::Chunk>)],
+    cancellation_token: &CancellationToken,
   ) -> Self::Chunk;
 
+  /// Gets the settings of the ambient occlusion of extracted chunks, which is applied after extraction by sampling the
+  /// volume around each vertex. Disabled by default.
+  #[inline]
+  fn ambient_occlusion_settings(&self) -> AmbientOcclusionSettings { AmbientOcclusionSettings::default() }
+
   fn update_render_data(
     &self,
     chunk: &Self::Chunk,
@@ -40,7 +53,7 @@ pub trait LodExtractor<C: ChunkSize>: Clone + Send + Sync + 'static {
 }
 
 /// Maximum depths at positive neighbors. A depth of 0 indicates that there is no neighbor.
-#[derive(Default, Copy, Clone, Debug)]
+#[derive(Default, Copy, Clone, Eq, PartialEq, Debug)]
 pub struct NeighborDepths {
   pub x: u8,
   pub y: u8,
@@ -69,10 +82,13 @@ impl<C: ChunkSize> LodExtractor<C> for () {
     ((), std::iter::empty())
   }
   #[inline]
+  fn create_job_input(&self, _aabb: AabbWithSize, _empty_lod_chunk_mesh: Self::Chunk) -> Self::JobInput {}
+  #[inline]
   fn run_job(
     &self,
     _input: Self::JobInput,
     _dependency_outputs: &[(Self::DependencyKey, LodJobOutput<MaybeCompressedChunkSampleArray<C>, Self::Chunk>)],
+    _cancellation_token: &CancellationToken,
   ) -> Self::Chunk {}
   #[inline]
   fn update_render_data(
@@ -89,4 +105,8 @@ impl LodChunkMesh for () {
   fn is_empty(&self) -> bool { false }
   #[inline]
   fn clear(&mut self) {}
+  #[inline]
+  fn heap_size_in_bytes(&self) -> usize { 0 }
+  #[inline]
+  fn for_each_chunk_mesh_mut(&mut self, _f: impl FnMut(&mut ChunkMesh)) {}
 }
@@ -1,6 +1,10 @@
 use std::marker::PhantomData;
 
+use job_queue::CancellationToken;
+
 use crate::chunk::mesh::{ChunkMesh, Vertex};
+use crate::chunk::morph::Geomorph;
+use crate::chunk::occlusion::AmbientOcclusionSettings;
 use crate::chunk::sample::MaybeCompressedChunkSampleArray;
 use crate::chunk::size::ChunkSize;
 use crate::lod::aabb::{Aabb, AabbWithSize};
@@ -15,7 +19,10 @@ use crate::volume::Volume;
 
 #[derive(Default, Copy, Clone, Debug)]
 #[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
-pub struct MarchingCubesExtractorSettings {}
+pub struct MarchingCubesExtractorSettings {
+  #[cfg_attr(feature = "serde", serde(default))]
+  pub ambient_occlusion: AmbientOcclusionSettings,
+}
 
 
 // Extractor
@@ -23,7 +30,7 @@ pub struct MarchingCubesExtractorSettings {}
 #[derive(Default, Copy, Clone)]
 pub struct MarchingCubesExtractor<C: ChunkSize> {
   marching_cubes: MarchingCubes<C>,
-  _settings: MarchingCubesExtractorSettings,
+  settings: MarchingCubesExtractorSettings,
 }
 
 impl<C: ChunkSize> LodExtractor<C> for MarchingCubesExtractor<C> {
@@ -40,26 +47,39 @@ impl<C: ChunkSize> LodExtractor<C> for MarchingCubesExtractor<C> {
     volume: V,
     empty_lod_chunk_mesh: Self::Chunk,
   ) -> (Self::JobInput, Self::DependenciesIterator<V>) {
-    let input = MarchingCubesJobInput { aabb, empty_lod_chunk_mesh };
+    let input = self.create_job_input(aabb, empty_lod_chunk_mesh);
     let dependencies = MarchingCubesJobDependenciesIterator::new(aabb.inner, volume);
     (input, dependencies)
   }
 
+  #[inline]
+  fn create_job_input(&self, aabb: AabbWithSize, empty_lod_chunk_mesh: Self::Chunk) -> Self::JobInput {
+    MarchingCubesJobInput { aabb, empty_lod_chunk_mesh }
+  }
+
   #[inline]
   fn run_job(
     &self,
     input: Self::JobInput,
     dependency_outputs: &[(Self::DependencyKey, LodJobOutput<MaybeCompressedChunkSampleArray<C>, Self::Chunk>)],
+    cancellation_token: &CancellationToken,
   ) -> Self::Chunk {
     if let (_, LodJobOutput::Sample(chunk_samples)) = &dependency_outputs[0] {
       let MarchingCubesJobInput { aabb, empty_lod_chunk_mesh: mut chunk } = input;
-      self.marching_cubes.extract_chunk(aabb.minimum_point(), aabb.step::<C>(), chunk_samples, &mut chunk.regular);
+      let min = aabb.minimum_point();
+      let step = aabb.step::<C>();
+      self.marching_cubes.extract_chunk(min, step, chunk_samples, &mut chunk.regular);
+      if cancellation_token.is_cancelled() { return chunk; }
+      Geomorph::new().apply(min, step, chunk_samples.as_ref(), &mut chunk.regular);
       chunk
     } else {
       panic!("Missing sample dependency output");
     }
   }
 
+  #[inline]
+  fn ambient_occlusion_settings(&self) -> AmbientOcclusionSettings { self.settings.ambient_occlusion }
+
   #[inline]
   fn update_render_data(&self, chunk: &Self::Chunk, vertices: &mut Vec<Vertex>, indices: &mut Vec<u16>, draws: &mut Vec<LodDraw>) {
     copy_chunk_vertices(&chunk.regular, vertices, indices, draws);
@@ -69,7 +89,7 @@ impl<C: ChunkSize> LodExtractor<C> for MarchingCubesExtractor<C> {
 impl<C: ChunkSize> MarchingCubesExtractor<C> {
   #[inline]
   pub fn new(marching_cubes: MarchingCubes<C>, settings: MarchingCubesExtractorSettings) -> Self {
-    Self { marching_cubes, _settings: settings }
+    Self { marching_cubes, settings }
   }
 }
 
@@ -147,4 +167,14 @@ impl LodChunkMesh for MarchingCubesLodChunkMesh {
   fn clear(&mut self) {
     self.regular.clear();
   }
+
+  #[inline]
+  fn heap_size_in_bytes(&self) -> usize {
+    self.regular.heap_size_in_bytes()
+  }
+
+  #[inline]
+  fn for_each_chunk_mesh_mut(&mut self, mut f: impl FnMut(&mut ChunkMesh)) {
+    f(&mut self.regular);
+  }
 }
@@ -4,6 +4,9 @@ pub mod chunk_mesh;
 pub mod render;
 
 pub mod octmap;
+pub mod paged;
+pub mod planet;
+pub mod sample_cache;
 
 pub mod marching_cubes;
 pub mod transvoxel;
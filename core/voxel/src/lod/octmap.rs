@@ -4,45 +4,83 @@ use std::sync::Arc;
 
 use profiling::scope;
 use rustc_hash::{FxHashMap, FxHashSet};
-use ultraviolet::{Isometry3, Vec3};
+use tracing::error;
+use ultraviolet::{DVec3, IVec3, Isometry3, Vec3};
 
-use job_queue::{Job, JobQueue, JobQueueMessage};
+use job_queue::{CancellationToken, Job, JobQueue, JobQueueMessage, JobQueueMetrics, Priority};
 
-use crate::chunk::sample::MaybeCompressedChunkSampleArray;
+use crate::chunk::occlusion::AmbientOcclusion;
+use crate::chunk::sample::{ChunkSampleQuantization, MaybeCompressedChunkSampleArray, MaybeCompressedChunkSamples};
 use crate::chunk::size::ChunkSize;
-use crate::lod::aabb::{Aabb, AabbSubdivide, PerAabbSubdivide};
-use crate::lod::chunk_mesh::{LodChunkMesh, LodChunkMeshManager, LodChunkMeshManagerParameters};
+use crate::lod::aabb::{Aabb, AabbSubdivide, PerAabbSubdivide, vec3_to_dvec3};
+use crate::lod::chunk_mesh::{LodChunkMesh, LodChunkMeshManager, LodChunkMeshManagerParameters, LodObserver};
 use crate::lod::extract::{LodExtractor, NeighborDepths};
+use crate::lod::sample_cache::ChunkSampleCache;
 use crate::volume::Volume;
 
 // Settings
 
+/// Metric that determines whether an octree node is subdivided.
+#[derive(Default, Copy, Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub enum LodMetric {
+  /// Subdivide nodes that are closer to the viewer than [`lod_factor`](LodOctmapSettings::lod_factor) times their size.
+  #[default]
+  Distance,
+  /// Subdivide nodes whose geometric error, projected onto the screen, is larger than
+  /// [`max_screen_space_error`](LodOctmapSettings::max_screen_space_error) pixels. The geometric error of a node is
+  /// estimated as the size of the cells of its chunk. Requires the projection to be set with
+  /// [`LodChunkMeshManagerParameters::set_projection`]. The LOD distance that follows from this is multiplied by
+  /// [`lod_factor`](LodOctmapSettings::lod_factor) (or the LOD factor of an observer), so that a factor of 2 subdivides
+  /// nodes at twice the distance.
+  ScreenSpaceError,
+}
+
 #[derive(Copy, Clone, Debug)]
 #[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
 pub struct LodOctmapSettings {
   pub root_size: u32,
+  pub lod_metric: LodMetric,
   pub lod_factor: f32,
+  pub max_screen_space_error: f32,
+  /// Fraction by which the LOD distance threshold of a subdivided node is increased before it is merged again, so that
+  /// a viewer near the threshold does not keep subdividing and merging that node. 0 disables hysteresis.
+  pub lod_hysteresis: f32,
   pub fixed_lod_level: Option<u8>,
+  /// Number of worker threads that run sampling and meshing jobs. When 0, jobs are instead run on the caller's thread
+  /// at the start of each [`LodOctmap::update`], making chunk creation deterministic (e.g., for tests).
   pub job_queue_worker_threads: usize,
   pub empty_lod_chunk_mesh_cache_size: usize,
+  pub sample_quantization: ChunkSampleQuantization,
+  /// Maximum number of bytes of samples of removed chunks to keep in memory, so that chunks that are requested again
+  /// shortly after being removed (e.g., when moving back and forth over a LOD boundary) do not have to be sampled
+  /// again. Chunk meshes are not kept, as they depend on the depths of their neighbors. 0 disables keeping samples.
+  pub removed_chunk_cache_byte_budget: usize,
 }
 impl LodOctmapSettings {
   #[inline]
   pub fn check(&self) {
     assert_ne!(self.root_size, 0, "Root size may not be 0");
     assert!(self.root_size.is_power_of_two(), "Root size {} must be a power of 2", self.root_size);
+    assert!(self.lod_hysteresis >= 0.0, "LOD hysteresis {} may not be negative", self.lod_hysteresis);
+    assert!(self.max_screen_space_error > 0.0, "Maximum screen-space error {} must be positive", self.max_screen_space_error);
   }
 }
 impl Default for LodOctmapSettings {
   fn default() -> Self {
     Self {
       root_size: 4096,
+      lod_metric: LodMetric::default(),
       lod_factor: 1.0,
+      max_screen_space_error: 32.0,
+      lod_hysteresis: 0.1,
       fixed_lod_level: None,
       job_queue_worker_threads: std::thread::available_parallelism().ok()
         .and_then(|p| NonZeroUsize::new(p.get().saturating_sub(1)))
         .unwrap_or(NonZeroUsize::new(7).unwrap()).get(),
       empty_lod_chunk_mesh_cache_size: 4096,
+      sample_quantization: ChunkSampleQuantization::default(),
+      removed_chunk_cache_byte_budget: 256 * 1024 * 1024,
     }
   }
 }
@@ -50,113 +88,532 @@ impl Default for LodOctmapSettings {
 // LOD octmap
 
 pub struct LodOctmap<C: ChunkSize, V: Volume, E: LodExtractor<C>> {
-  root_size: u32,
-  lod_factor: f32,
-  fixed_lod_level: Option<u8>,
-
   transform: Isometry3,
   transform_inversed: Isometry3,
+  shared: LodRootShared<C, V, E>,
+  root: LodRoot<C, V, E>,
+}
 
-  max_depth: u8,
-  volume: V,
-  extractor: E,
+impl<C: ChunkSize, V: Volume, E: LodExtractor<C>> LodOctmap<C, V, E> {
+  #[inline]
+  pub fn new(settings: LodOctmapSettings, transform: Isometry3, volume: V, extractor: E) -> Self {
+    Self::with_sample_cache(settings, transform, volume, extractor, None)
+  }
 
-  active_aabbs: FxHashSet<Aabb>,
-  keep_aabbs: FxHashSet<Aabb>,
-  prev_keep_aabbs: FxHashSet<Aabb>,
-  lod_chunk_meshes: FxHashMap<Aabb, Arc<E::Chunk>>,
-  empty_lod_chunk_mesh_cache: VecDeque<E::Chunk>,
-  empty_lod_chunk_mesh_cache_size: usize,
+  /// Creates an octmap that reads samples from `sample_cache` (if any) instead of sampling the volume, storing newly
+  /// sampled chunks in the cache.
+  pub fn with_sample_cache(settings: LodOctmapSettings, transform: Isometry3, volume: V, extractor: E, sample_cache: Option<Arc<ChunkSampleCache<C>>>) -> Self {
+    let root_size = settings.root_size;
+    let sample_quantization = settings.sample_quantization;
+    let sample = {
+      let sample_cache = sample_cache.clone();
+      move |key: LodJobKey, volume: V, cancellation_token: &CancellationToken| {
+        let aabb = key.aabb;
+        let chunk_samples = volume.sample_chunk(aabb.minimum_point(root_size), aabb.step::<C>(root_size), cancellation_token)
+          .quantize(sample_quantization);
+        if let Some(cache) = &sample_cache {
+          if !cancellation_token.is_cancelled() { // Samples of cancelled jobs may be incomplete.
+            cache.insert(aabb, &chunk_samples);
+          }
+        }
+        chunk_samples
+      }
+    };
+    let sample_occlusion = |_, volume: V| move |position| volume.sample_at(position);
+    let shared = LodRootShared::new(settings, extractor, sample, sample_occlusion, |_, _| {});
+    let shared = match sample_cache {
+      Some(sample_cache) => shared.with_sample_cache(sample_cache),
+      None => shared,
+    };
+    Self {
+      transform,
+      transform_inversed: transform.inversed(),
+      shared,
+      root: LodRoot::new([0, 0, 0], volume),
+    }
+  }
 
-  requested_meshing: FxHashSet<Aabb>,
-  requested_removal: FxHashSet<Aabb>,
-  job_queue: JobQueue<Aabb, E::DependencyKey, LodJobInput<V, E::JobInput>, LodJob<C, V, E>, LodJobOutput<MaybeCompressedChunkSampleArray<C>, E::Chunk>>,
+  #[inline]
+  pub fn get_max_lod_level(&self) -> u8 { self.shared.max_depth }
+
+  #[inline]
+  pub fn get_transform(&self) -> Isometry3 { self.transform }
+  /// Sets the transform from the local space of this octmap into world space, for example to move an orbiting or
+  /// rotating body every frame. Chunks are in local space, so none are invalidated. Observers are transformed into
+  /// local space by the next update.
+  #[inline]
+  pub fn set_transform(&mut self, transform: Isometry3) {
+    self.transform = transform;
+    self.transform_inversed = transform.inversed();
+  }
+
+  #[inline]
+  pub fn update(&mut self, position: Vec3) -> (u32, Isometry3, impl Iterator<Item=(&Aabb, &Arc<E::Chunk>)>) {
+    self.update_observers(&[LodObserver::new(position)])
+  }
+
+  /// Updates the chunk meshes for the union of the nodes that `observers` require, with each node at the finest LOD
+  /// that any observer requires. Without observers, only the root node is kept.
+  #[profiling::function]
+  pub fn update_observers(&mut self, observers: &[LodObserver]) -> (u32, Isometry3, impl Iterator<Item=(&Aabb, &Arc<E::Chunk>)>) {
+    let transform_inversed = self.transform_inversed;
+    self.root.set_observers(&self.shared, observers, |position| transform_inversed.transform_vec(position));
+
+    self.shared.run_inline_jobs();
+    let root = &mut self.root;
+    self.shared.process_messages(|_, message, empty_lod_chunk_mesh_cache| root.handle_message(message, empty_lod_chunk_mesh_cache));
+
+    self.root.update(&mut self.shared, None);
+
+    // Request metrics each update, which are received at the next update.
+    self.shared.request_metrics();
+
+    (self.shared.root_size, self.transform, self.root.active_chunks())
+  }
+
+  /// Invalidates the samples of the chunk at `aabb`, for example after editing the volume in that chunk. The chunk is
+  /// sampled again, and every chunk mesh that depends on its samples is extracted again, replacing the current chunk
+  /// mesh once completed. Cached samples of the chunk (if any) are removed from the sample cache, even if the samples of
+  /// the chunk are not used by any chunk mesh.
+  pub fn invalidate_chunk_samples(&mut self, aabb: Aabb) {
+    let root = &self.root;
+    self.shared.invalidate(LodJobKey::new(root.key, aabb.with_user_bit_unset()), |key| (key == root.key).then(|| root.volume().clone()));
+  }
+
+  pub fn clear(&mut self) {
+    self.root.clear();
+  }
 }
 
-impl<C: ChunkSize, V: Volume, E: LodExtractor<C>> LodOctmap<C, V, E> {
-  pub fn new(settings: LodOctmapSettings, transform: Isometry3, volume: V, extractor: E) -> Self {
+
+// Shared state of roots
+
+/// Soft limit on the number of submitted jobs that are not yet added to the job graph. Chunks that would exceed this are
+/// requested again at a later update, preventing fast camera movement from flooding the job queue.
+const JOB_SUBMISSION_CAPACITY: usize = 4096;
+
+/// Number of empty chunk meshes that are taken from the cache when invalidating the samples of a chunk, for the mesh jobs
+/// that are run again: typically those of the chunk itself and of its 7 negative neighbors at the same depth.
+const INVALIDATED_LOD_CHUNK_MESH_COUNT: usize = 8;
+
+pub(crate) type LodJobQueue<C, V, E> = JobQueue<
+  LodJobKey,
+  <E as LodExtractor<C>>::DependencyKey,
+  LodJobInput<V, <E as LodExtractor<C>>::JobInput>,
+  LodJob<C, V, E>,
+  LodJobOutput<MaybeCompressedChunkSampleArray<C>, <E as LodExtractor<C>>::Chunk>
+>;
+pub(crate) type LodJobQueueMessage<C, V, E> = JobQueueMessage<
+  LodJobKey,
+  <E as LodExtractor<C>>::DependencyKey,
+  LodJobInput<V, <E as LodExtractor<C>>::JobInput>,
+  LodJobOutput<MaybeCompressedChunkSampleArray<C>, <E as LodExtractor<C>>::Chunk>
+>;
+
+/// State shared by all roots whose chunks are created by the same job queue: the LOD parameters, the extractor, and the
+/// job queue itself.
+pub(crate) struct LodRootShared<C: ChunkSize, V: Volume, E: LodExtractor<C>> {
+  pub(crate) root_size: u32,
+  pub(crate) lod_metric: LodMetric,
+  pub(crate) lod_factor: f32,
+  pub(crate) max_screen_space_error: f32,
+  /// Number of pixels that one unit of geometric error at distance 1 is projected to.
+  pub(crate) projection_scale: f32,
+  pub(crate) lod_hysteresis: f32,
+  pub(crate) fixed_lod_level: Option<u8>,
+  pub(crate) max_depth: u8,
+  pub(crate) extractor: E,
+
+  empty_lod_chunk_mesh_cache: EmptyLodChunkMeshCache<E::Chunk>,
+  pub(crate) job_queue_metrics: Option<JobQueueMetrics>,
+  pub(crate) job_graph_dot: Option<String>,
+  job_queue: LodJobQueue<C, V, E>,
+  sample_cache: Option<Arc<ChunkSampleCache<C>>>,
+}
+
+impl<C: ChunkSize, V: Volume, E: LodExtractor<C>> LodRootShared<C, V, E> {
+  /// Creates the shared state with a job queue that samples chunks with `sample`, which is given the key of the sample
+  /// job, the volume of the root of the chunk, and the cancellation token of the job. Ambient occlusion of each extracted
+  /// chunk mesh samples the volume with the function that `sample_occlusion` creates for the key and volume of its mesh
+  /// job, at positions in the space of its vertices. Each extracted chunk mesh is then passed to `transform_mesh` along with the key of its mesh job.
+  pub(crate) fn new<S: Fn(Vec3) -> f32>(
+    settings: LodOctmapSettings,
+    extractor: E,
+    sample: impl Fn(LodJobKey, V, &CancellationToken) -> MaybeCompressedChunkSampleArray<C> + Clone + Send + 'static,
+    sample_occlusion: impl Fn(LodJobKey, V) -> S + Clone + Send + 'static,
+    transform_mesh: impl Fn(LodJobKey, &mut E::Chunk) + Clone + Send + 'static,
+  ) -> Self {
     settings.check();
     let root_size = settings.root_size;
     let lod_0_step = root_size / C::CELLS_IN_CHUNK_ROW;
     let max_depth = lod_0_step.ilog2() as u8;
+    assert!(max_depth <= Aabb::MAX_DEPTH, "Root size {} requires depth {}, which is deeper than the maximum AABB depth {}", root_size, max_depth, Aabb::MAX_DEPTH);
+    let handler = {
+      let extractor = extractor.clone();
+      let ambient_occlusion = AmbientOcclusion::new(extractor.ambient_occlusion_settings());
+      move |key: LodJobKey, input: LodJobInput<V, E::JobInput>, dependency_outputs: &[(E::DependencyKey, LodJobOutput<MaybeCompressedChunkSampleArray<C>, E::Chunk>)], cancellation_token: &CancellationToken| {
+        // The output of a cancelled job is discarded, so return an empty output instead of sampling or meshing. Sampling
+        // and meshing also check the cancellation token while running, to stop early.
+        match input {
+          LodJobInput::Sample(_) if cancellation_token.is_cancelled() => LodJobOutput::Sample(Arc::new(MaybeCompressedChunkSamples::Zero)),
+          LodJobInput::Sample(volume) => LodJobOutput::Sample(Arc::new(sample(key, volume, cancellation_token))),
+          LodJobInput::Mesh(..) if cancellation_token.is_cancelled() => LodJobOutput::Mesh(Arc::new(E::Chunk::default())),
+          LodJobInput::Mesh(volume, input) => {
+            let mut lod_chunk_mesh = extractor.run_job(input, dependency_outputs, cancellation_token);
+            if ambient_occlusion.is_enabled() && !cancellation_token.is_cancelled() {
+              let sample = sample_occlusion(key, volume);
+              let step = key.aabb.step::<C>(root_size);
+              lod_chunk_mesh.for_each_chunk_mesh_mut(|chunk_mesh| ambient_occlusion.apply(step, &sample, chunk_mesh));
+            }
+            transform_mesh(key, &mut lod_chunk_mesh);
+            LodJobOutput::Mesh(Arc::new(lod_chunk_mesh))
+          }
+        }
+      }
+    };
+    let job_queue = if settings.job_queue_worker_threads == 0 {
+      JobQueue::new_inline(4096, handler)
+    } else {
+      JobQueue::new(
+        settings.job_queue_worker_threads,
+        settings.job_queue_worker_threads * 2,
+        4096,
+        handler,
+      ).unwrap_or_else(|e| panic!("Failed to create job queue: {:?}", e))
+    }.with_submission_capacity(JOB_SUBMISSION_CAPACITY);
+    let job_queue = if settings.removed_chunk_cache_byte_budget > 0 {
+      job_queue.with_output_cache(settings.removed_chunk_cache_byte_budget, |output| match output {
+        LodJobOutput::Sample(chunk_samples) => Some(chunk_samples.size_in_bytes()),
+        LodJobOutput::Mesh(_) => None, // Chunk meshes also depend on the neighbor depths in their job input.
+      })
+    } else {
+      job_queue
+    };
     Self {
       root_size,
+      lod_metric: settings.lod_metric,
       lod_factor: settings.lod_factor,
+      max_screen_space_error: settings.max_screen_space_error,
+      projection_scale: projection_scale(60.0f32.to_radians(), 1080.0),
+      lod_hysteresis: settings.lod_hysteresis,
       fixed_lod_level: settings.fixed_lod_level,
+      max_depth,
+      extractor,
 
-      transform,
-      transform_inversed: transform.inversed(),
+      empty_lod_chunk_mesh_cache: EmptyLodChunkMeshCache::new(settings.empty_lod_chunk_mesh_cache_size),
+      job_queue_metrics: None,
+      job_graph_dot: None,
+      job_queue,
+      sample_cache: None,
+    }
+  }
 
-      max_depth,
+  /// Completes sample jobs with samples from `sample_cache` when cached, without scheduling them. The cache is keyed by
+  /// AABB only, so this must only be used for the shared state of a single root.
+  pub(crate) fn with_sample_cache(self, sample_cache: Arc<ChunkSampleCache<C>>) -> Self {
+    let job_queue = {
+      let sample_cache = sample_cache.clone();
+      self.job_queue.with_output_loader(move |job: &LodJob<C, V, E>| match job.input {
+        LodJobInput::Sample(_) => sample_cache.get(job.key.aabb).map(|chunk_samples| LodJobOutput::Sample(Arc::new(chunk_samples))),
+        LodJobInput::Mesh(..) => None,
+      })
+    };
+    Self { job_queue, sample_cache: Some(sample_cache), ..self }
+  }
+
+  /// Gets the distance from an observer under which nodes are subdivided, relative to their size, for an observer with
+  /// `lod_factor`.
+  #[inline]
+  pub(crate) fn lod_distance_per_size(&self, lod_factor: f32) -> f32 {
+    match self.lod_metric {
+      LodMetric::Distance => lod_factor,
+      LodMetric::ScreenSpaceError => {
+        // Geometric error (the size of a cell) projected at distance `d` is `geometric_error * projection_scale / d`
+        // pixels, which is larger than the maximum screen-space error when `d` is smaller than the LOD distance.
+        let geometric_error_per_size = 1.0 / C::CELLS_IN_CHUNK_ROW_F32;
+        lod_factor * geometric_error_per_size * self.projection_scale / self.max_screen_space_error
+      }
+    }
+  }
+
+  #[inline]
+  pub(crate) fn set_projection(&mut self, vertical_fov_radians: f32, viewport_height: f32) {
+    self.projection_scale = projection_scale(vertical_fov_radians, viewport_height);
+  }
+
+  /// Gets the distance from an observer with `lod_factor` at which nodes are merged into their parent, relative to the
+  /// size of the parent. Matches `is_terminal`: nodes that were subdivided are merged at a larger distance due to
+  /// hysteresis.
+  #[inline]
+  pub(crate) fn get_geomorph_distance_per_size(&self, lod_factor: Option<f32>) -> f32 {
+    if self.fixed_lod_level.is_some() { return 0.0; }
+    self.lod_distance_per_size(lod_factor.unwrap_or(self.lod_factor)) * (1.0 + self.lod_hysteresis)
+  }
+
+  #[inline]
+  pub(crate) fn run_inline_jobs(&mut self) {
+    if self.job_queue.is_inline() {
+      scope!("Run inline jobs");
+      self.job_queue.run_until_idle();
+    }
+  }
+
+  /// Processes messages from the job queue, passing messages about jobs to `handle` along with the key of their job and
+  /// the cache of empty chunk meshes.
+  #[profiling::function]
+  pub(crate) fn process_messages(&mut self, mut handle: impl FnMut(LodJobKey, LodJobQueueMessage<C, V, E>, &mut EmptyLodChunkMeshCache<E::Chunk>)) {
+    for message in self.job_queue.get_message_receiver().try_iter() {
+      let key = match &message {
+        JobQueueMessage::Metrics(metrics) => {
+          self.job_queue_metrics = Some(**metrics);
+          continue;
+        }
+        JobQueueMessage::JobGraph(job_graph) => {
+          self.job_graph_dot = Some(job_graph.to_dot());
+          continue;
+        }
+        JobQueueMessage::QueueEmpty => continue,
+        JobQueueMessage::JobCompleted(key, _) | JobQueueMessage::PendingJobRemoved(key, _) | JobQueueMessage::CompletedJobRemoved(key, _) => *key,
+        JobQueueMessage::RunningJobRemoved(key) | JobQueueMessage::JobFailed(key, _) | JobQueueMessage::FailedJobRemoved(key) => *key,
+      };
+      handle(key, message, &mut self.empty_lod_chunk_mesh_cache);
+    }
+  }
+
+  #[inline]
+  pub(crate) fn request_metrics(&mut self) {
+    self.job_queue.request_metrics().unwrap_or_else(|_| self.handle_send_error());
+  }
+
+  #[inline]
+  pub(crate) fn request_job_graph_dot(&mut self) {
+    self.job_queue.request_job_graph().unwrap_or_else(|_| self.handle_send_error());
+  }
+
+  /// Invalidates the sample job with `key`, running it again along with the mesh jobs that depend on it. `volume` gets
+  /// the volume of the root at a key, or `None` if that root is not loaded, in which case its jobs are not run again.
+  pub(crate) fn invalidate(&mut self, key: LodJobKey, volume: impl Fn([i32; 3]) -> Option<V>) {
+    if let Some(sample_cache) = &self.sample_cache {
+      sample_cache.remove(key.aabb); // Stale samples must not be loaded when the sample job is added again later.
+    }
+    // Mesh jobs that depend on the samples of a chunk are in the root of the chunk or in its negative neighbors.
+    let [x, y, z] = key.root;
+    let volumes: Vec<([i32; 3], V)> = (0..8)
+      .map(|i| [x - (i & 1), y - ((i >> 1) & 1), z - ((i >> 2) & 1)])
+      .filter_map(|root| volume(root).map(|volume| (root, volume)))
+      .collect();
+    let mut empty_lod_chunk_meshes: Vec<_> = (0..INVALIDATED_LOD_CHUNK_MESH_COUNT).map(|_| self.empty_lod_chunk_mesh_cache.pop()).collect();
+    let extractor = self.extractor.clone();
+    let root_size = self.root_size;
+    let create_input = move |key: &LodJobKey| {
+      let volume = volumes.iter().find(|(root, _)| *root == key.root)?.1.clone();
+      if key.aabb.is_user_bit_set() {
+        let empty_lod_chunk_mesh = empty_lod_chunk_meshes.pop().unwrap_or_default();
+        let input = extractor.create_job_input(key.aabb.with_size(root_size), empty_lod_chunk_mesh);
+        Some(LodJobInput::Mesh(volume, input))
+      } else {
+        Some(LodJobInput::Sample(volume))
+      }
+    };
+    self.job_queue.invalidate(key, create_input).unwrap_or_else(|_| self.handle_send_error());
+  }
+
+  fn handle_send_error(&mut self) {
+    if let Err(e) = self.job_queue.take_and_join() {
+      std::panic::resume_unwind(e);
+    } else {
+      panic!("Communicating with the job queue failed, but it did not panic");
+    }
+  }
+}
+
+/// Cache of cleared chunk meshes, which are reused for new chunks to prevent allocations.
+pub(crate) struct EmptyLodChunkMeshCache<M> {
+  cache: VecDeque<M>,
+  size: usize,
+}
+
+impl<M: LodChunkMesh> EmptyLodChunkMeshCache<M> {
+  #[inline]
+  fn new(size: usize) -> Self { Self { cache: VecDeque::with_capacity(size), size } }
+
+  /// Clears and caches the chunk mesh in `arc` if the cache is not full and there are no other references to it.
+  #[inline]
+  pub(crate) fn push(&mut self, arc: Arc<M>) {
+    if self.cache.len() >= self.size { return; }
+    if let Ok(mut lod_chunk_mesh) = Arc::try_unwrap(arc) {
+      lod_chunk_mesh.clear();
+      self.cache.push_back(lod_chunk_mesh);
+    }
+  }
+
+  #[inline]
+  fn pop(&mut self) -> M {
+    self.cache.pop_front().unwrap_or_default()
+  }
+}
+
+
+// Root
+
+/// Geometry of the nodes of a root in the local space of the root, which determines their LOD.
+pub(crate) trait LodRootGeometry: Send + 'static {
+  /// Whether the node at `aabb` exists. Nodes that do not exist are never kept nor meshed, and the root node always
+  /// exists.
+  #[inline]
+  fn contains(&self, _root_size: u32, _aabb: Aabb) -> bool { true }
+
+  /// Gets the distance from `position` to the node at `aabb`. Uses double precision, as single precision distances lose
+  /// precision in large and deep octrees.
+  #[inline]
+  fn distance_from(&self, root_size: u32, aabb: Aabb, position: DVec3) -> f64 { aabb.distance_from_f64(root_size, position) }
+
+  /// Gets the size of the node at `aabb`.
+  #[inline]
+  fn size(&self, root_size: u32, aabb: Aabb) -> f64 { aabb.size(root_size) as f64 }
+}
+
+/// Geometry of an octmap, where nodes are cubes in the local space of the root.
+pub(crate) struct CubeGeometry;
+
+impl LodRootGeometry for CubeGeometry {}
+
+/// Octree of a single root, whose chunks are created by the job queue of a [`LodRootShared`], which may be shared with
+/// other roots.
+pub(crate) struct LodRoot<C: ChunkSize, V: Volume, E: LodExtractor<C>, G = CubeGeometry> {
+  /// Grid coordinates of this root, which is the origin for a single octmap.
+  key: [i32; 3],
+  volume: V,
+  neighbor_volumes: Option<Box<NeighborVolumes<V>>>,
+  geometry: G,
+
+  observers: Vec<LocalLodObserver>,
+  active_aabbs: FxHashSet<Aabb>,
+  keep_aabbs: FxHashSet<Aabb>,
+  prev_keep_aabbs: FxHashSet<Aabb>,
+  lod_chunk_meshes: FxHashMap<Aabb, Arc<E::Chunk>>,
+
+  requested_meshing: FxHashMap<Aabb, Priority>,
+  requested_removal: FxHashSet<Aabb>,
+  /// Chunks to create mesh jobs for, which are only created when the job queue accepts them, so that rejected chunks do
+  /// not take an empty chunk mesh from the cache.
+  chunks_to_add: Vec<(Aabb, NeighborDepths, Priority)>,
+  aabbs_to_remove: Vec<Aabb>,
+  /// Neighbor depths that the requested chunks with neighbors in other roots were extracted with, to extract them again
+  /// when those neighbors change.
+  seam_neighbor_depths: FxHashMap<Aabb, NeighborDepths>,
+  aabbs_to_requeue: Vec<Aabb>,
+}
+
+impl<C: ChunkSize, V: Volume, E: LodExtractor<C>> LodRoot<C, V, E> {
+  #[inline]
+  pub(crate) fn new(key: [i32; 3], volume: V) -> Self { Self::with_geometry(key, volume, CubeGeometry) }
+}
+
+impl<C: ChunkSize, V: Volume, E: LodExtractor<C>, G: LodRootGeometry> LodRoot<C, V, E, G> {
+  pub(crate) fn with_geometry(key: [i32; 3], volume: V, geometry: G) -> Self {
+    Self {
+      key,
       volume,
-      extractor: extractor.clone(),
+      neighbor_volumes: None,
+      geometry,
 
+      observers: Vec::new(),
       active_aabbs: FxHashSet::default(),
       keep_aabbs: FxHashSet::default(),
       prev_keep_aabbs: FxHashSet::default(),
       lod_chunk_meshes: FxHashMap::default(),
-      empty_lod_chunk_mesh_cache: VecDeque::with_capacity(settings.empty_lod_chunk_mesh_cache_size),
-      empty_lod_chunk_mesh_cache_size: settings.empty_lod_chunk_mesh_cache_size,
 
-      requested_meshing: FxHashSet::default(),
+      requested_meshing: FxHashMap::default(),
       requested_removal: FxHashSet::default(),
-      job_queue: JobQueue::new(
-        settings.job_queue_worker_threads,
-        settings.job_queue_worker_threads * 2,
-        4096,
-        move |aabb: Aabb, input: LodJobInput<V, E::JobInput>, dependency_outputs: &[(E::DependencyKey, LodJobOutput<MaybeCompressedChunkSampleArray<C>, E::Chunk>)]| {
-          match input {
-            LodJobInput::Sample(volume) => {
-              LodJobOutput::Sample(Arc::new(volume.sample_chunk(aabb.minimum_point(root_size), aabb.step::<C>(root_size))))
-            }
-            LodJobInput::Mesh(input) => {
-              let lod_chunk_mesh = extractor.run_job(input, dependency_outputs);
-              LodJobOutput::Mesh(Arc::new(lod_chunk_mesh))
-            }
-          }
-        }).unwrap_or_else(|e| panic!("Failed to create job queue: {:?}", e)),
+      chunks_to_add: Vec::new(),
+      aabbs_to_remove: Vec::new(),
+      seam_neighbor_depths: FxHashMap::default(),
+      aabbs_to_requeue: Vec::new(),
     }
   }
 
   #[inline]
-  pub fn get_max_lod_level(&self) -> u8 { self.max_depth }
+  pub(crate) fn volume(&self) -> &V { &self.volume }
 
-  #[profiling::function]
-  pub fn update(&mut self, position: Vec3) -> (u32, Isometry3, impl Iterator<Item=(&Aabb, &Arc<E::Chunk>)>) {
-    let position = self.transform_inversed.transform_vec(position);
+  #[cfg(test)]
+  pub(crate) fn seam_neighbor_depths(&self) -> &FxHashMap<Aabb, NeighborDepths> { &self.seam_neighbor_depths }
 
-    {
-      scope!("Process job queue messages");
-      for message in self.job_queue.get_message_receiver().try_iter() {
-        match message {
-          JobQueueMessage::JobCompleted(job_key, output) => {
-            if let (aabb, LodJobOutput::Mesh(arc)) = (job_key, output) {
-              self.lod_chunk_meshes.insert(aabb, arc);
-              self.requested_meshing.remove(&aabb);
-              self.requested_removal.remove(&aabb); // TODO: is this needed?
-            }
-          }
-          JobQueueMessage::PendingJobRemoved(aabb, _) => {
-            if aabb.is_user_bit_set() {
-              self.requested_removal.remove(&aabb);
-            }
-          }
-          JobQueueMessage::RunningJobRemoved(aabb) => {
-            if aabb.is_user_bit_set() {
-              self.requested_removal.remove(&aabb);
-            }
-          }
-          JobQueueMessage::CompletedJobRemoved(job_key, output) => {
-            if let (aabb, LodJobOutput::Mesh(arc)) = (job_key, output) {
-              self.requested_removal.remove(&aabb);
-              Self::cache_empty_lod_chunk_mesh(&mut self.empty_lod_chunk_mesh_cache, self.empty_lod_chunk_mesh_cache_size, arc);
-            }
+  /// Sets the volumes of the positive neighbors of this root, with which chunks of those roots that the chunks of this
+  /// root depend on are sampled. Only affects jobs that are added afterwards.
+  #[inline]
+  pub(crate) fn set_neighbor_volumes(&mut self, neighbor_volumes: Option<Box<NeighborVolumes<V>>>) {
+    self.neighbor_volumes = neighbor_volumes;
+  }
+
+  /// Gets the depth to stitch chunks of a neighboring root to the node at `aabb` of this root with: the depth of the
+  /// kept node that contains it, or one level deeper if it is subdivided. Deeper nodes are stitched to in the same way
+  /// as nodes one level deeper, so that chunks are not extracted again whenever these are subdivided. At least 1, as 0
+  /// indicates that there is no neighbor.
+  pub(crate) fn stitch_depth(&self, aabb: Aabb) -> u8 {
+    let Some(kept) = std::iter::successors(Some(aabb), Aabb::parent).find(|aabb| self.keep_aabbs.contains(aabb)) else { return 1; };
+    let depth = kept.depth();
+    let subdivided = kept == aabb && depth < Aabb::MAX_DEPTH && self.keep_aabbs.contains(&aabb.subdivide().base);
+    if subdivided { depth + 1 } else { depth.max(1) }
+  }
+
+  /// Sets the observers of the next update, transforming their positions into the local space of this root with
+  /// `to_local`.
+  #[inline]
+  pub(crate) fn set_observers(&mut self, shared: &LodRootShared<C, V, E>, observers: &[LodObserver], to_local: impl Fn(Vec3) -> Vec3) {
+    self.observers.clear();
+    for observer in observers {
+      let position = vec3_to_dvec3(to_local(observer.position));
+      let lod_distance_per_size = shared.lod_distance_per_size(observer.lod_factor.unwrap_or(shared.lod_factor)) as f64;
+      self.observers.push(LocalLodObserver { position, lod_distance_per_size });
+    }
+  }
+
+  pub(crate) fn handle_message(&mut self, message: LodJobQueueMessage<C, V, E>, empty_lod_chunk_mesh_cache: &mut EmptyLodChunkMeshCache<E::Chunk>) {
+    match message {
+      JobQueueMessage::JobCompleted(LodJobKey { aabb, .. }, output) => {
+        if let LodJobOutput::Mesh(arc) = output {
+          if let Some(prev_arc) = self.lod_chunk_meshes.insert(aabb, arc) { // Chunk was meshed again after invalidation.
+            empty_lod_chunk_mesh_cache.push(prev_arc);
           }
-          _ => {}
+          self.requested_meshing.remove(&aabb);
+          self.requested_removal.remove(&aabb); // TODO: is this needed?
         }
       }
+      JobQueueMessage::PendingJobRemoved(LodJobKey { aabb, .. }, _) => {
+        if aabb.is_user_bit_set() {
+          self.requested_removal.remove(&aabb);
+        }
+      }
+      JobQueueMessage::RunningJobRemoved(LodJobKey { aabb, .. }) => {
+        if aabb.is_user_bit_set() {
+          self.requested_removal.remove(&aabb);
+        }
+      }
+      JobQueueMessage::CompletedJobRemoved(LodJobKey { aabb, .. }, output) => {
+        if let LodJobOutput::Mesh(arc) = output {
+          self.requested_removal.remove(&aabb);
+          empty_lod_chunk_mesh_cache.push(arc);
+        }
+      }
+      JobQueueMessage::JobFailed(key, error) => {
+        // Keep the failed chunk in `requested_meshing` so that it is not requested again while it is kept. Its parent
+        // stays active instead, as the chunk is never filled.
+        error!("Creating LOD chunk {:?} failed: {}", key, error);
+      }
+      JobQueueMessage::FailedJobRemoved(LodJobKey { aabb, .. }) => {
+        if aabb.is_user_bit_set() {
+          self.requested_removal.remove(&aabb);
+        }
+      }
+      _ => {}
     }
+  }
 
+  /// Updates the nodes of this root for its observers. Chunks with neighbors in other roots are stitched to the depth
+  /// that `seam_depth` returns for the neighboring node, given the offset of its root and its AABB in that root, and are
+  /// extracted again when that depth changes. Without `seam_depth`, the root has no neighbors.
+  pub(crate) fn update(&mut self, shared: &mut LodRootShared<C, V, E>, seam_depth: Option<&dyn Fn(IVec3, Aabb) -> u8>) {
     {
       scope!("Clear active/keep AABBs");
       self.active_aabbs.clear();
@@ -164,63 +621,134 @@ impl<C: ChunkSize, V: Volume, E: LodExtractor<C>> LodOctmap<C, V, E> {
       self.keep_aabbs.drain().collect_into(&mut self.prev_keep_aabbs);
     }
 
-    self.update_root_node(position);
+    self.update_root_node(shared);
+
+    if let Some(seam_depth) = seam_depth {
+      self.requeue_seam_chunks(shared, seam_depth);
+    }
 
     {
-      scope!("Process removed AABBs");
-      let mut send_error = false;
-      for removed in self.prev_keep_aabbs.difference(&self.keep_aabbs) { // OPTO: can we update `prev_keep_aabbs` and then drain it?
-        if !self.requested_removal.contains(removed) {
-          self.requested_meshing.remove(&removed);
-          self.requested_removal.insert(*removed);
-          send_error |= self.job_queue.try_remove_job_and_orphaned_dependencies(*removed).is_err();
-          if send_error { break; }
-          if let Some(arc) = self.lod_chunk_meshes.remove(removed) {
-            Self::cache_empty_lod_chunk_mesh(&mut self.empty_lod_chunk_mesh_cache, self.empty_lod_chunk_mesh_cache_size, arc);
+      scope!("Add jobs");
+      // Only chunks whose job was accepted are requested. The rest are requested again at a later update.
+      let mut chunks = self.chunks_to_add.drain(..);
+      let requested_meshing = &mut self.requested_meshing;
+      let requested_removal = &mut self.requested_removal;
+      let seam_neighbor_depths = &mut self.seam_neighbor_depths;
+      let key = self.key;
+      let volume = &self.volume;
+      let neighbor_volumes = &self.neighbor_volumes;
+      let root_size = shared.root_size;
+      let extractor = &shared.extractor;
+      let empty_lod_chunk_mesh_cache = &mut shared.empty_lod_chunk_mesh_cache;
+      let accepted_jobs = chunks.by_ref().map(|(aabb, neighbor_depths, priority)| {
+        requested_meshing.insert(aabb, priority);
+        requested_removal.remove(&aabb); // TODO: is this needed?
+        let neighbor_depths = match seam_depth.and_then(|seam_depth| seam_neighbor_depths_of(aabb, neighbor_depths, seam_depth)) {
+          Some(neighbor_depths) => {
+            seam_neighbor_depths.insert(aabb, neighbor_depths);
+            neighbor_depths
           }
-        }
-      }
+          None => neighbor_depths,
+        };
+        let empty_lod_chunk_mesh = empty_lod_chunk_mesh_cache.pop();
+        let (input, dependencies) = extractor.create_job(aabb.with_size(root_size), neighbor_depths, volume.clone(), empty_lod_chunk_mesh);
+        let job = LodJob {
+          key: LodJobKey::new(key, aabb),
+          input: LodJobInput::Mesh(volume.clone(), input),
+          dependencies: Some(dependencies),
+          neighbor_volumes: neighbor_volumes.clone(),
+        };
+        (job, priority)
+      });
+      let send_error = shared.job_queue.try_add_jobs(accepted_jobs).is_err();
+      drop(chunks);
       if send_error {
-        self.handle_send_error();
+        shared.handle_send_error();
       }
     }
 
-    let chunks = self.lod_chunk_meshes.iter().filter(|(aabb, _)| self.active_aabbs.contains(*aabb));
-    (self.root_size, self.transform, chunks)
+    self.remove_unkept_aabbs(shared);
   }
 
-  pub fn clear(&mut self) {
+  /// Removes all nodes of this root, for example before dropping it.
+  pub(crate) fn remove_all(&mut self, shared: &mut LodRootShared<C, V, E>) {
+    self.active_aabbs.clear();
+    self.prev_keep_aabbs.clear();
+    self.keep_aabbs.drain().collect_into(&mut self.prev_keep_aabbs);
+    self.remove_unkept_aabbs(shared);
+  }
+
+  #[inline]
+  pub(crate) fn active_chunks(&self) -> impl Iterator<Item=(&Aabb, &Arc<E::Chunk>)> {
+    self.lod_chunk_meshes.iter().filter(|(aabb, _)| self.active_aabbs.contains(*aabb))
+  }
+
+  pub(crate) fn clear(&mut self) {
     self.keep_aabbs.clear();
     self.active_aabbs.clear();
     self.lod_chunk_meshes.clear();
+    self.seam_neighbor_depths.clear();
   }
 
-  #[inline]
-  fn cache_empty_lod_chunk_mesh(empty_lod_chunk_mesh_cache: &mut VecDeque<E::Chunk>, empty_lod_chunk_mesh_cache_size: usize, arc: Arc<E::Chunk>) {
-    if empty_lod_chunk_mesh_cache.len() >= empty_lod_chunk_mesh_cache_size { return; }
-    if let Ok(mut lod_chunk_mesh) = Arc::try_unwrap(arc) {
-      lod_chunk_mesh.clear();
-      empty_lod_chunk_mesh_cache.push_back(lod_chunk_mesh);
+  /// Queues the requested seam chunks whose neighbors in other roots changed depth since they were extracted, replacing
+  /// their jobs. Their current chunk mesh stays active until the new one is completed.
+  #[profiling::function]
+  fn requeue_seam_chunks(&mut self, shared: &mut LodRootShared<C, V, E>, seam_depth: &dyn Fn(IVec3, Aabb) -> u8) {
+    for (aabb, neighbor_depths) in &self.seam_neighbor_depths {
+      if !self.keep_aabbs.contains(aabb) { continue; } // Removed by `remove_unkept_aabbs` instead.
+      let Some(current_neighbor_depths) = seam_neighbor_depths_of(*aabb, *neighbor_depths, seam_depth) else { continue; };
+      if current_neighbor_depths != *neighbor_depths {
+        let priority = chunk_priority(&self.geometry, shared.root_size, *aabb, &self.observers);
+        self.chunks_to_add.push((*aabb, current_neighbor_depths, priority));
+        self.aabbs_to_requeue.push(*aabb);
+      }
+    }
+    if self.aabbs_to_requeue.is_empty() { return; }
+    // Remove the current jobs first, so that the jobs with the current neighbor depths replace them.
+    let key = self.key;
+    if shared.job_queue.try_remove_jobs_and_orphaned_dependencies(self.aabbs_to_requeue.drain(..).map(|aabb| LodJobKey::new(key, aabb))).is_err() {
+      shared.handle_send_error();
+    }
+  }
+
+  #[profiling::function]
+  fn remove_unkept_aabbs(&mut self, shared: &mut LodRootShared<C, V, E>) {
+    for removed in self.prev_keep_aabbs.difference(&self.keep_aabbs) { // OPTO: can we update `prev_keep_aabbs` and then drain it?
+      if !self.requested_removal.contains(removed) {
+        self.requested_meshing.remove(removed);
+        self.seam_neighbor_depths.remove(removed);
+        self.requested_removal.insert(*removed);
+        self.aabbs_to_remove.push(*removed);
+        if let Some(arc) = self.lod_chunk_meshes.remove(removed) {
+          shared.empty_lod_chunk_mesh_cache.push(arc);
+        }
+      }
+    }
+    let key = self.key;
+    if shared.job_queue.try_remove_jobs_and_orphaned_dependencies(self.aabbs_to_remove.drain(..).map(|aabb| LodJobKey::new(key, aabb))).is_err() {
+      shared.handle_send_error();
     }
   }
 
 
   #[profiling::function]
-  fn update_root_node(&mut self, position: Vec3) {
+  fn update_root_node(&mut self, shared: &mut LodRootShared<C, V, E>) {
     let root = Aabb::root().with_user_bit_set();
     let depth = 0;
-    let neighbor_depths = NeighborDepths::default();
-    let NodeResult { filled, activated, .. } = self.update_nodes(root, depth, neighbor_depths, position);
+    let observers = std::mem::take(&mut self.observers);
+    // The root node has no neighbors in this root. Neighbors in other roots are set when adding jobs.
+    let NodeResult { filled, activated, .. } = self.update_nodes(shared, root, depth, NeighborDepths::default(), &observers);
     if filled && !activated {
       self.active_aabbs.insert(root);
     }
+    self.observers = observers;
   }
 
   #[inline]
-  fn update_nodes(&mut self, aabb: Aabb, depth: u8, neighbor_depths: NeighborDepths, position: Vec3) -> NodeResult {
+  fn update_nodes(&mut self, shared: &mut LodRootShared<C, V, E>, aabb: Aabb, depth: u8, neighbor_depths: NeighborDepths, observers: &[LocalLodObserver]) -> NodeResult {
     self.keep_aabbs.insert(aabb);
-    let self_filled = self.update_chunk(aabb, neighbor_depths);
-    if self.is_terminal(aabb, depth, position) {
+    let self_filled = self.update_chunk(shared, aabb, neighbor_depths, observers);
+    if self.is_terminal(shared, aabb, depth, observers) {
       NodeResult::new(self_filled, false, depth)
     } else { // Subdivide
       let mut all_filled = true;
@@ -230,7 +758,7 @@ impl<C: ChunkSize, V: Volume, E: LodExtractor<C>> LodOctmap<C, V, E> {
       let subdivided @ AabbSubdivide { base, x, y, xy, z, xz, yz, xyz } = aabb.subdivide();
 
       let xyz_result = {
-        let result = self.update_nodes(xyz, depth_plus_one, neighbor_depths, position);
+        let result = self.update_child_nodes(shared, xyz, depth_plus_one, neighbor_depths, observers);
         activated.xyz = result.activated;
         all_filled &= result.filled;
         maximum_depth = maximum_depth.max(result.maximum_depth);
@@ -239,7 +767,7 @@ impl<C: ChunkSize, V: Volume, E: LodExtractor<C>> LodOctmap<C, V, E> {
       let yz_result = {
         let mut neighbor_depths = neighbor_depths;
         neighbor_depths.x = xyz_result.maximum_depth;
-        let result = self.update_nodes(yz, depth_plus_one, neighbor_depths, position);
+        let result = self.update_child_nodes(shared, yz, depth_plus_one, neighbor_depths, observers);
         activated.yz = result.activated;
         all_filled &= result.filled;
         maximum_depth = maximum_depth.max(result.maximum_depth);
@@ -248,7 +776,7 @@ impl<C: ChunkSize, V: Volume, E: LodExtractor<C>> LodOctmap<C, V, E> {
       let xz_result = {
         let mut neighbor_depths = neighbor_depths;
         neighbor_depths.y = xyz_result.maximum_depth;
-        let result = self.update_nodes(xz, depth_plus_one, neighbor_depths, position);
+        let result = self.update_child_nodes(shared, xz, depth_plus_one, neighbor_depths, observers);
         activated.xz = result.activated;
         all_filled &= result.filled;
         maximum_depth = maximum_depth.max(result.maximum_depth);
@@ -259,7 +787,7 @@ impl<C: ChunkSize, V: Volume, E: LodExtractor<C>> LodOctmap<C, V, E> {
         neighbor_depths.x = xz_result.maximum_depth;
         neighbor_depths.y = yz_result.maximum_depth;
         neighbor_depths.xy = xyz_result.maximum_depth;
-        let result = self.update_nodes(z, depth_plus_one, neighbor_depths, position);
+        let result = self.update_child_nodes(shared, z, depth_plus_one, neighbor_depths, observers);
         activated.z = result.activated;
         all_filled &= result.filled;
         maximum_depth = maximum_depth.max(result.maximum_depth);
@@ -268,7 +796,7 @@ impl<C: ChunkSize, V: Volume, E: LodExtractor<C>> LodOctmap<C, V, E> {
       let xy_result = {
         let mut neighbor_depths = neighbor_depths;
         neighbor_depths.z = xyz_result.maximum_depth;
-        let result = self.update_nodes(xy, depth_plus_one, neighbor_depths, position);
+        let result = self.update_child_nodes(shared, xy, depth_plus_one, neighbor_depths, observers);
         activated.xy = result.activated;
         all_filled &= result.filled;
         maximum_depth = maximum_depth.max(result.maximum_depth);
@@ -279,7 +807,7 @@ impl<C: ChunkSize, V: Volume, E: LodExtractor<C>> LodOctmap<C, V, E> {
         neighbor_depths.x = xy_result.maximum_depth;
         neighbor_depths.z = yz_result.maximum_depth;
         neighbor_depths.xz = xyz_result.maximum_depth;
-        let result = self.update_nodes(y, depth_plus_one, neighbor_depths, position);
+        let result = self.update_child_nodes(shared, y, depth_plus_one, neighbor_depths, observers);
         activated.y = result.activated;
         all_filled &= result.filled;
         maximum_depth = maximum_depth.max(result.maximum_depth);
@@ -290,7 +818,7 @@ impl<C: ChunkSize, V: Volume, E: LodExtractor<C>> LodOctmap<C, V, E> {
         neighbor_depths.y = xy_result.maximum_depth;
         neighbor_depths.z = xz_result.maximum_depth;
         neighbor_depths.yz = xyz_result.maximum_depth;
-        let result = self.update_nodes(x, depth_plus_one, neighbor_depths, position);
+        let result = self.update_child_nodes(shared, x, depth_plus_one, neighbor_depths, observers);
         activated.x = result.activated;
         all_filled &= result.filled;
         maximum_depth = maximum_depth.max(result.maximum_depth);
@@ -304,7 +832,7 @@ impl<C: ChunkSize, V: Volume, E: LodExtractor<C>> LodOctmap<C, V, E> {
         neighbor_depths.xy = xy_result.maximum_depth;
         neighbor_depths.yz = yz_result.maximum_depth;
         neighbor_depths.xz = xz_result.maximum_depth;
-        let result = self.update_nodes(base, depth_plus_one, neighbor_depths, position);
+        let result = self.update_child_nodes(shared, base, depth_plus_one, neighbor_depths, observers);
         activated.base = result.activated;
         all_filled &= result.filled;
         maximum_depth = maximum_depth.max(result.maximum_depth);
@@ -324,38 +852,92 @@ impl<C: ChunkSize, V: Volume, E: LodExtractor<C>> LodOctmap<C, V, E> {
     }
   }
 
+  /// Updates the nodes at child `aabb` if it exists.
   #[inline]
-  fn is_terminal(&self, aabb: Aabb, depth: u8, position: Vec3) -> bool {
-    if let Some(fixed_lod_level) = self.fixed_lod_level {
-      depth >= self.max_depth.min(fixed_lod_level)
+  fn update_child_nodes(&mut self, shared: &mut LodRootShared<C, V, E>, aabb: Aabb, depth: u8, neighbor_depths: NeighborDepths, observers: &[LocalLodObserver]) -> NodeResult {
+    if self.geometry.contains(shared.root_size, aabb) {
+      self.update_nodes(shared, aabb, depth, neighbor_depths, observers)
     } else {
-      depth >= self.max_depth || aabb.distance_from(self.root_size, position) > self.lod_factor * aabb.size(self.root_size) as f32
+      NodeResult::new(true, true, 0) // Act as filled and activated so that our parent does not activate it, without depth as there is no neighbor.
     }
   }
 
-  fn update_chunk(&mut self, aabb: Aabb, neighbor_depths: NeighborDepths) -> bool {
-    if self.lod_chunk_meshes.contains_key(&aabb) { return true; }
-    if !self.requested_meshing.contains(&aabb) {
-      let empty_lod_chunk_mesh = self.empty_lod_chunk_mesh_cache.pop_front().unwrap_or_else(|| E::Chunk::default());
-      let (input, dependencies) = self.extractor.create_job(aabb.with_size(self.root_size), neighbor_depths, self.volume.clone(), empty_lod_chunk_mesh);
-      let job = LodJob { aabb, input: LodJobInput::Mesh(input), dependencies: Some(dependencies) };
-      self.job_queue.try_add_job(job).unwrap_or_else(|_| self.handle_send_error());
-      self.requested_meshing.insert(aabb);
-      self.requested_removal.remove(&aabb); // TODO: is this needed?
+  #[inline]
+  fn is_terminal(&self, shared: &LodRootShared<C, V, E>, aabb: Aabb, depth: u8, observers: &[LocalLodObserver]) -> bool {
+    if let Some(fixed_lod_level) = shared.fixed_lod_level {
+      depth >= shared.max_depth.min(fixed_lod_level)
+    } else if depth >= shared.max_depth {
+      true
+    } else {
+      // Nodes that were subdivided at the previous update are merged at a larger distance than they are subdivided at.
+      let was_subdivided = self.prev_keep_aabbs.contains(&aabb.subdivide().base);
+      let size = self.geometry.size(shared.root_size, aabb);
+      let size = if was_subdivided { size * (1.0 + shared.lod_hysteresis as f64) } else { size };
+      // Terminal only when no observer requires the node to be subdivided.
+      observers.iter().all(|observer| self.geometry.distance_from(shared.root_size, aabb, observer.position) > observer.lod_distance_per_size * size)
     }
-    return false;
   }
 
-
-  fn handle_send_error(&mut self) {
-    if let Err(e) = self.job_queue.take_and_join() {
-      std::panic::resume_unwind(e);
+  fn update_chunk(&mut self, shared: &mut LodRootShared<C, V, E>, aabb: Aabb, neighbor_depths: NeighborDepths, observers: &[LocalLodObserver]) -> bool {
+    if self.lod_chunk_meshes.contains_key(&aabb) { return true; }
+    let priority = chunk_priority(&self.geometry, shared.root_size, aabb, observers);
+    if let Some(requested_priority) = self.requested_meshing.get_mut(&aabb) {
+      if *requested_priority != priority {
+        *requested_priority = priority;
+        shared.job_queue.update_priority(LodJobKey::new(self.key, aabb), priority).unwrap_or_else(|_| shared.handle_send_error());
+      }
     } else {
-      panic!("Communicating with the job queue failed, but it did not panic");
+      self.chunks_to_add.push((aabb, neighbor_depths, priority)); // Jobs are created and added after updating all nodes.
     }
+    false
   }
 }
 
+/// Gets `neighbor_depths` of the chunk at `aabb` with the neighbors that are in other roots set to the depth that
+/// `seam_depth` returns for them, or `None` if no neighbor is in another root.
+fn seam_neighbor_depths_of(aabb: Aabb, mut neighbor_depths: NeighborDepths, seam_depth: &dyn Fn(IVec3, Aabb) -> u8) -> Option<NeighborDepths> {
+  let mut is_seam = false;
+  let mut set_depth = |offset: IVec3, depth: &mut u8| {
+    let (root_offset, neighbor) = aabb.neighbor_across_roots(offset);
+    if root_offset != IVec3::zero() {
+      *depth = seam_depth(root_offset, neighbor);
+      is_seam = true;
+    }
+  };
+  set_depth(IVec3::new(1, 0, 0), &mut neighbor_depths.x);
+  set_depth(IVec3::new(0, 1, 0), &mut neighbor_depths.y);
+  set_depth(IVec3::new(0, 0, 1), &mut neighbor_depths.z);
+  set_depth(IVec3::new(1, 1, 0), &mut neighbor_depths.xy);
+  set_depth(IVec3::new(0, 1, 1), &mut neighbor_depths.yz);
+  set_depth(IVec3::new(1, 0, 1), &mut neighbor_depths.xz);
+  if is_seam { Some(neighbor_depths) } else { None }
+}
+
+/// Gets the job priority of the chunk at `aabb`: its distance to the nearest observer relative to its size, so that
+/// nearby and coarse chunks (which fill gaps in the octree) are meshed first.
+#[inline]
+fn chunk_priority(geometry: &impl LodRootGeometry, root_size: u32, aabb: Aabb, observers: &[LocalLodObserver]) -> Priority {
+  let distance = observers.iter()
+    .map(|observer| geometry.distance_from(root_size, aabb, observer.position))
+    .fold(f64::INFINITY, f64::min);
+  let relative_distance = distance / geometry.size(root_size, aabb);
+  (relative_distance * 256.0).min(Priority::MAX as f64) as Priority
+}
+
+
+/// Observer in the local space of a root.
+struct LocalLodObserver {
+  position: DVec3,
+  lod_distance_per_size: f64,
+}
+
+/// Gets the number of pixels that one unit at distance 1 is projected to, with a perspective projection with
+/// `vertical_fov_radians` onto a viewport `viewport_height` pixels high.
+#[inline]
+fn projection_scale(vertical_fov_radians: f32, viewport_height: f32) -> f32 {
+  viewport_height / (2.0 * (vertical_fov_radians * 0.5).tan())
+}
+
 
 // Octmap algorithm return type
 
@@ -375,68 +957,134 @@ impl NodeResult {
 
 // Job types
 
+/// Key of a LOD job: the chunk at `aabb` in the root at grid coordinates `root`. A single octmap only has the root at
+/// the origin.
+#[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug)]
+pub struct LodJobKey {
+  pub root: [i32; 3],
+  pub aabb: Aabb,
+}
+
+impl LodJobKey {
+  #[inline]
+  pub fn new(root: [i32; 3], aabb: Aabb) -> Self { Self { root, aabb } }
+}
+
 pub enum LodJobInput<V, JI> {
   Sample(V),
-  Mesh(JI),
+  /// Extracts a chunk mesh with the input of the extractor, where the volume is used for ambient occlusion.
+  Mesh(V, JI),
+}
+
+/// Volumes of the positive neighbors of a root, indexed by [`neighbor_volume_index`], with which chunks in those roots
+/// are sampled. `None` for neighbors that are not loaded.
+pub(crate) type NeighborVolumes<V> = [Option<V>; 7];
+
+/// Gets the index into [`NeighborVolumes`] of the neighbor at `root_offset`, or `None` if it is not a positive neighbor.
+#[inline]
+fn neighbor_volume_index([x, y, z]: [i32; 3]) -> Option<usize> {
+  let in_range = |c: i32| (0..=1).contains(&c);
+  (in_range(x) && in_range(y) && in_range(z) && [x, y, z] != [0, 0, 0]).then(|| (x + 2 * y + 4 * z - 1) as usize)
+}
+
+/// Creates the volumes of the positive neighbors of a root, where `volume` gets the volume of the neighbor at a root
+/// offset.
+#[inline]
+pub(crate) fn neighbor_volumes<V>(volume: impl Fn([i32; 3]) -> Option<V>) -> NeighborVolumes<V> {
+  [
+    volume([1, 0, 0]),
+    volume([0, 1, 0]),
+    volume([1, 1, 0]),
+    volume([0, 0, 1]),
+    volume([1, 0, 1]),
+    volume([0, 1, 1]),
+    volume([1, 1, 1]),
+  ]
 }
 
 pub struct LodJob<C: ChunkSize, V: Volume, E: LodExtractor<C>> {
-  aabb: Aabb,
+  key: LodJobKey,
   input: LodJobInput<V, E::JobInput>,
   dependencies: Option<E::DependenciesIterator<V>>,
+  neighbor_volumes: Option<Box<NeighborVolumes<V>>>,
 }
 
 impl<C: ChunkSize, V: Volume, E: LodExtractor<C>> LodJob<C, V, E> {
   #[inline]
   pub fn new_sample(aabb: Aabb, volume: V) -> Self {
+    Self::new_sample_in_root(IVec3::zero(), aabb, volume)
+  }
+
+  /// Creates a job that samples the chunk at `aabb` in the root at `root_offset` from the root of the job that depends
+  /// on it, for extractors that stitch chunks to neighboring chunks in other roots (see
+  /// [`Aabb::neighbor_across_roots`]). `volume` is the volume of the root of the depending job, which is replaced by the
+  /// volume of the neighboring root when the depending job has one.
+  #[inline]
+  pub fn new_sample_in_root(root_offset: IVec3, aabb: Aabb, volume: V) -> Self {
     Self {
-      aabb: aabb.with_user_bit_unset(),
+      key: LodJobKey::new(root_offset.into(), aabb.with_user_bit_unset()),
       input: LodJobInput::Sample(volume),
       dependencies: None,
+      neighbor_volumes: None,
     }
   }
 
   #[inline]
-  pub fn new_mesh(aabb: Aabb, extractor_job_input: E::JobInput, extractor_dependencies_iterator: E::DependenciesIterator<V>) -> Self {
+  pub fn new_mesh(aabb: Aabb, volume: V, extractor_job_input: E::JobInput, extractor_dependencies_iterator: E::DependenciesIterator<V>) -> Self {
     Self {
-      aabb: aabb.with_user_bit_set(),
-      input: LodJobInput::Mesh(extractor_job_input),
+      key: LodJobKey::new([0, 0, 0], aabb.with_user_bit_set()),
+      input: LodJobInput::Mesh(volume, extractor_job_input),
       dependencies: Some(extractor_dependencies_iterator),
+      neighbor_volumes: None,
     }
   }
 }
 
-impl<C: ChunkSize, V: Volume, E: LodExtractor<C>> Job<Aabb, E::DependencyKey, LodJobInput<V, E::JobInput>> for LodJob<C, V, E> {
+impl<C: ChunkSize, V: Volume, E: LodExtractor<C>> Job<LodJobKey, E::DependencyKey, LodJobInput<V, E::JobInput>> for LodJob<C, V, E> {
   #[inline]
-  fn key(&self) -> &Aabb { &self.aabb }
+  fn key(&self) -> &LodJobKey { &self.key }
 
   type DependencyIterator = LodJobDependencyIterator<C, V, E>;
 
   #[inline]
   fn into(self) -> (LodJobInput<V, E::JobInput>, Self::DependencyIterator) {
     let input = self.input;
-    let dependencies = LodJobDependencyIterator::<C, V, E>(self.dependencies);
+    let dependencies = LodJobDependencyIterator::<C, V, E> { root: self.key.root, dependencies: self.dependencies, neighbor_volumes: self.neighbor_volumes };
     (input, dependencies)
   }
 }
 
-#[repr(transparent)]
-pub struct LodJobDependencyIterator<C: ChunkSize, V: Volume, E: LodExtractor<C>>(Option<E::DependenciesIterator<V>>);
+/// Iterates over the dependencies of a job, whose roots are relative to `root` (the root of the depending job). Sample
+/// jobs in neighboring roots sample the volume of that root from `neighbor_volumes`, if loaded.
+pub struct LodJobDependencyIterator<C: ChunkSize, V: Volume, E: LodExtractor<C>> {
+  root: [i32; 3],
+  dependencies: Option<E::DependenciesIterator<V>>,
+  neighbor_volumes: Option<Box<NeighborVolumes<V>>>,
+}
 
 impl<C: ChunkSize, V: Volume, E: LodExtractor<C>> Iterator for LodJobDependencyIterator<C, V, E> {
   type Item = (E::DependencyKey, LodJob<C, V, E>);
 
   #[inline]
   fn next(&mut self) -> Option<Self::Item> {
-    match &mut self.0 {
-      Some(i) => i.next(),
+    match &mut self.dependencies {
+      Some(i) => i.next().map(|(dependency_key, mut job)| {
+        let [x, y, z] = job.key.root;
+        if let (LodJobInput::Sample(volume), Some(neighbor_volumes)) = (&mut job.input, &self.neighbor_volumes) {
+          if let Some(neighbor_volume) = neighbor_volume_index(job.key.root).and_then(|i| neighbor_volumes[i].as_ref()) {
+            *volume = neighbor_volume.clone();
+          }
+        }
+        job.key.root = [self.root[0] + x, self.root[1] + y, self.root[2] + z];
+        (dependency_key, job)
+      }),
       _ => None,
     }
   }
 
   #[inline]
   fn size_hint(&self) -> (usize, Option<usize>) {
-    match &self.0 {
+    match &self.dependencies {
       Some(i) => i.size_hint(),
       _ => (0, Some(0)),
     }
@@ -447,7 +1095,7 @@ impl<C: ChunkSize, V: Volume, E: LodExtractor<C>> ExactSizeIterator for LodJobDe
   E::DependenciesIterator<V>: ExactSizeIterator
 {
   fn len(&self) -> usize {
-    match &self.0 {
+    match &self.dependencies {
       Some(i) => i.len(),
       None => 0,
     }
@@ -476,27 +1124,224 @@ impl<C: ChunkSize, V: Volume, E: LodExtractor<C>> LodChunkMeshManager<C> for Lod
   type Extractor = E;
   #[inline]
   fn get_extractor(&self) -> &E {
-    &self.extractor
+    &self.shared.extractor
   }
 
   #[inline]
-  fn update(&mut self, position: Vec3) -> (u32, Isometry3, Box<dyn Iterator<Item=(&Aabb, &Arc<E::Chunk>)> + '_>) {
-    let (root_half_size, transform, chunks) = self.update(position);
-    (root_half_size, transform, Box::new(chunks))
+  fn update_observers(&mut self, observers: &[LodObserver]) -> (Isometry3, Box<dyn Iterator<Item=(Vec3, (Vec3, Vec3), &Arc<E::Chunk>)> + '_>) {
+    let (root_size, transform, chunks) = self.update_observers(observers);
+    (transform, Box::new(chunks.map(move |(aabb, chunk)| (Vec3::zero(), aabb.bounds(root_size), chunk))))
   }
 }
 
 impl<C: ChunkSize, V: Volume, E: LodExtractor<C>> LodChunkMeshManagerParameters for LodOctmap<C, V, E> {
   #[inline]
-  fn get_max_lod_level(&self) -> u8 { self.max_depth }
+  fn get_max_lod_level(&self) -> u8 { self.shared.max_depth }
+
+  #[inline]
+  fn get_lod_factor(&self) -> f32 { self.shared.lod_factor }
+  #[inline]
+  fn get_lod_factor_mut(&mut self) -> &mut f32 { &mut self.shared.lod_factor }
 
   #[inline]
-  fn get_lod_factor(&self) -> f32 { self.lod_factor }
+  fn get_lod_hysteresis(&self) -> f32 { self.shared.lod_hysteresis }
+  #[inline]
+  fn get_lod_hysteresis_mut(&mut self) -> &mut f32 { &mut self.shared.lod_hysteresis }
+
+  #[inline]
+  fn set_projection(&mut self, vertical_fov_radians: f32, viewport_height: f32) {
+    self.shared.set_projection(vertical_fov_radians, viewport_height);
+  }
+
   #[inline]
-  fn get_lod_factor_mut(&mut self) -> &mut f32 { &mut self.lod_factor }
+  fn get_geomorph_distance_per_size(&self, lod_factor: Option<f32>) -> f32 { self.shared.get_geomorph_distance_per_size(lod_factor) }
 
   #[inline]
-  fn get_fixed_lod_level(&self) -> Option<u8> { self.fixed_lod_level }
+  fn get_transform(&self) -> Isometry3 { self.transform }
   #[inline]
-  fn get_fixed_lod_level_mut(&mut self) -> &mut Option<u8> { &mut self.fixed_lod_level }
+  fn set_transform(&mut self, transform: Isometry3) { self.set_transform(transform) }
+
+  #[inline]
+  fn get_fixed_lod_level(&self) -> Option<u8> { self.shared.fixed_lod_level }
+  #[inline]
+  fn get_fixed_lod_level_mut(&mut self) -> &mut Option<u8> { &mut self.shared.fixed_lod_level }
+
+  #[inline]
+  fn get_job_queue_metrics(&self) -> Option<&JobQueueMetrics> { self.shared.job_queue_metrics.as_ref() }
+
+  #[inline]
+  fn request_job_graph_dot(&mut self) { self.shared.request_job_graph_dot() }
+  #[inline]
+  fn take_job_graph_dot(&mut self) -> Option<String> { self.shared.job_graph_dot.take() }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use std::sync::Arc;
+
+  use rustc_hash::FxHashSet;
+  use ultraviolet::{Isometry3, Rotor3, Vec3};
+
+  use crate::chunk::size::ChunkSize16;
+  use crate::lod::aabb::Aabb;
+  use crate::lod::marching_cubes::{MarchingCubesExtractor, MarchingCubesExtractorSettings};
+  use crate::lod::chunk_mesh::{LodChunkMeshManagerParameters, LodObserver};
+  use crate::lod::octmap::{LodMetric, LodOctmap, LodOctmapSettings};
+  use crate::marching_cubes::MarchingCubes;
+  use crate::volume::{Sphere, SphereSettings};
+
+  type TestOctmap = LodOctmap<ChunkSize16, Sphere, MarchingCubesExtractor<ChunkSize16>>;
+
+  fn test_settings() -> LodOctmapSettings {
+    LodOctmapSettings {
+      root_size: 256,
+      job_queue_worker_threads: 0,
+      ..LodOctmapSettings::default()
+    }
+  }
+
+  fn create_octmap(settings: LodOctmapSettings) -> TestOctmap {
+    let volume = Sphere::new(SphereSettings { radius: 256.0, ..SphereSettings::default() });
+    let extractor = MarchingCubesExtractor::new(MarchingCubes::new(), MarchingCubesExtractorSettings::default());
+    LodOctmap::new(settings, Isometry3::identity(), volume, extractor)
+  }
+
+  /// Updates `octmap` at `position` until all requested chunks are meshed, returning the kept and active AABBs.
+  fn update_until_meshed(octmap: &mut TestOctmap, position: Vec3) -> (FxHashSet<Aabb>, FxHashSet<Aabb>) {
+    update_observers_until_meshed(octmap, &[LodObserver::new(position)])
+  }
+
+  fn update_observers_until_meshed(octmap: &mut TestOctmap, observers: &[LodObserver]) -> (FxHashSet<Aabb>, FxHashSet<Aabb>) {
+    for _ in 0..16 {
+      let _ = octmap.update_observers(observers);
+      if octmap.root.requested_meshing.is_empty() { break; }
+    }
+    assert!(octmap.root.requested_meshing.is_empty(), "Chunks were not meshed after 16 updates");
+    (octmap.root.keep_aabbs.clone(), octmap.root.active_aabbs.clone())
+  }
+
+  // The base child of the root spans 0..128 on each axis, so its distance from this position is exactly its size,
+  // which is the distance at which it is subdivided with a LOD factor of 1.
+  const BOUNDARY_POSITION: Vec3 = Vec3::new(256.0, 64.0, 64.0);
+  const NEAR_POSITION: Vec3 = Vec3::new(256.0 - 0.01, 64.0, 64.0);
+  const FAR_POSITION: Vec3 = Vec3::new(256.0 + 0.01, 64.0, 64.0);
+
+  #[test]
+  fn hysteresis_keeps_active_set_stable() {
+    let mut octmap = create_octmap(LodOctmapSettings { lod_hysteresis: 0.1, ..test_settings() });
+    let (keep_aabbs, active_aabbs) = update_until_meshed(&mut octmap, BOUNDARY_POSITION);
+    for _ in 0..8 {
+      for position in [FAR_POSITION, NEAR_POSITION] {
+        let (moved_keep_aabbs, moved_active_aabbs) = update_until_meshed(&mut octmap, position);
+        assert_eq!(keep_aabbs, moved_keep_aabbs);
+        assert_eq!(active_aabbs, moved_active_aabbs);
+      }
+    }
+  }
+
+  #[test]
+  fn no_hysteresis_thrashes() {
+    let mut octmap = create_octmap(LodOctmapSettings { lod_hysteresis: 0.0, ..test_settings() });
+    let (near_keep_aabbs, _) = update_until_meshed(&mut octmap, NEAR_POSITION);
+    let (far_keep_aabbs, _) = update_until_meshed(&mut octmap, FAR_POSITION);
+    assert_ne!(near_keep_aabbs, far_keep_aabbs);
+    assert_eq!(near_keep_aabbs, update_until_meshed(&mut octmap, NEAR_POSITION).0);
+  }
+
+  #[test]
+  fn hysteresis_merges_beyond_threshold() {
+    let mut octmap = create_octmap(LodOctmapSettings { lod_hysteresis: 0.1, ..test_settings() });
+    let (near_keep_aabbs, _) = update_until_meshed(&mut octmap, NEAR_POSITION);
+    // Beyond the merge distance of 128 * 1.1 from the base child of the root.
+    let (merged_keep_aabbs, _) = update_until_meshed(&mut octmap, Vec3::new(128.0 + 128.0 * 1.1 + 0.01, 64.0, 64.0));
+    assert_ne!(near_keep_aabbs, merged_keep_aabbs);
+    assert!(!merged_keep_aabbs.contains(&Aabb::root().with_user_bit_set().subdivide().base.subdivide().base));
+  }
+
+  #[test]
+  fn screen_space_error_depends_on_viewport() {
+    let settings = LodOctmapSettings { lod_metric: LodMetric::ScreenSpaceError, ..test_settings() };
+    let position = Vec3::broadcast(128.0);
+    let vertical_fov_radians = 60.0f32.to_radians();
+    let mut octmap = create_octmap(settings);
+    octmap.set_projection(vertical_fov_radians, 1080.0);
+    let (high_resolution_keep_aabbs, _) = update_until_meshed(&mut octmap, position);
+    let mut octmap = create_octmap(settings);
+    octmap.set_projection(vertical_fov_radians, 270.0);
+    let (low_resolution_keep_aabbs, _) = update_until_meshed(&mut octmap, position);
+    assert!(high_resolution_keep_aabbs.len() > low_resolution_keep_aabbs.len());
+    assert!(high_resolution_keep_aabbs.is_superset(&low_resolution_keep_aabbs));
+  }
+
+  #[test]
+  fn screen_space_error_applies_observer_lod_factor() {
+    let settings = LodOctmapSettings { lod_metric: LodMetric::ScreenSpaceError, lod_hysteresis: 0.0, ..test_settings() };
+    let position = Vec3::broadcast(128.0);
+    let create = || {
+      let mut octmap = create_octmap(settings);
+      octmap.set_projection(60.0f32.to_radians(), 1080.0);
+      octmap
+    };
+    let (keep_aabbs, _) = update_observers_until_meshed(&mut create(), &[LodObserver::new(position)]);
+    let (detailed_keep_aabbs, _) = update_observers_until_meshed(&mut create(), &[LodObserver::with_lod_factor(position, 2.0)]);
+    assert!(detailed_keep_aabbs.len() > keep_aabbs.len());
+    assert!(detailed_keep_aabbs.is_superset(&keep_aabbs));
+  }
+
+  #[test]
+  fn multiple_observers_keep_union_of_nodes() {
+    let settings = LodOctmapSettings { lod_hysteresis: 0.0, ..test_settings() };
+    let observer_a = LodObserver::new(Vec3::broadcast(32.0));
+    let observer_b = LodObserver::with_lod_factor(Vec3::broadcast(224.0), 2.0);
+    let (keep_aabbs_a, _) = update_observers_until_meshed(&mut create_octmap(settings), &[observer_a]);
+    let (keep_aabbs_b, _) = update_observers_until_meshed(&mut create_octmap(settings), &[observer_b]);
+    let (keep_aabbs, active_aabbs) = update_observers_until_meshed(&mut create_octmap(settings), &[observer_a, observer_b]);
+    assert_eq!(keep_aabbs, keep_aabbs_a.union(&keep_aabbs_b).copied().collect());
+    assert!(active_aabbs.is_subset(&keep_aabbs));
+    // The observer with the larger LOD factor requires more nodes.
+    assert!(keep_aabbs_b.len() > keep_aabbs_a.len());
+  }
+
+  #[test]
+  fn geomorph_distance_matches_merge_distance() {
+    let octmap = create_octmap(LodOctmapSettings { lod_factor: 1.5, lod_hysteresis: 0.1, ..test_settings() });
+    // Nodes are merged into their parent at the LOD distance of the parent, extended by the hysteresis.
+    assert!((octmap.get_geomorph_distance_per_size(None) - 1.5 * 1.1).abs() < 1e-6);
+    assert!((octmap.get_geomorph_distance_per_size(Some(2.0)) - 2.0 * 1.1).abs() < 1e-6);
+    let octmap = create_octmap(LodOctmapSettings { fixed_lod_level: Some(2), ..test_settings() });
+    assert_eq!(octmap.get_geomorph_distance_per_size(None), 0.0);
+  }
+
+  #[test]
+  fn set_transform_keeps_chunks_in_local_space() {
+    let settings = test_settings();
+    let local_position = Vec3::new(32.0, 200.0, 96.0);
+    let (keep_aabbs, active_aabbs) = update_until_meshed(&mut create_octmap(settings), local_position);
+
+    let mut octmap = create_octmap(settings);
+    let _ = update_until_meshed(&mut octmap, local_position);
+    let transform = Isometry3::new(Vec3::new(1000.0, -500.0, 20.0), Rotor3::from_euler_angles(0.3, 1.2, -0.7));
+    octmap.set_transform(transform);
+    assert_eq!(transform, octmap.get_transform());
+    // Moving the observer along with the octmap keeps all chunks, without requesting new ones.
+    let (_, returned_transform, _) = octmap.update(transform.transform_vec(local_position));
+    assert_eq!(transform, returned_transform);
+    assert!(octmap.root.requested_meshing.is_empty());
+    assert_eq!(keep_aabbs, octmap.root.keep_aabbs);
+    assert_eq!(active_aabbs, octmap.root.active_aabbs);
+  }
+
+  #[test]
+  fn invalidating_chunk_samples_extracts_chunk_meshes_again() {
+    let mut octmap = create_octmap(test_settings());
+    let (_, active_aabbs) = update_until_meshed(&mut octmap, NEAR_POSITION);
+    let aabb = *active_aabbs.iter().next().unwrap();
+    let lod_chunk_mesh = octmap.root.lod_chunk_meshes[&aabb].clone();
+    octmap.invalidate_chunk_samples(aabb);
+    let _ = octmap.update(NEAR_POSITION);
+    assert!(!Arc::ptr_eq(&lod_chunk_mesh, &octmap.root.lod_chunk_meshes[&aabb]));
+    assert!(octmap.root.requested_meshing.is_empty());
+    assert_eq!(active_aabbs, octmap.root.active_aabbs);
+  }
 }
@@ -0,0 +1,530 @@
+use std::sync::Arc;
+
+use rustc_hash::{FxHashMap, FxHashSet};
+use tracing::error;
+use ultraviolet::{IVec3, Isometry3, UVec3, Vec3};
+
+use job_queue::{CancellationToken, JobQueueMessage, JobQueueMetrics};
+
+use crate::chunk::size::ChunkSize;
+use crate::lod::aabb::Aabb;
+use crate::lod::chunk_mesh::{LodChunkMeshManager, LodChunkMeshManagerParameters, LodObserver};
+use crate::lod::extract::LodExtractor;
+use crate::lod::octmap::{LodJobKey, LodJobOutput, LodOctmapSettings, LodRoot, LodRootShared, neighbor_volumes};
+use crate::volume::Volume;
+
+// Settings
+
+#[derive(Copy, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub struct PagedLodWorldSettings {
+  /// Settings of each root, where `root_size` is the size of a page.
+  pub octmap: LodOctmapSettings,
+  /// Number of roots around the root that contains an observer to load, per axis.
+  pub load_radius: UVec3,
+  /// Number of roots around the root that contains an observer to keep loaded, per axis. Larger than or equal to
+  /// `load_radius`, so that an observer moving back and forth over a root boundary does not keep loading and unloading
+  /// roots.
+  pub unload_radius: UVec3,
+}
+impl PagedLodWorldSettings {
+  #[inline]
+  pub fn check(&self) {
+    self.octmap.check();
+    let (load, unload) = (self.load_radius, self.unload_radius);
+    assert!(unload.x >= load.x && unload.y >= load.y && unload.z >= load.z, "Unload radius {:?} must be larger than or equal to load radius {:?}", unload, load);
+  }
+}
+impl Default for PagedLodWorldSettings {
+  fn default() -> Self {
+    Self {
+      octmap: LodOctmapSettings::default(),
+      load_radius: UVec3::new(1, 1, 1),
+      unload_radius: UVec3::new(2, 2, 2),
+    }
+  }
+}
+
+
+// Paged LOD world
+
+/// Active chunk of a [`PagedLodWorld`]: the grid coordinates of its root, the transform from the local space of its root
+/// into world space, its AABB in its root, and its chunk mesh.
+pub type PagedLodChunk<'a, M> = (IVec3, Isometry3, &'a Aabb, &'a Arc<M>);
+
+/// Grid of octmap roots of `root_size` around the observers, for worlds that do not fit in a single root (e.g., flat
+/// worlds). Roots are loaded and unloaded as observers move, and the chunks of all roots are created by a single job
+/// queue, so that jobs are prioritized over all roots, and chunks are stitched to chunks in neighboring roots.
+///
+/// The root at grid coordinates `root` covers `root * root_size` to `(root + 1) * root_size` in the local space of the
+/// world, and samples the volume created for it by the page volume function when it is loaded, in the local space of the
+/// root.
+pub struct PagedLodWorld<C: ChunkSize, V: Volume, E: LodExtractor<C>> {
+  load_radius: UVec3,
+  unload_radius: UVec3,
+  transform: Isometry3,
+  transform_inversed: Isometry3,
+  page_volume: Box<dyn Fn(IVec3) -> V + Send + Sync>,
+  shared: LodRootShared<C, V, E>,
+  roots: FxHashMap<[i32; 3], LodRoot<C, V, E>>,
+
+  roots_to_load: FxHashSet<[i32; 3]>,
+  roots_to_keep: FxHashSet<[i32; 3]>,
+  update_order: Vec<[i32; 3]>,
+}
+
+impl<C: ChunkSize, V: Volume, E: LodExtractor<C>> PagedLodWorld<C, V, E> {
+  /// Creates a paged world where `page_volume` creates the volume of the root at the given grid coordinates.
+  pub fn new(settings: PagedLodWorldSettings, transform: Isometry3, page_volume: impl Fn(IVec3) -> V + Send + Sync + 'static, extractor: E) -> Self {
+    settings.check();
+    let root_size = settings.octmap.root_size;
+    let sample_quantization = settings.octmap.sample_quantization;
+    // Sample jobs of chunks in neighboring roots are given the volume of that root, see `update_roots`.
+    let sample = move |key: LodJobKey, volume: V, cancellation_token: &CancellationToken| {
+      let aabb = key.aabb;
+      volume.sample_chunk(aabb.minimum_point(root_size), aabb.step::<C>(root_size), cancellation_token)
+        .quantize(sample_quantization)
+    };
+    let sample_occlusion = |_, volume: V| move |position| volume.sample_at(position);
+    Self {
+      load_radius: settings.load_radius,
+      unload_radius: settings.unload_radius,
+      transform,
+      transform_inversed: transform.inversed(),
+      page_volume: Box::new(page_volume),
+      shared: LodRootShared::new(settings.octmap, extractor, sample, sample_occlusion, |_, _| {}),
+      roots: FxHashMap::default(),
+
+      roots_to_load: FxHashSet::default(),
+      roots_to_keep: FxHashSet::default(),
+      update_order: Vec::new(),
+    }
+  }
+
+  #[inline]
+  pub fn get_root_size(&self) -> u32 { self.shared.root_size }
+
+  #[inline]
+  pub fn get_max_lod_level(&self) -> u8 { self.shared.max_depth }
+
+  #[inline]
+  pub fn get_transform(&self) -> Isometry3 { self.transform }
+  /// Sets the transform from the local space of this world into world space. Chunks are in the local space of their
+  /// root, so none are invalidated. Observers are transformed into local space by the next update.
+  #[inline]
+  pub fn set_transform(&mut self, transform: Isometry3) {
+    self.transform = transform;
+    self.transform_inversed = transform.inversed();
+  }
+
+  /// Gets the transform from the local space of the root at `root` into world space.
+  #[inline]
+  pub fn root_transform(&self, root: IVec3) -> Isometry3 {
+    let mut transform = self.transform;
+    transform.prepend_translation(root_offset(root.into(), self.shared.root_size));
+    transform
+  }
+
+  /// Gets the grid coordinates of the root that contains `position` in world space.
+  #[inline]
+  pub fn root_at(&self, position: Vec3) -> IVec3 {
+    let position = self.transform_inversed.transform_vec(position) / self.shared.root_size as f32;
+    IVec3::new(position.x.floor() as i32, position.y.floor() as i32, position.z.floor() as i32)
+  }
+
+  #[inline]
+  pub fn loaded_roots(&self) -> impl Iterator<Item=IVec3> + '_ {
+    self.roots.keys().map(|root| IVec3::from(*root))
+  }
+
+  #[inline]
+  pub fn update(&mut self, position: Vec3) -> (u32, impl Iterator<Item=PagedLodChunk<'_, E::Chunk>>) {
+    self.update_observers(&[LodObserver::new(position)])
+  }
+
+  /// Loads the roots around `observers` and unloads the roots away from all of them, then updates the chunk meshes of
+  /// each loaded root for `observers`. Returns the root size and the active chunks, along with the grid coordinates of
+  /// their root and the transform from the local space of their root into world space.
+  #[profiling::function]
+  pub fn update_observers(&mut self, observers: &[LodObserver]) -> (u32, impl Iterator<Item=PagedLodChunk<'_, E::Chunk>>) {
+    self.update_roots(observers);
+
+    let root_size = self.shared.root_size;
+    let transform_inversed = self.transform_inversed;
+    for (key, root) in &mut self.roots {
+      let offset = root_offset(*key, root_size);
+      root.set_observers(&self.shared, observers, |position| transform_inversed.transform_vec(position) - offset);
+    }
+
+    self.shared.run_inline_jobs();
+    let roots = &mut self.roots;
+    self.shared.process_messages(|key, message, empty_lod_chunk_mesh_cache| {
+      if let Some(root) = roots.get_mut(&key.root) {
+        root.handle_message(message, empty_lod_chunk_mesh_cache);
+      } else {
+        // Root was unloaded, but the job queue still reports its jobs.
+        match message {
+          JobQueueMessage::JobCompleted(_, LodJobOutput::Mesh(arc)) | JobQueueMessage::CompletedJobRemoved(_, LodJobOutput::Mesh(arc)) => {
+            empty_lod_chunk_mesh_cache.push(arc);
+          }
+          JobQueueMessage::JobFailed(key, error) => {
+            error!("Creating LOD chunk {:?} failed: {}", key, error);
+          }
+          _ => {}
+        }
+      }
+    });
+
+    // Update positive neighbors first, so that the seams of a root are stitched to the current nodes of its neighbors.
+    // Each root is taken out of `roots` while it is updated, so that it can look up the nodes of its neighbors.
+    self.update_order.clear();
+    self.update_order.extend(self.roots.keys().copied());
+    self.update_order.sort_unstable_by_key(|[x, y, z]| std::cmp::Reverse(x + y + z));
+    for key @ [x, y, z] in self.update_order.iter().copied() {
+      let mut root = self.roots.remove(&key).unwrap(); // Unwrap OK: keys are taken from `roots`.
+      let roots = &self.roots;
+      let seam_depth = |offset: IVec3, aabb: Aabb| roots.get(&[x + offset.x, y + offset.y, z + offset.z])
+        .map_or(0, |neighbor| neighbor.stitch_depth(aabb)); // Not loaded: no neighbor to stitch to.
+      root.update(&mut self.shared, Some(&seam_depth));
+      self.roots.insert(key, root);
+    }
+
+    // Request metrics each update, which are received at the next update.
+    self.shared.request_metrics();
+
+    let transform = self.transform;
+    let chunks = self.roots.iter().flat_map(move |(key, root)| {
+      let mut transform = transform;
+      transform.prepend_translation(root_offset(*key, root_size));
+      let key = IVec3::from(*key);
+      root.active_chunks().map(move |(aabb, chunk)| (key, transform, aabb, chunk))
+    });
+    (root_size, chunks)
+  }
+
+  /// Invalidates the samples of the chunk at `aabb` in the root at `root`, for example after editing the volume in that
+  /// chunk. See [`LodOctmap::invalidate_chunk_samples`](crate::lod::octmap::LodOctmap::invalidate_chunk_samples).
+  pub fn invalidate_chunk_samples(&mut self, root: IVec3, aabb: Aabb) {
+    let roots = &self.roots;
+    self.shared.invalidate(LodJobKey::new(root.into(), aabb.with_user_bit_unset()), |key| roots.get(&key).map(|root| root.volume().clone()));
+  }
+
+  pub fn clear(&mut self) {
+    for root in self.roots.values_mut() {
+      root.clear();
+    }
+  }
+
+  fn update_roots(&mut self, observers: &[LodObserver]) {
+    self.roots_to_load.clear();
+    self.roots_to_keep.clear();
+    for observer in observers {
+      let center = self.root_at(observer.position);
+      add_roots_around(&mut self.roots_to_load, center, self.load_radius);
+      add_roots_around(&mut self.roots_to_keep, center, self.unload_radius);
+    }
+
+    let shared = &mut self.shared;
+    let roots_to_keep = &self.roots_to_keep;
+    let root_count = self.roots.len();
+    self.roots.retain(|key, root| {
+      let keep = roots_to_keep.contains(key);
+      if !keep {
+        root.remove_all(shared);
+      }
+      keep
+    });
+    let mut changed = self.roots.len() != root_count;
+    for key in &self.roots_to_load {
+      if !self.roots.contains_key(key) {
+        let volume = (self.page_volume)((*key).into());
+        self.roots.insert(*key, LodRoot::new(*key, volume));
+        changed = true;
+      }
+    }
+
+    if changed { // Chunks in neighboring roots are sampled with the volume of that root, if loaded.
+      self.update_order.clear();
+      self.update_order.extend(self.roots.keys().copied());
+      for [x, y, z] in self.update_order.iter().copied() {
+        let roots = &self.roots;
+        let neighbor_volumes = neighbor_volumes(|[dx, dy, dz]| roots.get(&[x + dx, y + dy, z + dz]).map(|root| root.volume().clone()));
+        self.roots.get_mut(&[x, y, z]).unwrap().set_neighbor_volumes(Some(Box::new(neighbor_volumes))); // Unwrap OK: keys are taken from `roots`.
+      }
+    }
+  }
+}
+
+/// Gets the position of the root at `root` in the local space of the world.
+#[inline]
+fn root_offset([x, y, z]: [i32; 3], root_size: u32) -> Vec3 {
+  Vec3::new(x as f32, y as f32, z as f32) * root_size as f32
+}
+
+#[inline]
+fn add_roots_around(roots: &mut FxHashSet<[i32; 3]>, center: IVec3, radius: UVec3) {
+  let radius = IVec3::new(radius.x as i32, radius.y as i32, radius.z as i32);
+  for x in center.x - radius.x..=center.x + radius.x {
+    for y in center.y - radius.y..=center.y + radius.y {
+      for z in center.z - radius.z..=center.z + radius.z {
+        roots.insert([x, y, z]);
+      }
+    }
+  }
+}
+
+
+// LodChunkMeshManager trait implementation
+
+impl<C: ChunkSize, V: Volume, E: LodExtractor<C>> LodChunkMeshManager<C> for PagedLodWorld<C, V, E> {
+  type Extractor = E;
+  #[inline]
+  fn get_extractor(&self) -> &E {
+    &self.shared.extractor
+  }
+
+  /// Chunks are in the local space of their root, so the offset of each chunk is the position of its root in the local
+  /// space of this world.
+  #[inline]
+  fn update_observers(&mut self, observers: &[LodObserver]) -> (Isometry3, Box<dyn Iterator<Item=(Vec3, (Vec3, Vec3), &Arc<E::Chunk>)> + '_>) {
+    let transform = self.transform;
+    let (root_size, chunks) = self.update_observers(observers);
+    let chunks = chunks.map(move |(root, _, aabb, chunk)| {
+      let offset = root_offset(root.into(), root_size);
+      let (min, max) = aabb.bounds(root_size);
+      (offset, (offset + min, offset + max), chunk)
+    });
+    (transform, Box::new(chunks))
+  }
+}
+
+impl<C: ChunkSize, V: Volume, E: LodExtractor<C>> LodChunkMeshManagerParameters for PagedLodWorld<C, V, E> {
+  #[inline]
+  fn get_max_lod_level(&self) -> u8 { self.shared.max_depth }
+
+  #[inline]
+  fn get_lod_factor(&self) -> f32 { self.shared.lod_factor }
+  #[inline]
+  fn get_lod_factor_mut(&mut self) -> &mut f32 { &mut self.shared.lod_factor }
+
+  #[inline]
+  fn get_lod_hysteresis(&self) -> f32 { self.shared.lod_hysteresis }
+  #[inline]
+  fn get_lod_hysteresis_mut(&mut self) -> &mut f32 { &mut self.shared.lod_hysteresis }
+
+  #[inline]
+  fn set_projection(&mut self, vertical_fov_radians: f32, viewport_height: f32) {
+    self.shared.set_projection(vertical_fov_radians, viewport_height);
+  }
+
+  #[inline]
+  fn get_geomorph_distance_per_size(&self, lod_factor: Option<f32>) -> f32 { self.shared.get_geomorph_distance_per_size(lod_factor) }
+
+  #[inline]
+  fn get_transform(&self) -> Isometry3 { self.transform }
+  #[inline]
+  fn set_transform(&mut self, transform: Isometry3) { self.set_transform(transform) }
+
+  #[inline]
+  fn get_fixed_lod_level(&self) -> Option<u8> { self.shared.fixed_lod_level }
+  #[inline]
+  fn get_fixed_lod_level_mut(&mut self) -> &mut Option<u8> { &mut self.shared.fixed_lod_level }
+
+  #[inline]
+  fn get_job_queue_metrics(&self) -> Option<&JobQueueMetrics> { self.shared.job_queue_metrics.as_ref() }
+
+  #[inline]
+  fn request_job_graph_dot(&mut self) { self.shared.request_job_graph_dot() }
+  #[inline]
+  fn take_job_graph_dot(&mut self) -> Option<String> { self.shared.job_graph_dot.take() }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use std::hash::Hasher;
+
+  use rustc_hash::FxHashSet;
+  use ultraviolet::{IVec3, Isometry3, UVec3, Vec3};
+
+  use crate::chunk::size::ChunkSize16;
+  use crate::lod::aabb::Aabb;
+  use crate::lod::octmap::LodOctmapSettings;
+  use crate::lod::paged::{PagedLodWorld, PagedLodWorldSettings};
+  use crate::lod::surface_nets::{SurfaceNetsExtractor, SurfaceNetsExtractorSettings};
+  use crate::surface_nets::lod::SurfaceNetsLod;
+  use crate::surface_nets::SurfaceNets;
+  use crate::volume::Volume;
+
+  /// Ground at a fixed height in world space, sampled in the local space of the root at `root`.
+  #[derive(Copy, Clone)]
+  struct Ground {
+    root: IVec3,
+  }
+  impl Volume for Ground {
+    fn sample(&self, position: UVec3) -> f32 {
+      GROUND_HEIGHT - (self.root.y * ROOT_SIZE as i32 + position.y as i32) as f32
+    }
+    fn hash_description<H: Hasher>(&self, state: &mut H) {
+      state.write(b"ground");
+    }
+  }
+
+  const ROOT_SIZE: u32 = 64;
+  const GROUND_HEIGHT: f32 = 20.5;
+
+  type TestWorld = PagedLodWorld<ChunkSize16, Ground, SurfaceNetsExtractor<ChunkSize16>>;
+
+  fn create_world(fixed_lod_level: Option<u8>) -> TestWorld {
+    create_world_with_extractor_settings(fixed_lod_level, SurfaceNetsExtractorSettings::default())
+  }
+
+  fn create_world_with_extractor_settings(fixed_lod_level: Option<u8>, extractor_settings: SurfaceNetsExtractorSettings) -> TestWorld {
+    let settings = PagedLodWorldSettings {
+      octmap: LodOctmapSettings { root_size: ROOT_SIZE, fixed_lod_level, job_queue_worker_threads: 0, ..LodOctmapSettings::default() },
+      load_radius: UVec3::new(1, 1, 1),
+      unload_radius: UVec3::new(2, 2, 2),
+    };
+    let extractor = SurfaceNetsExtractor::new(SurfaceNets::new(), SurfaceNetsLod::new(), extractor_settings);
+    PagedLodWorld::new(settings, Isometry3::identity(), |root| Ground { root }, extractor)
+  }
+
+  fn loaded_roots(world: &TestWorld) -> FxHashSet<[i32; 3]> {
+    world.loaded_roots().map(|root| root.into()).collect()
+  }
+
+  fn roots_around(center: IVec3, radius: i32) -> FxHashSet<[i32; 3]> {
+    let mut roots = FxHashSet::default();
+    for x in center.x - radius..=center.x + radius {
+      for y in center.y - radius..=center.y + radius {
+        for z in center.z - radius..=center.z + radius {
+          roots.insert([x, y, z]);
+        }
+      }
+    }
+    roots
+  }
+
+  #[test]
+  fn roots_are_loaded_and_unloaded_around_viewer() {
+    let mut world = create_world(Some(0));
+    let _ = world.update(Vec3::new(32.0, 32.0, 32.0));
+    assert_eq!(loaded_roots(&world), roots_around(IVec3::zero(), 1));
+
+    // Roots within the unload radius of the new root are kept, others are unloaded.
+    let position = Vec3::new(3.5 * ROOT_SIZE as f32, 32.0, 32.0);
+    assert_eq!(world.root_at(position), IVec3::new(3, 0, 0));
+    let _ = world.update(position);
+    let expected: FxHashSet<_> = roots_around(IVec3::new(3, 0, 0), 1).into_iter()
+      .chain(roots_around(IVec3::zero(), 1).into_iter().filter(|[x, _, _]| *x >= 1))
+      .collect();
+    assert_eq!(loaded_roots(&world), expected);
+
+    // Negative roots are loaded as well.
+    let _ = world.update(Vec3::new(-10.0 * ROOT_SIZE as f32, -0.5, 32.0));
+    assert_eq!(loaded_roots(&world), roots_around(IVec3::new(-10, -1, 0), 1));
+  }
+
+  #[test]
+  fn chunks_are_stitched_across_roots() {
+    let mut world = create_world(Some(1));
+    let position = Vec3::new(32.0, 32.0, 32.0);
+    for _ in 0..4 {
+      let _ = world.update(position);
+    }
+    let loaded = loaded_roots(&world);
+    let (root_size, chunks) = world.update(position);
+    assert_eq!(root_size, ROOT_SIZE);
+    let base = Aabb::root().with_user_bit_set().subdivide();
+    let (mut stitched, mut not_stitched) = (0, 0);
+    for (root, transform, aabb, chunk) in chunks {
+      assert_eq!(transform.translation, Vec3::new(root.x as f32, root.y as f32, root.z as f32) * ROOT_SIZE as f32);
+      if root.y != 0 || aabb.minimum_point(ROOT_SIZE).y != 0 { continue; } // Only these chunks contain the ground.
+      assert!(!chunk.regular.is_empty());
+      // Chunks at the positive X edge of a root are stitched to the chunks of the next root, if it is loaded.
+      if *aabb == base.x || *aabb == base.xz {
+        let has_neighbor = loaded.contains(&[root.x + 1, root.y, root.z]);
+        assert_eq!(!chunk.border_x_chunk.is_empty(), has_neighbor, "Chunk {:?} of root {:?} is stitched: {}, has neighbor: {}", aabb, root, !chunk.border_x_chunk.is_empty(), has_neighbor);
+        if has_neighbor { stitched += 1; } else { not_stitched += 1; }
+      }
+    }
+    // Each root that contains the ground has 2 chunks at its positive X edge that contain the ground.
+    let ground_roots = |has_neighbor: bool| loaded.iter().filter(|[x, y, z]| *y == 0 && loaded.contains(&[x + 1, *y, *z]) == has_neighbor).count();
+    assert_eq!(stitched, 2 * ground_roots(true));
+    assert_eq!(not_stitched, 2 * ground_roots(false));
+  }
+
+  #[test]
+  fn chunks_are_stitched_again_when_neighbor_is_loaded() {
+    let mut world = create_world(Some(1));
+    let root = IVec3::new(1, 0, 0);
+    let base = Aabb::root().with_user_bit_set().subdivide();
+    let stitched_edge_chunks = |world: &mut TestWorld, position: Vec3| {
+      for _ in 0..4 {
+        let _ = world.update(position);
+      }
+      let (_, chunks) = world.update(position);
+      chunks.filter(|(r, _, aabb, chunk)| *r == root && (**aabb == base.x || **aabb == base.xz) && !chunk.border_x_chunk.is_empty()).count()
+    };
+    // Root (1, 0, 0) is at the edge of the loaded roots, so its positive X edge is not stitched.
+    assert_eq!(stitched_edge_chunks(&mut world, Vec3::new(32.0, 32.0, 32.0)), 0);
+    // Root (2, 0, 0) is loaded while root (1, 0, 0) keeps its nodes, so its chunks at the positive X edge that contain
+    // the ground are extracted again.
+    assert_eq!(stitched_edge_chunks(&mut world, Vec3::new(1.5 * ROOT_SIZE as f32, 32.0, 32.0)), 2);
+  }
+
+  /// Gets the depth that chunks are stitched to the node at `aabb` in `root` with, derived from the active chunks: the
+  /// depth of the active chunk that contains the node, or one level deeper if the node contains active chunks. 0 if the
+  /// root is not loaded.
+  fn expected_stitch_depth(active: &FxHashSet<([i32; 3], Aabb)>, loaded: &FxHashSet<[i32; 3]>, root: [i32; 3], aabb: Aabb) -> u8 {
+    if !loaded.contains(&root) { return 0; }
+    match std::iter::successors(Some(aabb), Aabb::parent).find(|aabb| active.contains(&(root, *aabb))) {
+      Some(active_aabb) => active_aabb.depth().max(1),
+      None => aabb.depth() + 1,
+    }
+  }
+
+  /// Updates `world` for an observer at `position` until its chunks are extracted, then checks that the chunks at the
+  /// positive Z edge of each root are stitched to the current nodes of the next root. Returns the number of checked
+  /// chunks, and the number of those whose neighbor is deeper.
+  fn check_stitched_along_z(world: &mut TestWorld, position: Vec3) -> (usize, usize) {
+    for _ in 0..8 {
+      let _ = world.update(position);
+    }
+    let loaded = loaded_roots(world);
+    let (_, chunks) = world.update(position);
+    let chunks: Vec<_> = chunks.map(|(root, _, aabb, chunk)| (<[i32; 3]>::from(root), *aabb, !chunk.border_z_chunk.is_empty())).collect();
+    let active: FxHashSet<_> = chunks.iter().map(|(root, aabb, _)| (*root, *aabb)).collect();
+    let (mut checked, mut deeper) = (0, 0);
+    for (root @ [x, y, z], aabb, is_stitched) in chunks {
+      let (root_offset, neighbor) = aabb.neighbor_across_roots(IVec3::new(0, 0, 1));
+      if root_offset == IVec3::zero() { continue; } // Not at the positive Z edge.
+      let neighbor_root = [x, y, z + 1];
+      let expected_depth = expected_stitch_depth(&active, &loaded, neighbor_root, neighbor);
+      let neighbor_depths = world.roots[&root].seam_neighbor_depths()[&aabb];
+      assert_eq!(neighbor_depths.z, expected_depth, "Chunk {:?} of root {:?} is stitched to the wrong depth", aabb, root);
+      let contains_ground = y == 0 && aabb.minimum_point(ROOT_SIZE).y as f32 <= GROUND_HEIGHT && (aabb.maximum_point(ROOT_SIZE).y as f32) > GROUND_HEIGHT;
+      if contains_ground {
+        assert_eq!(is_stitched, loaded.contains(&neighbor_root), "Chunk {:?} of root {:?} is stitched: {}", aabb, root, is_stitched);
+      }
+      checked += 1;
+      if expected_depth > aabb.depth() { deeper += 1; }
+    }
+    (checked, deeper)
+  }
+
+  #[test]
+  fn chunks_are_stitched_across_roots_at_distance_lod() {
+    // Border X chunks are disabled, as they stitch chunks to higher resolution neighbors differently. Border Z chunks are
+    // stitched in the same way for neighbors of any depth.
+    let extractor_settings = SurfaceNetsExtractorSettings { extract_border_x_chunks: false, ..SurfaceNetsExtractorSettings::default() };
+    let mut world = create_world_with_extractor_settings(None, extractor_settings);
+    // Near the positive Z edge of root (0, 0, 0), so that the depths of chunks vary along the edge.
+    let (checked, deeper) = check_stitched_along_z(&mut world, Vec3::new(40.0, GROUND_HEIGHT, 60.0));
+    assert!(checked > 0 && deeper > 0, "Checked {} chunks, of which {} have a deeper neighbor", checked, deeper);
+    // Moving within the same root changes the nodes at both sides of the edge, so the chunks that are kept are extracted
+    // again for the current nodes of their neighbor.
+    let (checked, _) = check_stitched_along_z(&mut world, Vec3::new(40.0, GROUND_HEIGHT, 4.0));
+    assert!(checked > 0);
+  }
+}
@@ -0,0 +1,488 @@
+use std::f32::consts::FRAC_PI_4;
+use std::sync::Arc;
+
+use rustc_hash::FxHashMap;
+use ultraviolet::{DVec3, Isometry3, Vec3};
+
+use job_queue::{CancellationToken, JobQueueMetrics};
+
+use crate::chunk::mesh::{ChunkMesh, Vertex};
+use crate::chunk::size::ChunkSize;
+use crate::lod::aabb::{Aabb, vec3_to_dvec3};
+use crate::lod::chunk_mesh::{LodChunkMesh, LodChunkMeshManager, LodChunkMeshManagerParameters, LodObserver};
+use crate::lod::extract::LodExtractor;
+use crate::lod::octmap::{LodJobKey, LodOctmapSettings, LodRoot, LodRootGeometry, LodRootShared};
+use crate::volume::Volume;
+
+// Settings
+
+#[derive(Copy, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub struct LodPlanetSettings {
+  /// Settings of the tree of each face, where `root_size` is the number of cells along a face at the finest LOD.
+  pub octmap: LodOctmapSettings,
+  /// Center of the planet in the volume.
+  pub center: Vec3,
+  /// Radius of the surface of the planet.
+  pub radius: f32,
+  /// Distance below the surface that the shell of chunks extends to.
+  pub shell_depth: f32,
+  /// Distance above the surface that the shell of chunks extends to.
+  pub shell_height: f32,
+}
+impl LodPlanetSettings {
+  #[inline]
+  pub fn check(&self) {
+    self.octmap.check();
+    assert!(self.radius > 0.0, "Radius {} must be positive", self.radius);
+    assert!(self.shell_depth >= 0.0 && self.shell_depth < self.radius, "Shell depth {} must be between 0 and radius {}", self.shell_depth, self.radius);
+    assert!(self.shell_height >= 0.0, "Shell height {} may not be negative", self.shell_height);
+    let shell_size = CubeSphere::new(self).shell_size;
+    assert!(shell_size <= self.octmap.root_size, "Shell of {} cells is thicker than a face of {} cells", shell_size, self.octmap.root_size);
+  }
+}
+impl Default for LodPlanetSettings {
+  fn default() -> Self {
+    Self {
+      octmap: LodOctmapSettings::default(),
+      center: Vec3::broadcast(2048.0),
+      radius: 2048.0,
+      shell_depth: 256.0,
+      shell_height: 256.0,
+    }
+  }
+}
+
+
+// Cube-sphere projection
+
+/// Outward normal of each face of the cube, and the directions of the X and Y axes of the grid of the face. The cross
+/// product of the X and Y directions is the normal, so that triangles keep their winding when projected.
+const FACES: [(Vec3, Vec3, Vec3); 6] = [
+  (Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0), Vec3::new(0.0, 0.0, 1.0)),
+  (Vec3::new(-1.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 1.0), Vec3::new(0.0, 1.0, 0.0)),
+  (Vec3::new(0.0, 1.0, 0.0), Vec3::new(0.0, 0.0, 1.0), Vec3::new(1.0, 0.0, 0.0)),
+  (Vec3::new(0.0, -1.0, 0.0), Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 1.0)),
+  (Vec3::new(0.0, 0.0, 1.0), Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0)),
+  (Vec3::new(0.0, 0.0, -1.0), Vec3::new(0.0, 1.0, 0.0), Vec3::new(1.0, 0.0, 0.0)),
+];
+
+/// Projection of the grids of the 6 faces of a cube onto a spherical shell. In the grid of a face, X and Y are positions
+/// on the face from 0 to `root_size`, and Z is the height from the bottom of the shell, so that chunks in the grid are
+/// curved chunks in the shell. Positions on the faces are spaced by equal angles, and the grid is scaled so that cells
+/// are about as wide as they are high at the surface.
+#[derive(Copy, Clone, Debug)]
+pub struct CubeSphere {
+  center: Vec3,
+  inner_radius: f32,
+  /// Distance in the volume per unit of the grid.
+  scale: f32,
+  root_size: u32,
+  /// Height of the shell in units of the grid, rounded up.
+  shell_size: u32,
+}
+
+impl CubeSphere {
+  pub fn new(settings: &LodPlanetSettings) -> Self {
+    let root_size = settings.octmap.root_size;
+    // A quarter circle at the surface spans a face.
+    let scale = settings.radius * std::f32::consts::FRAC_PI_2 / root_size as f32;
+    let shell_size = ((settings.shell_depth + settings.shell_height) / scale).ceil() as u32;
+    Self { center: settings.center, inner_radius: settings.radius - settings.shell_depth, scale, root_size, shell_size }
+  }
+
+  #[inline]
+  pub fn scale(&self) -> f32 { self.scale }
+
+  #[inline]
+  pub fn shell_size(&self) -> u32 { self.shell_size }
+
+  /// Gets the direction from the center of the sphere to position `x`, `y` in the grid of `face`.
+  #[inline]
+  pub fn direction(&self, face: u8, x: f32, y: f32) -> Vec3 {
+    let (normal, x_axis, y_axis) = FACES[face as usize];
+    let to_face = |position: f32| (FRAC_PI_4 * (2.0 * position / self.root_size as f32 - 1.0)).tan();
+    (normal + x_axis * to_face(x) + y_axis * to_face(y)).normalized()
+  }
+
+  /// Projects `position` in the grid of `face` into the volume.
+  #[inline]
+  pub fn project(&self, face: u8, position: Vec3) -> Vec3 {
+    self.center + self.direction(face, position.x, position.y) * (self.inner_radius + position.z * self.scale)
+  }
+
+  /// Gets the minimum and maximum point of a box in the volume that contains the chunk at `aabb` in the grid of `face`.
+  pub fn bounds(&self, face: u8, aabb: Aabb) -> (Vec3, Vec3) {
+    let min = Vec3::from(aabb.minimum_point(self.root_size));
+    let size = aabb.size(self.root_size) as f32;
+    let mut bounds_min = Vec3::broadcast(f32::INFINITY);
+    let mut bounds_max = Vec3::broadcast(f32::NEG_INFINITY);
+    for x in 0..3 {
+      for y in 0..3 {
+        for z in 0..2 {
+          let point = self.project(face, min + Vec3::new(x as f32, y as f32, z as f32 * 2.0) * (size / 2.0));
+          bounds_min = bounds_min.min_by_component(point);
+          bounds_max = bounds_max.max_by_component(point);
+        }
+      }
+    }
+    // The shell between the projected points bulges out by at most the sagitta of the angle between them.
+    let angle = FRAC_PI_4 * size / self.root_size as f32;
+    let bulge = (self.inner_radius + (min.z + size) * self.scale) * (1.0 - angle.cos());
+    (bounds_min - Vec3::broadcast(bulge), bounds_max + Vec3::broadcast(bulge))
+  }
+}
+
+/// Geometry of the tree of a face, which only contains nodes in the shell, and where the LOD of nodes is determined by
+/// their distance in the volume.
+struct CubeSphereFace {
+  face: u8,
+  cube_sphere: CubeSphere,
+}
+
+impl LodRootGeometry for CubeSphereFace {
+  #[inline]
+  fn contains(&self, root_size: u32, aabb: Aabb) -> bool { aabb.minimum_point(root_size).z < self.cube_sphere.shell_size }
+
+  #[inline]
+  fn distance_from(&self, _root_size: u32, aabb: Aabb, position: DVec3) -> f64 {
+    let (min, max) = self.cube_sphere.bounds(self.face, aabb);
+    (position.clamped(vec3_to_dvec3(min), vec3_to_dvec3(max)) - position).mag()
+  }
+
+  #[inline]
+  fn size(&self, root_size: u32, aabb: Aabb) -> f64 { aabb.size(root_size) as f64 * self.cube_sphere.scale as f64 }
+}
+
+
+// LOD planet
+
+/// Active chunk of a [`LodPlanet`]: the minimum and maximum point of a box in the local space of the planet that contains
+/// it, and its chunk mesh.
+pub type PlanetLodChunk<'a, M> = ((Vec3, Vec3), &'a Arc<M>);
+
+/// Planet surface with LOD, made of a tree on each of the 6 faces of a cube that is projected onto a spherical shell
+/// around the surface (see [`CubeSphere`]). Chunks are sampled along the curved grid of the shell, and extracted with
+/// the regular extractors, after which their vertices are projected onto the shell. The trees are quadtrees on the face
+/// that only split into several layers in the shell once their nodes are smaller than the height of the shell, so no
+/// nodes are spent on the space and interior of the planet. All trees share one job queue.
+///
+/// Chunks meshes are in the local space of this planet, which is the space of the volume translated to put the center of
+/// the planet at the origin (see [`update_observers`](Self::update_observers)). Chunks of neighboring faces are not
+/// stitched to each other, as their grids are rotated with respect to each other. Instead, chunks at the edges of a face
+/// get skirts that hide the gaps between faces (see [`add_face_edge_skirts`]).
+pub struct LodPlanet<C: ChunkSize, V: Volume, E: LodExtractor<C>> {
+  transform: Isometry3,
+  transform_inversed: Isometry3,
+  cube_sphere: CubeSphere,
+  shared: LodRootShared<C, V, E>,
+  faces: Vec<LodRoot<C, V, E, CubeSphereFace>>,
+}
+
+impl<C: ChunkSize, V: Volume, E: LodExtractor<C>> LodPlanet<C, V, E> {
+  /// Creates a planet, where `transform` transforms from the space of `volume` into world space.
+  pub fn new(settings: LodPlanetSettings, transform: Isometry3, volume: V, extractor: E) -> Self {
+    settings.check();
+    let cube_sphere = CubeSphere::new(&settings);
+    let center = settings.center;
+    let root_size = settings.octmap.root_size;
+    let sample_quantization = settings.octmap.sample_quantization;
+    let sample = move |key: LodJobKey, volume: V, cancellation_token: &CancellationToken| {
+      let face = key.root[0] as u8;
+      let min = key.aabb.minimum_point(root_size);
+      let step = key.aabb.step::<C>(root_size);
+      volume.sample_chunk_at::<C>(|offset| cube_sphere.project(face, (min + offset * step).into()), cancellation_token)
+        .quantize(sample_quantization)
+    };
+    let sample_occlusion = move |key: LodJobKey, volume: V| {
+      let face = key.root[0] as u8;
+      move |position| volume.sample_at(cube_sphere.project(face, position))
+    };
+    let transform_mesh = move |key: LodJobKey, lod_chunk_mesh: &mut E::Chunk| {
+      let face = key.root[0] as u8;
+      let min = key.aabb.minimum_point(root_size);
+      let max = key.aabb.maximum_point(root_size);
+      let at_face_edge = min.x == 0 || min.y == 0 || max.x == root_size || max.y == root_size;
+      let step = key.aabb.step::<C>(root_size) as f32;
+      lod_chunk_mesh.for_each_chunk_mesh_mut(|chunk_mesh| {
+        if at_face_edge {
+          add_face_edge_skirts(chunk_mesh, root_size as f32, step, min.z as f32);
+        }
+        for vertex in chunk_mesh.vertices_mut() {
+          vertex.position = cube_sphere.project(face, vertex.position) - center;
+          vertex.morph_position = cube_sphere.project(face, vertex.morph_position) - center;
+          vertex.lod_size *= cube_sphere.scale;
+        }
+      });
+    };
+    let faces = (0..FACES.len() as u8)
+      .map(|face| LodRoot::with_geometry([face as i32, 0, 0], volume.clone(), CubeSphereFace { face, cube_sphere }))
+      .collect();
+    Self {
+      transform,
+      transform_inversed: transform.inversed(),
+      cube_sphere,
+      shared: LodRootShared::new(settings.octmap, extractor, sample, sample_occlusion, transform_mesh),
+      faces,
+    }
+  }
+
+  #[inline]
+  pub fn get_max_lod_level(&self) -> u8 { self.shared.max_depth }
+
+  #[inline]
+  pub fn get_cube_sphere(&self) -> &CubeSphere { &self.cube_sphere }
+
+  #[inline]
+  pub fn get_transform(&self) -> Isometry3 { self.transform }
+  /// Sets the transform from the space of the volume into world space. Chunks are in local space, so none are
+  /// invalidated. Observers are transformed into local space by the next update.
+  #[inline]
+  pub fn set_transform(&mut self, transform: Isometry3) {
+    self.transform = transform;
+    self.transform_inversed = transform.inversed();
+  }
+
+  #[inline]
+  pub fn update(&mut self, position: Vec3) -> (Isometry3, impl Iterator<Item=PlanetLodChunk<'_, E::Chunk>>) {
+    self.update_observers(&[LodObserver::new(position)])
+  }
+
+  /// Updates the chunk meshes of each face for the union of the nodes that `observers` require. Returns the transform
+  /// from local space into world space, and the active chunks along with the minimum and maximum point of a box in
+  /// local space that contains them, for culling.
+  #[profiling::function]
+  pub fn update_observers(&mut self, observers: &[LodObserver]) -> (Isometry3, impl Iterator<Item=PlanetLodChunk<'_, E::Chunk>>) {
+    let transform_inversed = self.transform_inversed;
+    for face in &mut self.faces {
+      face.set_observers(&self.shared, observers, |position| transform_inversed.transform_vec(position));
+    }
+
+    self.shared.run_inline_jobs();
+    let faces = &mut self.faces;
+    self.shared.process_messages(|key, message, empty_lod_chunk_mesh_cache| faces[key.root[0] as usize].handle_message(message, empty_lod_chunk_mesh_cache));
+
+    for face in &mut self.faces {
+      face.update(&mut self.shared, None);
+    }
+
+    // Request metrics each update, which are received at the next update.
+    self.shared.request_metrics();
+
+    let cube_sphere = self.cube_sphere;
+    let mut transform = self.transform;
+    transform.prepend_translation(cube_sphere.center);
+    let chunks = self.faces.iter().enumerate().flat_map(move |(face, root)| root.active_chunks().map(move |(aabb, lod_chunk_mesh)| {
+      let (min, max) = cube_sphere.bounds(face as u8, *aabb);
+      ((min - cube_sphere.center, max - cube_sphere.center), lod_chunk_mesh)
+    }));
+    (transform, chunks)
+  }
+
+  /// Invalidates the samples of the chunk at `aabb` in the grid of `face`. See
+  /// [`LodOctmap::invalidate_chunk_samples`](crate::lod::octmap::LodOctmap::invalidate_chunk_samples).
+  pub fn invalidate_chunk_samples(&mut self, face: u8, aabb: Aabb) {
+    let key = [face as i32, 0, 0];
+    let volume = self.faces[face as usize].volume();
+    self.shared.invalidate(LodJobKey::new(key, aabb.with_user_bit_unset()), |root| (root == key).then(|| volume.clone()));
+  }
+
+  pub fn clear(&mut self) {
+    for face in &mut self.faces {
+      face.clear();
+    }
+  }
+}
+
+/// Adds skirts to `chunk_mesh` in the grid of a face with `root_size`, along the edges of the face. As chunks are not
+/// stitched across faces, the meshes of neighboring faces end up to a cell of `step` before the edge between them. Each
+/// edge of the boundary of the mesh within a cell of an edge of the face is extended to that edge, and from there hangs
+/// down by a cell (but not below `min_z`), so that both faces cover the gap with a curtain along the edge. Skirts are
+/// double-sided, as they are seen from both faces.
+fn add_face_edge_skirts(chunk_mesh: &mut ChunkMesh, root_size: f32, step: f32, min_z: f32) {
+  let edges_of = |triangle: &[u16]| [(triangle[0], triangle[1]), (triangle[1], triangle[2]), (triangle[2], triangle[0])];
+  // Edges on the boundary of the mesh belong to a single triangle.
+  let mut triangles_per_edge = FxHashMap::default();
+  for (a, b) in chunk_mesh.indices().chunks_exact(3).flat_map(edges_of) {
+    *triangles_per_edge.entry((a.min(b), a.max(b))).or_insert(0) += 1;
+  }
+  let boundary_edges: Vec<_> = chunk_mesh.indices().chunks_exact(3).flat_map(edges_of)
+    .filter(|(a, b)| triangles_per_edge[&(*a.min(b), *a.max(b))] == 1)
+    .collect();
+  for (a, b) in boundary_edges {
+    let (vertex_a, vertex_b) = (chunk_mesh.vertices()[a as usize], chunk_mesh.vertices()[b as usize]);
+    for (axis, edge) in [(0, 0.0), (0, root_size), (1, 0.0), (1, root_size)] {
+      let near_edge = |vertex: &Vertex| (vertex.position[axis] - edge).abs() <= step;
+      if !near_edge(&vertex_a) || !near_edge(&vertex_b) { continue; }
+      if chunk_mesh.vertices().len() + 4 > u16::MAX as usize { return; }
+      let skirt_vertex = |mut vertex: Vertex, z_offset: f32| {
+        for position in [&mut vertex.position, &mut vertex.morph_position] {
+          position[axis] = edge;
+          position.z = (position.z - z_offset).max(min_z);
+        }
+        vertex
+      };
+      let edge_a = chunk_mesh.push_vertex(skirt_vertex(vertex_a, 0.0));
+      let edge_b = chunk_mesh.push_vertex(skirt_vertex(vertex_b, 0.0));
+      let bottom_a = chunk_mesh.push_vertex(skirt_vertex(vertex_a, step));
+      let bottom_b = chunk_mesh.push_vertex(skirt_vertex(vertex_b, step));
+      for [top_a, top_b, low_a, low_b] in [[a, b, edge_a, edge_b], [edge_a, edge_b, bottom_a, bottom_b]] {
+        chunk_mesh.extend_indices_from_slice(&[top_a, low_a, top_b, top_b, low_a, low_b]);
+        chunk_mesh.extend_indices_from_slice(&[top_a, top_b, low_a, top_b, low_b, low_a]);
+      }
+    }
+  }
+}
+
+// LodChunkMeshManager trait implementation
+
+impl<C: ChunkSize, V: Volume, E: LodExtractor<C>> LodChunkMeshManager<C> for LodPlanet<C, V, E> {
+  type Extractor = E;
+  #[inline]
+  fn get_extractor(&self) -> &E {
+    &self.shared.extractor
+  }
+
+  #[inline]
+  fn update_observers(&mut self, observers: &[LodObserver]) -> (Isometry3, Box<dyn Iterator<Item=(Vec3, (Vec3, Vec3), &Arc<E::Chunk>)> + '_>) {
+    let (transform, chunks) = self.update_observers(observers);
+    (transform, Box::new(chunks.map(|(bounds, chunk)| (Vec3::zero(), bounds, chunk))))
+  }
+}
+
+impl<C: ChunkSize, V: Volume, E: LodExtractor<C>> LodChunkMeshManagerParameters for LodPlanet<C, V, E> {
+  #[inline]
+  fn get_max_lod_level(&self) -> u8 { self.shared.max_depth }
+
+  #[inline]
+  fn get_lod_factor(&self) -> f32 { self.shared.lod_factor }
+  #[inline]
+  fn get_lod_factor_mut(&mut self) -> &mut f32 { &mut self.shared.lod_factor }
+
+  #[inline]
+  fn get_lod_hysteresis(&self) -> f32 { self.shared.lod_hysteresis }
+  #[inline]
+  fn get_lod_hysteresis_mut(&mut self) -> &mut f32 { &mut self.shared.lod_hysteresis }
+
+  #[inline]
+  fn set_projection(&mut self, vertical_fov_radians: f32, viewport_height: f32) {
+    self.shared.set_projection(vertical_fov_radians, viewport_height);
+  }
+
+  #[inline]
+  fn get_geomorph_distance_per_size(&self, lod_factor: Option<f32>) -> f32 { self.shared.get_geomorph_distance_per_size(lod_factor) }
+
+  #[inline]
+  fn get_transform(&self) -> Isometry3 { self.transform }
+  #[inline]
+  fn set_transform(&mut self, transform: Isometry3) { self.set_transform(transform) }
+
+  #[inline]
+  fn get_fixed_lod_level(&self) -> Option<u8> { self.shared.fixed_lod_level }
+  #[inline]
+  fn get_fixed_lod_level_mut(&mut self) -> &mut Option<u8> { &mut self.shared.fixed_lod_level }
+
+  #[inline]
+  fn get_job_queue_metrics(&self) -> Option<&JobQueueMetrics> { self.shared.job_queue_metrics.as_ref() }
+
+  #[inline]
+  fn request_job_graph_dot(&mut self) { self.shared.request_job_graph_dot() }
+  #[inline]
+  fn take_job_graph_dot(&mut self) -> Option<String> { self.shared.job_graph_dot.take() }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use ultraviolet::{Isometry3, Vec3};
+
+  use crate::chunk::size::ChunkSize16;
+  use crate::lod::marching_cubes::{MarchingCubesExtractor, MarchingCubesExtractorSettings};
+  use crate::lod::octmap::LodOctmapSettings;
+  use crate::lod::planet::{CubeSphere, FACES, LodPlanet, LodPlanetSettings};
+  use crate::marching_cubes::MarchingCubes;
+  use crate::volume::{Sphere, SphereSettings};
+
+  const ROOT_SIZE: u32 = 256;
+  const RADIUS: f32 = 256.0;
+
+  fn create_settings(fixed_lod_level: Option<u8>) -> LodPlanetSettings {
+    LodPlanetSettings {
+      octmap: LodOctmapSettings { root_size: ROOT_SIZE, fixed_lod_level, job_queue_worker_threads: 0, ..LodOctmapSettings::default() },
+      center: Vec3::broadcast(RADIUS),
+      radius: RADIUS,
+      shell_depth: 32.0,
+      shell_height: 32.0,
+    }
+  }
+
+  #[test]
+  fn edges_of_neighboring_faces_coincide() {
+    let cube_sphere = CubeSphere::new(&create_settings(None));
+    let size = ROOT_SIZE as f32;
+    let edge_points = |face: u8| (0..=8).flat_map(move |i| {
+      let t = i as f32 * size / 8.0;
+      [Vec3::new(t, 0.0, 3.0), Vec3::new(t, size, 3.0), Vec3::new(0.0, t, 3.0), Vec3::new(size, t, 3.0)]
+    }).map(move |position| cube_sphere.project(face, position));
+    for face in 0..FACES.len() as u8 {
+      for point in edge_points(face) {
+        let coincides = (0..FACES.len() as u8)
+          .filter(|other| *other != face)
+          .any(|other| edge_points(other).any(|other_point| (other_point - point).mag() < 1e-3));
+        assert!(coincides, "Edge point {:?} of face {} does not lie on the edge of another face", point, face);
+      }
+    }
+  }
+
+  #[test]
+  fn projection_keeps_winding() {
+    let cube_sphere = CubeSphere::new(&create_settings(None));
+    for face in 0..FACES.len() as u8 {
+      for position in [Vec3::new(1.0, 1.0, 0.0), Vec3::new(128.0, 128.0, 10.0), Vec3::new(250.0, 20.0, 5.0)] {
+        let projected = cube_sphere.project(face, position);
+        let dx = cube_sphere.project(face, position + Vec3::unit_x()) - projected;
+        let dy = cube_sphere.project(face, position + Vec3::unit_y()) - projected;
+        let dz = cube_sphere.project(face, position + Vec3::unit_z()) - projected;
+        assert!(dx.cross(dy).dot(dz) > 0.0, "Projection of face {} at {:?} flips winding", face, position);
+      }
+    }
+  }
+
+  #[test]
+  fn chunks_mesh_surface_of_sphere() {
+    let volume = Sphere::new(SphereSettings { radius: RADIUS * 2.0, ..SphereSettings::default() });
+    let extractor = MarchingCubesExtractor::new(MarchingCubes::new(), MarchingCubesExtractorSettings::default());
+    let mut planet = LodPlanet::<ChunkSize16, _, _>::new(create_settings(Some(2)), Isometry3::identity(), volume, extractor);
+    for _ in 0..4 {
+      let _ = planet.update(Vec3::zero());
+    }
+    let (transform, chunks) = planet.update(Vec3::zero());
+    assert_eq!(transform.translation, Vec3::broadcast(RADIUS));
+    let chunks: Vec<_> = chunks.collect();
+    // Only the bottom layer of nodes of each face lies in the shell: 4x4 nodes of 64 cells on 6 faces.
+    assert_eq!(chunks.len(), 6 * 4 * 4);
+    // Skirts hang down by a cell of 4 units in the grid of the bottom layer of nodes.
+    let skirt_depth = 4.0 * CubeSphere::new(&create_settings(Some(2))).scale();
+    let mut skirt_vertices = 0;
+    for ((min, max), chunk) in chunks {
+      let vertices = chunk.regular.vertices();
+      assert!(!vertices.is_empty(), "Chunk in {:?} does not contain the surface", (min, max));
+      // A quarter of a face spans 22.5 degrees, so its bounds are far smaller than the planet.
+      assert!((max - min).component_max() < RADIUS, "Culling bounds {:?} are not tight", (min, max));
+      for vertex in vertices {
+        let height = vertex.position.mag() - RADIUS;
+        if height.abs() >= 1.0 {
+          // Below the surface, the vertex must be part of a skirt, which lies on an edge between faces, where the 2
+          // largest components of the direction from the center are equal.
+          let direction = vertex.position.normalized().abs();
+          let largest = direction.component_max();
+          let on_edge = [direction.x, direction.y, direction.z].into_iter().filter(|c| *c < largest - 1e-3).count() < 2;
+          assert!(on_edge && height > -skirt_depth - 1.0, "Vertex {:?} is not on the surface or on a skirt", vertex.position);
+          skirt_vertices += 1;
+        }
+        assert_eq!(vertex.position.clamped(min, max), vertex.position, "Vertex is outside of culling bounds {:?}", (min, max));
+      }
+    }
+    assert!(skirt_vertices > 0, "Chunks at the edges of faces have no skirts");
+  }
+}
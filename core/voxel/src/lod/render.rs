@@ -3,31 +3,41 @@ use std::ops::Range;
 use ultraviolet::{Mat4, Vec3, Vec4};
 
 use gfx::{Gfx, GfxFrame};
+use gfx::camera::frustum::Frustum;
 use gfx::debug_renderer::DebugRenderer;
 
 use crate::chunk::mesh::{ChunkMesh, Vertex};
 use crate::chunk::size::ChunkSize;
-use crate::lod::chunk_mesh::{LodChunkMesh, LodChunkMeshManager, LodChunkMeshManagerParameters};
+use crate::lod::chunk_mesh::{LodChunkMesh, LodChunkMeshManager, LodChunkMeshManagerParameters, LodObserver};
 use crate::lod::extract::LodExtractor;
 
 // Trait
 
 pub trait LodRenderDataManager<C: ChunkSize> {
+  /// Updates the LOD chunk meshes for `observers`, and copies the meshes that are inside the frustum of
+  /// `view_projection_matrix` into `data`.
   fn update(
     &mut self,
-    position: Vec3,
+    observers: &[LodObserver],
+    view_projection_matrix: Mat4,
     settings: &LodRenderDataSettings,
     data: &mut LodRenderData,
   );
 
   fn debug_render(&mut self, gfx: &Gfx, frame: &mut GfxFrame, view_projection_matrix: Mat4, data: &LodRenderData);
 
+  fn get_mesh_manager_parameters(&self) -> &dyn LodChunkMeshManagerParameters;
   fn get_mesh_manager_parameters_mut(&mut self) -> &mut dyn LodChunkMeshManagerParameters;
 }
 
 #[derive(Copy, Clone, Debug)]
 #[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 pub struct LodRenderDataSettings {
+  pub frustum_culling: bool,
+  /// Fraction of the distance at which a parent LOD replaces its children, over which the vertices of the children are
+  /// morphed towards their position at the parent LOD. 0 disables geomorphing.
+  pub geomorph_range: f32,
   pub debug_render_vertices: bool,
   pub debug_render_vertex_color: Vec4,
   pub debug_render_vertex_point_size: f32,
@@ -36,6 +46,8 @@ pub struct LodRenderDataSettings {
   pub debug_render_octree_nodes: bool,
   pub debug_render_octree_node_color: Vec4,
   pub debug_render_octree_node_empty_color: Vec4,
+  pub debug_render_culled_octree_nodes: bool,
+  pub debug_render_octree_node_culled_color: Vec4,
   pub debug_render_octree_aabb_closest_points: bool,
   pub debug_render_octree_aabb_closest_points_color: Vec4,
   pub debug_render_octree_aabb_closest_points_point_size: f32,
@@ -43,6 +55,8 @@ pub struct LodRenderDataSettings {
 impl Default for LodRenderDataSettings {
   fn default() -> Self {
     Self {
+      frustum_culling: true,
+      geomorph_range: 0.3,
       debug_render_vertices: false,
       debug_render_vertex_color: Vec4::new(0.0, 0.0, 0.5, 0.5),
       debug_render_vertex_point_size: 3.0,
@@ -51,6 +65,8 @@ impl Default for LodRenderDataSettings {
       debug_render_octree_nodes: true,
       debug_render_octree_node_color: Vec4::new(0.0, 0.1, 0.0, 0.1),
       debug_render_octree_node_empty_color: Vec4::new(0.1, 0.0, 0.0, 0.1),
+      debug_render_culled_octree_nodes: false,
+      debug_render_octree_node_culled_color: Vec4::new(0.1, 0.1, 0.0, 0.1),
       debug_render_octree_aabb_closest_points: false,
       debug_render_octree_aabb_closest_points_color: Vec4::new(0.0, 0.0, 0.1, 0.1),
       debug_render_octree_aabb_closest_points_point_size: 3.0,
@@ -64,6 +80,10 @@ pub struct LodRenderData {
   pub indices: Vec<u16>,
   pub draws: Vec<LodDraw>,
   pub model: Mat4,
+  /// Distance from the viewer at which LOD nodes are replaced by their parent, relative to the size of the parent. 0
+  /// disables geomorphing.
+  pub lod_distance_per_size: f32,
+  pub geomorph_range: f32,
 }
 impl LodRenderData {
   #[inline]
@@ -100,7 +120,8 @@ impl<C: ChunkSize, E: LodExtractor<C>, MM> LodRenderDataManager<C> for SimpleLod
   #[profiling::function]
   fn update(
     &mut self,
-    position: Vec3,
+    observers: &[LodObserver],
+    view_projection_matrix: Mat4,
     settings: &LodRenderDataSettings,
     data: &mut LodRenderData,
   ) {
@@ -108,35 +129,57 @@ impl<C: ChunkSize, E: LodExtractor<C>, MM> LodRenderDataManager<C> for SimpleLod
     data.clear();
 
     let extractor = self.chunk_mesh_manager.get_extractor().clone();
-    let (root_half_size, transform, lod_chunk_meshes) = self.chunk_mesh_manager.update(position);
+    // Geomorph for the first observer, which is the viewer that the chunk meshes are rendered for.
+    data.lod_distance_per_size = self.chunk_mesh_manager.get_geomorph_distance_per_size(observers.first().and_then(|observer| observer.lod_factor));
+    data.geomorph_range = settings.geomorph_range;
+    let (transform, lod_chunk_meshes) = self.chunk_mesh_manager.update_observers(observers);
     data.model = transform.into_homogeneous_matrix();
 
-    // Transform the position into one local to AABBs, used when `debug_render_octree_aabb_closest_points` is true.
+    // Transform the positions of observers into ones local to AABBs, used when `debug_render_octree_aabb_closest_points`
+    // is true.
     let transform_inverse = transform.inversed();
-    let aabb_local_position = transform_inverse.transform_vec(position);
-
-    for (aabb, lod_chunk_mesh) in lod_chunk_meshes {
+    let aabb_local_positions: Vec<Vec3> = if settings.debug_render_octree_aabb_closest_points {
+      observers.iter().map(|observer| transform_inverse.transform_vec(observer.position)).collect()
+    } else {
+      Vec::new()
+    };
+    // Frustum in the local space of AABBs, by including the transform in the view-projection matrix.
+    let aabb_local_frustum = Frustum::from_view_projection_matrix(&(view_projection_matrix * data.model));
+
+    for (offset, (min, max), lod_chunk_mesh) in lod_chunk_meshes {
       let is_empty = lod_chunk_mesh.is_empty();
-      if !is_empty {
+      let is_culled = settings.frustum_culling && !aabb_local_frustum.intersects_aabb(min, max);
+      if !is_empty && !is_culled {
+        let first_vertex = data.vertices.len();
         extractor.update_render_data(&lod_chunk_mesh, &mut data.vertices, &mut data.indices, &mut data.draws);
+        if offset != Vec3::zero() { // Move vertices from the local space of their root into the space of `data.model`.
+          for vertex in &mut data.vertices[first_vertex..] {
+            vertex.position += offset;
+            vertex.morph_position += offset;
+          }
+        }
       }
-      if settings.debug_render_octree_nodes {
-        let min = aabb.minimum_point(root_half_size).into();
-        let size = aabb.size(root_half_size) as f32;
+      if is_culled {
+        if settings.debug_render_culled_octree_nodes {
+          self.debug_renderer.draw_box_lines(min, max, settings.debug_render_octree_node_culled_color);
+        }
+      } else if settings.debug_render_octree_nodes {
         if is_empty {
-          self.debug_renderer.draw_cube_lines(min, size, settings.debug_render_octree_node_empty_color);
+          self.debug_renderer.draw_box_lines(min, max, settings.debug_render_octree_node_empty_color);
         } else {
-          self.debug_renderer.draw_cube_lines(min, size, settings.debug_render_octree_node_color);
+          self.debug_renderer.draw_box_lines(min, max, settings.debug_render_octree_node_color);
         }
       }
       if settings.debug_render_octree_aabb_closest_points {
-        // Use `aabb_local_position` here because AABBs are in their own local space. Afterwards, we do not have to
+        // Use `aabb_local_positions` here because AABBs are in their own local space. Afterwards, we do not have to
         // transform back because the debug renderer will transform everything into world space using the
         // (non-inverse) transform.
-        let aabb_local_closest_point = aabb.closest_point(root_half_size, aabb_local_position);
-        let color = settings.debug_render_octree_aabb_closest_points_color;
-        self.debug_renderer.draw_point(aabb_local_closest_point, color, settings.debug_render_octree_aabb_closest_points_point_size);
-        self.debug_renderer.draw_line(aabb_local_position, aabb_local_closest_point, color, color);
+        for aabb_local_position in &aabb_local_positions {
+          let aabb_local_closest_point = aabb_local_position.clamped(min, max);
+          let color = settings.debug_render_octree_aabb_closest_points_color;
+          self.debug_renderer.draw_point(aabb_local_closest_point, color, settings.debug_render_octree_aabb_closest_points_point_size);
+          self.debug_renderer.draw_line(*aabb_local_position, aabb_local_closest_point, color, color);
+        }
       }
     }
 
@@ -159,6 +202,11 @@ impl<C: ChunkSize, E: LodExtractor<C>, MM> LodRenderDataManager<C> for SimpleLod
     self.debug_renderer.render(gfx, frame, view_projection_matrix * data.model);
   }
 
+  #[inline]
+  fn get_mesh_manager_parameters(&self) -> &dyn LodChunkMeshManagerParameters {
+    &self.chunk_mesh_manager
+  }
+
   #[inline]
   fn get_mesh_manager_parameters_mut(&mut self) -> &mut dyn LodChunkMeshManagerParameters {
     &mut self.chunk_mesh_manager
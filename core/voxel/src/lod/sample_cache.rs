@@ -0,0 +1,587 @@
+use std::fs::{File, OpenOptions, TryLockError};
+use std::hash::Hasher;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use rustc_hash::FxHashMap;
+use tracing::warn;
+use twox_hash::XxHash64;
+
+use crate::chunk::sample::{ChunkSampleQuantization, MaybeCompressedChunkSampleArray};
+use crate::chunk::size::ChunkSize;
+use crate::lod::aabb::Aabb;
+use crate::volume::Volume;
+
+/// Persistent on-disk cache of sampled chunks, keyed by (volume description hash, [`Aabb`], [`ChunkSize`]).
+///
+/// Chunks are stored in region files under `{directory}/{volume hash}/{cells in chunk row}/`. A region file contains
+/// all cached chunks at one depth that share an ancestor [`REGION_DEPTH`] levels up. Region files start with a header
+/// containing a magic number, format version, and chunk size; files with a non-matching header are discarded. Entries
+/// are appended to region files as an AABB code, payload length, and LZ4 compressed payload. Removing a chunk appends
+/// an entry with an empty payload. Region files in which replaced and removed entries take up more bytes than live
+/// entries are compacted when they are opened.
+///
+/// Each region file is opened at most once, and its handle is shared by all threads that use it, so that appends are
+/// never interleaved with appends through another handle. Open region files are exclusively locked, so that other
+/// processes (or other caches in this process) using the same directory skip caching in those regions until they are
+/// closed.
+///
+/// The cache is best-effort: IO errors are logged and treated as cache misses.
+pub struct ChunkSampleCache<C: ChunkSize> {
+  directory: PathBuf,
+  regions: Mutex<OpenRegions>,
+  _phantom: PhantomData<C>,
+}
+
+/// Number of depth levels grouped into a single region file, e.g., `3` stores up to `8^3 = 512` chunks per region.
+pub const REGION_DEPTH: u8 = 3;
+/// Number of region files kept open. When exceeded, the least recently used region that is not in use is closed.
+const MAX_OPEN_REGIONS: usize = 64;
+
+const MAGIC: [u8; 4] = *b"VXSC";
+const FORMAT_VERSION: u32 = 4;
+const HEADER_LEN: u64 = 12;
+const ENTRY_HEADER_LEN: u64 = 12;
+
+impl<C: ChunkSize> ChunkSampleCache<C> {
+  /// Creates a cache for samples of `volume` in a `root_size` octmap quantized with `quantization`, storing files in a
+  /// subdirectory of `directory`.
+  pub fn new<V: Volume>(directory: impl AsRef<Path>, volume: &V, root_size: u32, quantization: ChunkSampleQuantization) -> Self {
+    let mut hasher = StableHasher::default();
+    volume.hash_description(&mut hasher);
+    hasher.write_u32(root_size);
+    hasher.write_u8(quantization as u8);
+    let directory = directory.as_ref()
+      .join(format!("{:016x}", hasher.finish()))
+      .join(C::CELLS_IN_CHUNK_ROW.to_string());
+    Self { directory, regions: Mutex::new(OpenRegions::default()), _phantom: PhantomData }
+  }
+
+  #[inline]
+  pub fn directory(&self) -> &Path { &self.directory }
+
+  /// Gets the cached samples of `aabb`, returning `None` if they are not cached or could not be read.
+  #[profiling::function]
+  pub fn get(&self, aabb: Aabb) -> Option<MaybeCompressedChunkSampleArray<C>> {
+    let aabb = aabb.with_user_bit_unset();
+    let region = self.region(aabb)?;
+    let mut region = region.lock().unwrap();
+    match region.read(aabb) {
+      Ok(Some(bytes)) => {
+        let samples = lz4_flex::decompress_size_prepended(&bytes).ok()
+          .and_then(|bytes| MaybeCompressedChunkSampleArray::<C>::decode(&bytes));
+        if samples.is_none() {
+          warn!("Discarding corrupt cached samples of {:?} in '{}'", aabb, region.path.display());
+        }
+        samples
+      }
+      Ok(None) => None,
+      Err(e) => {
+        warn!("Failed to read cached samples of {:?} from '{}': {}", aabb, region.path.display(), e);
+        None
+      }
+    }
+  }
+
+  /// Stores `samples` of `aabb` in the cache.
+  #[profiling::function]
+  pub fn insert(&self, aabb: Aabb, samples: &MaybeCompressedChunkSampleArray<C>) {
+    let aabb = aabb.with_user_bit_unset();
+    let Some(region) = self.region(aabb) else { return; };
+    let mut bytes = Vec::new();
+    samples.encode(&mut bytes);
+    let bytes = lz4_flex::compress_prepend_size(&bytes);
+    let mut region = region.lock().unwrap();
+    if let Err(e) = region.append(aabb, &bytes) {
+      warn!("Failed to write cached samples of {:?} to '{}': {}", aabb, region.path.display(), e);
+    }
+  }
+
+  /// Removes the cached samples of `aabb` from the cache, for example after editing the volume in that chunk.
+  #[profiling::function]
+  pub fn remove(&self, aabb: Aabb) {
+    let aabb = aabb.with_user_bit_unset();
+    let Some(region) = self.region(aabb) else { return; };
+    let mut region = region.lock().unwrap();
+    if let Err(e) = region.remove(aabb) {
+      warn!("Failed to remove cached samples of {:?} from '{}': {}", aabb, region.path.display(), e);
+    }
+  }
+
+  /// Gets the shared handle of the region containing `aabb`, opening it if needed. Returns `None` if the region could
+  /// not be opened.
+  fn region(&self, aabb: Aabb) -> Option<RegionHandle> {
+    let key = RegionKey::from_aabb(aabb);
+    let mut regions = self.regions.lock().unwrap();
+    regions.clock += 1;
+    let clock = regions.clock;
+    if let Some((region, last_used)) = regions.map.get_mut(&key) {
+      *last_used = clock;
+      return region.clone();
+    }
+    if regions.map.len() >= MAX_OPEN_REGIONS {
+      regions.close_least_recently_used();
+    }
+    let region = match Region::open::<C>(self.region_path(key)) {
+      Ok(region) => Some(Arc::new(Mutex::new(region))),
+      Err(RegionOpenError::Locked) => return None, // Retry next time, as the lock may be released.
+      Err(RegionOpenError::Io(path, e)) => {
+        warn!("Failed to open sample cache region file '{}': {}", path.display(), e);
+        None
+      }
+    };
+    regions.map.insert(key, (region.clone(), clock));
+    region
+  }
+
+  #[inline]
+  fn region_path(&self, key: RegionKey) -> PathBuf {
+    self.directory.join(format!("{}-{:x}.region", key.depth, key.code))
+  }
+}
+
+/// Hasher whose hashes are stable across runs, builds, and platforms, for naming cache directories.
+#[derive(Default)]
+struct StableHasher(XxHash64);
+
+impl Hasher for StableHasher {
+  #[inline]
+  fn finish(&self) -> u64 { self.0.finish() }
+  #[inline]
+  fn write(&mut self, bytes: &[u8]) { self.0.write(bytes) }
+
+  // Hash integers as little-endian bytes, as the default implementations use native-endian bytes.
+  #[inline]
+  fn write_u8(&mut self, i: u8) { self.write(&[i]) }
+  #[inline]
+  fn write_u16(&mut self, i: u16) { self.write(&i.to_le_bytes()) }
+  #[inline]
+  fn write_u32(&mut self, i: u32) { self.write(&i.to_le_bytes()) }
+  #[inline]
+  fn write_u64(&mut self, i: u64) { self.write(&i.to_le_bytes()) }
+  #[inline]
+  fn write_u128(&mut self, i: u128) { self.write(&i.to_le_bytes()) }
+  #[inline]
+  fn write_usize(&mut self, i: usize) { self.write_u64(i as u64) }
+  #[inline]
+  fn write_i8(&mut self, i: i8) { self.write_u8(i as u8) }
+  #[inline]
+  fn write_i16(&mut self, i: i16) { self.write_u16(i as u16) }
+  #[inline]
+  fn write_i32(&mut self, i: i32) { self.write_u32(i as u32) }
+  #[inline]
+  fn write_i64(&mut self, i: i64) { self.write_u64(i as u64) }
+  #[inline]
+  fn write_i128(&mut self, i: i128) { self.write_u128(i as u128) }
+  #[inline]
+  fn write_isize(&mut self, i: isize) { self.write_u64(i as u64) }
+}
+
+
+// Open regions
+
+type RegionHandle = Arc<Mutex<Region>>;
+
+/// Open regions with the time they were last used, including regions that failed to open (`None`) so that opening
+/// them is not retried.
+#[derive(Default)]
+struct OpenRegions {
+  map: FxHashMap<RegionKey, (Option<RegionHandle>, u64)>,
+  clock: u64,
+}
+
+impl OpenRegions {
+  /// Closes the least recently used region that is not in use. Handles are only cloned while the regions are locked,
+  /// so a region whose handle is not referenced elsewhere cannot be in use, and closing it never results in two open
+  /// handles of the same file. Does nothing if all regions are in use.
+  fn close_least_recently_used(&mut self) {
+    let least_recently_used = self.map.iter()
+      .filter(|(_, (region, _))| region.as_ref().is_none_or(|region| Arc::strong_count(region) == 1))
+      .min_by_key(|(_, (_, last_used))| *last_used)
+      .map(|(key, _)| *key);
+    if let Some(key) = least_recently_used {
+      self.map.remove(&key);
+    }
+  }
+}
+
+
+// Region
+
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+struct RegionKey {
+  depth: u8,
+  code: u64,
+}
+
+impl RegionKey {
+  #[inline]
+  fn from_aabb(aabb: Aabb) -> Self {
+    let depth = aabb.depth();
+    let code = aabb.code() >> (3 * depth.min(REGION_DEPTH) as u32);
+    Self { depth, code }
+  }
+}
+
+struct Region {
+  path: PathBuf,
+  file: File,
+  end: u64,
+  entries: FxHashMap<Aabb, (u64, u32)>,
+}
+
+enum RegionOpenError {
+  /// The region file is locked by another handle.
+  Locked,
+  Io(PathBuf, io::Error),
+}
+
+impl Region {
+  fn open<C: ChunkSize>(path: PathBuf) -> Result<Self, RegionOpenError> {
+    match Self::open_inner::<C>(&path) {
+      Ok(Some((file, end, entries))) => {
+        let mut region = Self { path, file, end, entries };
+        if let Err(e) = region.compact_if_mostly_dead() {
+          return Err(RegionOpenError::Io(region.path, e));
+        }
+        Ok(region)
+      }
+      Ok(None) => Err(RegionOpenError::Locked),
+      Err(e) => Err(RegionOpenError::Io(path, e)),
+    }
+  }
+
+  /// Opens and locks the region file at `path`, indexing its entries. Returns `Ok(None)` if the file is locked.
+  fn open_inner<C: ChunkSize>(path: &Path) -> io::Result<Option<(File, u64, FxHashMap<Aabb, (u64, u32)>)>> {
+    if let Some(parent) = path.parent() {
+      std::fs::create_dir_all(parent)?;
+    }
+    let mut file = OpenOptions::new().read(true).write(true).create(true).truncate(false).open(path)?;
+    // Lock before reading, as the file may be reset or truncated below. The lock is released when the file is closed.
+    match file.try_lock() {
+      Ok(()) => {}
+      Err(TryLockError::WouldBlock) => return Ok(None),
+      Err(TryLockError::Error(e)) => return Err(e),
+    }
+    let file_len = file.metadata()?.len();
+
+    let mut header = [0u8; HEADER_LEN as usize];
+    header[0..4].copy_from_slice(&MAGIC);
+    header[4..8].copy_from_slice(&FORMAT_VERSION.to_le_bytes());
+    header[8..12].copy_from_slice(&C::CELLS_IN_CHUNK_ROW.to_le_bytes());
+    let mut file_header = [0u8; HEADER_LEN as usize];
+    if file_len < HEADER_LEN || file.read_exact(&mut file_header).is_err() || file_header != header { // New file, or written by a different format version or chunk size: start over.
+      file.set_len(0)?;
+      file.seek(SeekFrom::Start(0))?;
+      file.write_all(&header)?;
+      return Ok(Some((file, HEADER_LEN, FxHashMap::default())));
+    }
+
+    // Index entries by reading only their headers. Later entries of the same AABB replace earlier ones.
+    let mut entries = FxHashMap::default();
+    let mut offset = HEADER_LEN;
+    let mut entry_header = [0u8; ENTRY_HEADER_LEN as usize];
+    while offset + ENTRY_HEADER_LEN <= file_len {
+      file.seek(SeekFrom::Start(offset))?;
+      file.read_exact(&mut entry_header)?;
+      let code = u64::from_le_bytes(entry_header[0..8].try_into().unwrap());
+      let len = u32::from_le_bytes(entry_header[8..12].try_into().unwrap());
+      let payload_offset = offset + ENTRY_HEADER_LEN;
+      let Some(aabb) = Aabb::from_code(code) else { break; };
+      if payload_offset + len as u64 > file_len { break; }
+      if len == 0 { // Removed.
+        entries.remove(&aabb);
+      } else {
+        entries.insert(aabb, (payload_offset, len));
+      }
+      offset = payload_offset + len as u64;
+    }
+    if offset != file_len { // Drop a partially written or corrupt tail.
+      file.set_len(offset)?;
+    }
+    Ok(Some((file, offset, entries)))
+  }
+
+  /// Rewrites the region file with only its live entries if replaced and removed entries take up more bytes than live
+  /// entries. Live entries are read into memory and the file is truncated before they are written back, so that an
+  /// interrupted rewrite loses entries instead of leaving stale entries behind.
+  fn compact_if_mostly_dead(&mut self) -> io::Result<()> {
+    let live_len: u64 = self.entries.values().map(|(_, len)| ENTRY_HEADER_LEN + *len as u64).sum();
+    let dead_len = self.end - HEADER_LEN - live_len;
+    if dead_len <= live_len { return Ok(()); }
+    let mut live_entries = Vec::with_capacity(self.entries.len());
+    for (&aabb, &(offset, len)) in &self.entries {
+      let mut payload = vec![0; len as usize];
+      self.file.seek(SeekFrom::Start(offset))?;
+      self.file.read_exact(&mut payload)?;
+      live_entries.push((offset, aabb, payload));
+    }
+    live_entries.sort_unstable_by_key(|(offset, _, _)| *offset); // Keep the order of entries.
+    self.file.set_len(HEADER_LEN)?;
+    self.end = HEADER_LEN;
+    self.entries.clear();
+    for (_, aabb, payload) in live_entries {
+      self.append(aabb, &payload)?;
+    }
+    Ok(())
+  }
+
+  fn read(&mut self, aabb: Aabb) -> io::Result<Option<Vec<u8>>> {
+    let Some(&(offset, len)) = self.entries.get(&aabb) else { return Ok(None); };
+    let mut bytes = vec![0; len as usize];
+    self.file.seek(SeekFrom::Start(offset))?;
+    self.file.read_exact(&mut bytes)?;
+    Ok(Some(bytes))
+  }
+
+  fn remove(&mut self, aabb: Aabb) -> io::Result<()> {
+    if !self.entries.contains_key(&aabb) { return Ok(()); }
+    self.append(aabb, &[])?;
+    self.entries.remove(&aabb);
+    Ok(())
+  }
+
+  fn append(&mut self, aabb: Aabb, payload: &[u8]) -> io::Result<()> {
+    let len = payload.len() as u32;
+    let mut entry = Vec::with_capacity(ENTRY_HEADER_LEN as usize + payload.len());
+    entry.extend_from_slice(&aabb.code().to_le_bytes());
+    entry.extend_from_slice(&len.to_le_bytes());
+    entry.extend_from_slice(payload);
+    self.file.seek(SeekFrom::Start(self.end))?;
+    if let Err(e) = self.file.write_all(&entry) {
+      let _ = self.file.set_len(self.end); // Try to remove the partially written entry.
+      return Err(e);
+    }
+    self.entries.insert(aabb, (self.end + ENTRY_HEADER_LEN, len));
+    self.end += entry.len() as u64;
+    Ok(())
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use std::fs::OpenOptions;
+  use std::path::{Path, PathBuf};
+
+  use crate::chunk::sample::{ChunkSampleArray, ChunkSampleQuantization, ChunkSamplesMut, MaybeCompressedChunkSampleArray, MaybeCompressedChunkSamples};
+  use crate::chunk::size::ChunkSize16;
+  use crate::lod::aabb::Aabb;
+  use crate::lod::sample_cache::{ChunkSampleCache, HEADER_LEN, OpenRegions, RegionKey};
+  use crate::volume::{Sphere, SphereSettings};
+
+  type C16 = ChunkSize16;
+
+  /// Temporary directory that is removed when dropped.
+  struct TestDirectory(PathBuf);
+
+  impl TestDirectory {
+    fn new(name: &str) -> Self {
+      let path = std::env::temp_dir().join(format!("voxel-sample-cache-test-{}-{}", name, std::process::id()));
+      let _ = std::fs::remove_dir_all(&path);
+      Self(path)
+    }
+  }
+
+  impl Drop for TestDirectory {
+    fn drop(&mut self) {
+      let _ = std::fs::remove_dir_all(&self.0);
+    }
+  }
+
+  fn create_cache(directory: &TestDirectory) -> ChunkSampleCache<C16> {
+    ChunkSampleCache::new(&directory.0, &Sphere::new(SphereSettings::default()), 4096, ChunkSampleQuantization::None)
+  }
+
+  fn mixed_samples(offset: f32) -> MaybeCompressedChunkSampleArray<C16> {
+    let mut array = ChunkSampleArray::<C16>::new_positive_zeroed();
+    array.set(1, 2, 3, -1.0 - offset);
+    array.set(4, 5, 6, 1.0 + offset);
+    MaybeCompressedChunkSamples::Mixed(Box::new(array))
+  }
+
+  fn encoded(samples: &MaybeCompressedChunkSampleArray<C16>) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    samples.encode(&mut bytes);
+    bytes
+  }
+
+  fn assert_cached(cache: &ChunkSampleCache<C16>, aabb: Aabb, samples: &MaybeCompressedChunkSampleArray<C16>) {
+    let cached = cache.get(aabb).unwrap_or_else(|| panic!("Samples of {:?} are not cached", aabb));
+    assert_eq!(encoded(&cached), encoded(samples), "Cached samples of {:?} differ", aabb);
+  }
+
+  /// Gets the path of the region file of the children of the root, which all share one region.
+  fn region_path(cache: &ChunkSampleCache<C16>) -> PathBuf {
+    cache.region_path(RegionKey::from_aabb(Aabb::root().subdivide().base))
+  }
+
+  fn file_len(path: &Path) -> u64 { std::fs::metadata(path).unwrap().len() }
+
+  #[test]
+  fn inserted_samples_are_read_back_after_reopening() {
+    let directory = TestDirectory::new("round_trip");
+    let children = Aabb::root().subdivide();
+    let uniform = MaybeCompressedChunkSamples::Uniform(0.5);
+    {
+      let cache = create_cache(&directory);
+      assert!(cache.get(children.base).is_none());
+      cache.insert(children.base, &mixed_samples(0.0));
+      cache.insert(children.x, &uniform);
+      cache.insert(Aabb::root(), &MaybeCompressedChunkSamples::Negative);
+      assert_cached(&cache, children.base, &mixed_samples(0.0));
+      assert_cached(&cache, children.x, &uniform);
+      assert!(cache.get(children.y).is_none());
+    }
+    let cache = create_cache(&directory);
+    assert_cached(&cache, children.base, &mixed_samples(0.0));
+    assert_cached(&cache, children.x, &uniform);
+    assert_cached(&cache, Aabb::root(), &MaybeCompressedChunkSamples::Negative);
+    assert!(cache.get(children.y).is_none());
+    // The user bit is not part of the key.
+    assert_cached(&cache, children.base.with_user_bit_set(), &mixed_samples(0.0));
+  }
+
+  #[test]
+  fn removed_samples_stay_removed_after_reopening() {
+    let directory = TestDirectory::new("remove");
+    let children = Aabb::root().subdivide();
+    let path = {
+      let cache = create_cache(&directory);
+      cache.insert(children.base, &mixed_samples(0.0));
+      cache.insert(children.x, &mixed_samples(1.0));
+      let len = file_len(&region_path(&cache));
+      cache.remove(children.base);
+      assert!(cache.get(children.base).is_none());
+      // A removal appends an entry with an empty payload: just the entry header.
+      assert_eq!(file_len(&region_path(&cache)), len + super::ENTRY_HEADER_LEN);
+      // Removing samples that are not cached appends nothing.
+      cache.remove(children.y);
+      assert_eq!(file_len(&region_path(&cache)), len + super::ENTRY_HEADER_LEN);
+      region_path(&cache)
+    };
+    let cache = create_cache(&directory);
+    assert!(cache.get(children.base).is_none());
+    assert_cached(&cache, children.x, &mixed_samples(1.0));
+    assert!(path.exists());
+  }
+
+  #[test]
+  fn truncated_tail_is_dropped() {
+    let directory = TestDirectory::new("truncated");
+    let children = Aabb::root().subdivide();
+    let (path, len_after_first) = {
+      let cache = create_cache(&directory);
+      cache.insert(children.base, &mixed_samples(0.0));
+      let len_after_first = file_len(&region_path(&cache));
+      cache.insert(children.x, &mixed_samples(1.0));
+      (region_path(&cache), len_after_first)
+    };
+    // Simulate a crash while appending the second entry.
+    let len = file_len(&path);
+    OpenOptions::new().write(true).open(&path).unwrap().set_len(len - 3).unwrap();
+    let cache = create_cache(&directory);
+    assert_cached(&cache, children.base, &mixed_samples(0.0));
+    assert!(cache.get(children.x).is_none());
+    assert_eq!(file_len(&path), len_after_first, "Partially written entry was not dropped");
+    // New entries are appended after the last complete entry.
+    cache.insert(children.y, &mixed_samples(2.0));
+    drop(cache);
+    let cache = create_cache(&directory);
+    assert_cached(&cache, children.base, &mixed_samples(0.0));
+    assert_cached(&cache, children.y, &mixed_samples(2.0));
+  }
+
+  #[test]
+  fn region_with_mismatching_header_is_reset() {
+    let directory = TestDirectory::new("header");
+    let aabb = Aabb::root().subdivide().base;
+    // Magic number, format version, and chunk size.
+    for (offset, byte) in [(0, b'X'), (4, 0xFF), (8, 0xFF)] {
+      let path = {
+        let cache = create_cache(&directory);
+        cache.insert(aabb, &mixed_samples(0.0));
+        region_path(&cache)
+      };
+      let mut bytes = std::fs::read(&path).unwrap();
+      bytes[offset] = byte;
+      std::fs::write(&path, bytes).unwrap();
+      let cache = create_cache(&directory);
+      assert!(cache.get(aabb).is_none(), "Samples were read from a region file with a mismatching header at byte {}", offset);
+      assert_eq!(file_len(&path), HEADER_LEN);
+    }
+  }
+
+  #[test]
+  fn mostly_dead_region_is_compacted_on_open() {
+    let directory = TestDirectory::new("compact");
+    let children = Aabb::root().subdivide();
+    let (path, live_bytes) = {
+      let cache = create_cache(&directory);
+      cache.insert(children.base, &mixed_samples(0.0));
+      cache.insert(children.x, &mixed_samples(1.0));
+      let live_bytes = std::fs::read(region_path(&cache)).unwrap();
+      // Replace one entry once: dead bytes do not outweigh live bytes.
+      cache.insert(children.x, &mixed_samples(1.0));
+      (region_path(&cache), live_bytes)
+    };
+    let len = file_len(&path);
+    assert_cached(&create_cache(&directory), children.x, &mixed_samples(1.0)); // Opens the region.
+    assert_eq!(file_len(&path), len, "Region was compacted while most bytes are live");
+    {
+      // Invalidate and sample again several times, ending with the original samples.
+      let cache = create_cache(&directory);
+      for offset in [2.0, 3.0, 4.0, 1.0] {
+        cache.remove(children.x);
+        cache.insert(children.x, &mixed_samples(offset));
+      }
+    }
+    let cache = create_cache(&directory);
+    assert_cached(&cache, children.base, &mixed_samples(0.0)); // Opens the region.
+    assert_cached(&cache, children.x, &mixed_samples(1.0));
+    assert_eq!(std::fs::read(&path).unwrap(), live_bytes, "Region was not compacted to its live entries in order");
+  }
+
+  #[test]
+  fn locked_region_is_skipped_until_released() {
+    let directory = TestDirectory::new("lock");
+    let aabb = Aabb::root().subdivide().base;
+    let cache = create_cache(&directory);
+    cache.insert(aabb, &mixed_samples(0.0));
+    // Another cache (e.g., of another process) sharing the directory does not use the locked region.
+    let other_cache = create_cache(&directory);
+    assert!(other_cache.get(aabb).is_none());
+    other_cache.insert(aabb, &mixed_samples(1.0));
+    other_cache.remove(aabb);
+    assert_cached(&cache, aabb, &mixed_samples(0.0));
+    // Once the region is closed, the other cache opens it.
+    drop(cache);
+    assert_cached(&other_cache, aabb, &mixed_samples(0.0));
+  }
+
+  #[test]
+  fn least_recently_used_region_not_in_use_is_closed() {
+    let key = |code| RegionKey { depth: 1, code };
+    let directory = TestDirectory::new("lru");
+    let cache = create_cache(&directory);
+    let mut regions = OpenRegions::default();
+    let in_use = cache.region(Aabb::root().subdivide().base).unwrap();
+    regions.map.insert(key(1), (Some(in_use.clone()), 1));
+    regions.map.insert(key(2), (None, 2)); // Failed to open.
+    regions.map.insert(key(3), (cache.region(Aabb::root().subdivide().base.subdivide().base), 3));
+    drop(cache); // Release the handles of the cache, keeping `in_use`.
+    // The least recently used region is in use, so the next least recently used one is closed.
+    regions.close_least_recently_used();
+    assert!(regions.map.contains_key(&key(1)) && !regions.map.contains_key(&key(2)) && regions.map.contains_key(&key(3)));
+    regions.close_least_recently_used();
+    assert!(regions.map.contains_key(&key(1)) && !regions.map.contains_key(&key(3)));
+    // All remaining regions are in use.
+    regions.close_least_recently_used();
+    assert!(regions.map.contains_key(&key(1)));
+    drop(in_use);
+    regions.close_least_recently_used();
+    assert!(regions.map.is_empty());
+  }
+}
@@ -1,7 +1,13 @@
 use std::borrow::Borrow;
 use std::marker::PhantomData;
 
+use ultraviolet::{IVec3, UVec3};
+
+use job_queue::CancellationToken;
+
 use crate::chunk::mesh::{ChunkMesh, Vertex};
+use crate::chunk::morph::Geomorph;
+use crate::chunk::occlusion::AmbientOcclusionSettings;
 use crate::chunk::sample::MaybeCompressedChunkSampleArray;
 use crate::chunk::size::ChunkSize;
 use crate::lod::aabb::{Aabb, AabbWithSize};
@@ -25,6 +31,8 @@ pub struct SurfaceNetsExtractorSettings {
   pub extract_border_xy_chunks: bool,
   pub extract_border_yz_chunks: bool,
   pub extract_border_xz_chunks: bool,
+  #[cfg_attr(feature = "serde", serde(default))]
+  pub ambient_occlusion: AmbientOcclusionSettings,
 }
 impl Default for SurfaceNetsExtractorSettings {
   #[inline]
@@ -37,6 +45,7 @@ impl Default for SurfaceNetsExtractorSettings {
       extract_border_xy_chunks: false,
       extract_border_yz_chunks: false,
       extract_border_xz_chunks: false,
+      ambient_occlusion: Default::default(),
     }
   }
 }
@@ -65,16 +74,22 @@ impl<C: ChunkSize> LodExtractor<C> for SurfaceNetsExtractor<C> {
     volume: V,
     empty_lod_chunk_mesh: Self::Chunk,
   ) -> (Self::JobInput, Self::DependenciesIterator<V>) {
-    let input = SurfaceNetsJobInput { aabb, empty_lod_chunk_mesh };
+    let input = self.create_job_input(aabb, empty_lod_chunk_mesh);
     let dependencies_iterator = SurfaceNetsJobDependenciesIterator::new(aabb.inner, neighbor_depths, volume, self.settings);
     (input, dependencies_iterator)
   }
 
+  #[inline]
+  fn create_job_input(&self, aabb: AabbWithSize, empty_lod_chunk_mesh: Self::Chunk) -> Self::JobInput {
+    SurfaceNetsJobInput { aabb, empty_lod_chunk_mesh }
+  }
+
   #[inline]
   fn run_job(
     &self,
     input: Self::JobInput,
     dependency_outputs: &[(Self::DependencyKey, LodJobOutput<MaybeCompressedChunkSampleArray<C>, Self::Chunk>)],
+    cancellation_token: &CancellationToken,
   ) -> Self::Chunk {
     let mut chunk = input.empty_lod_chunk_mesh;
     // Gather samples
@@ -113,11 +128,14 @@ impl<C: ChunkSize> LodExtractor<C> for SurfaceNetsExtractor<C> {
       let aabb = input.aabb;
       let min = aabb.minimum_point();
       let step = aabb.step::<C>();
-      let min_x = aabb.sibling_positive_x().map(|aabb| aabb.minimum_point());
-      let min_y = aabb.sibling_positive_y().map(|aabb| aabb.minimum_point());
-      let min_z = aabb.sibling_positive_z().map(|aabb| aabb.minimum_point());
+      // Neighbors may lie in other roots, so derive their minimum points from ours instead of from their AABBs.
+      let size = aabb.size();
+      let min_x = min + UVec3::new(size, 0, 0);
+      let min_y = min + UVec3::new(0, size, 0);
+      let min_z = min + UVec3::new(0, 0, size);
       // Regular
       self.surface_nets.extract_chunk_from_maybe_compressed_samples(min, step, &chunk_samples, &mut chunk.regular);
+      if cancellation_token.is_cancelled() { return chunk; }
       // Positive X border
       if let (
         Some(LodJobOutput::Sample(chunk_samples_x_front))
@@ -125,54 +143,63 @@ impl<C: ChunkSize> LodExtractor<C> for SurfaceNetsExtractor<C> {
         , Some(LodJobOutput::Sample(chunk_samples_x_back))
         , Some(LodJobOutput::Sample(chunk_samples_x_back_y))
       ) = (chunk_samples_x_front, chunk_samples_x_front_y, chunk_samples_x_back, chunk_samples_x_back_y) {
-        let x_subdivided = aabb.sibling_positive_x().map(|aabb| aabb.subdivide_array());
-        let min_x_front = x_subdivided.map(|subdivided| subdivided[1].minimum_point(aabb.root_size)).unwrap();
-        let min_x_front_y = x_subdivided.map(|subdivided| subdivided[3].minimum_point(aabb.root_size)).unwrap();
-        let min_x_back = x_subdivided.map(|subdivided| subdivided[5].minimum_point(aabb.root_size)).unwrap();
-        let min_x_back_y = x_subdivided.map(|subdivided| subdivided[7].minimum_point(aabb.root_size)).unwrap();
+        let half_size = aabb.half_size();
+        let min_x_front = min_x + UVec3::new(half_size, 0, 0);
+        let min_x_front_y = min_x + UVec3::new(half_size, half_size, 0);
+        let min_x_back = min_x + UVec3::new(half_size, 0, half_size);
+        let min_x_back_y = min_x + UVec3::new(half_size, half_size, half_size);
         self.surface_nets_lod.extract_border_x_hires(step, min, &chunk_samples, step * 2, min_x_front, chunk_samples_x_front, min_x_front_y, chunk_samples_x_front_y, min_x_back, chunk_samples_x_back, min_x_back_y, chunk_samples_x_back_y, &mut chunk.border_x_chunk);
       } else if let Some(chunk_samples_x) = &chunk_samples_x {
         if let LodJobOutput::Sample(chunk_samples_x) = chunk_samples_x.borrow() {
-          self.surface_nets_lod.extract_border_x(step, min, &chunk_samples, min_x.unwrap(), chunk_samples_x, &mut chunk.border_x_chunk);
+          self.surface_nets_lod.extract_border_x(step, min, &chunk_samples, min_x, chunk_samples_x, &mut chunk.border_x_chunk);
         }
       }
       // Positive Y border
       if let Some(chunk_samples_y) = &chunk_samples_y {
         if let LodJobOutput::Sample(chunk_samples_y) = chunk_samples_y.borrow() {
-          self.surface_nets_lod.extract_border_y(step, min, &chunk_samples, min_y.unwrap(), chunk_samples_y, &mut chunk.border_y_chunk);
+          self.surface_nets_lod.extract_border_y(step, min, &chunk_samples, min_y, chunk_samples_y, &mut chunk.border_y_chunk);
         }
       }
       // Positive Z border
       if let Some(chunk_samples_z) = &chunk_samples_z {
         if let LodJobOutput::Sample(chunk_samples_z) = chunk_samples_z.borrow() {
-          self.surface_nets_lod.extract_border_z(step, min, &chunk_samples, min_z.unwrap(), chunk_samples_z, &mut chunk.border_z_chunk);
+          self.surface_nets_lod.extract_border_z(step, min, &chunk_samples, min_z, chunk_samples_z, &mut chunk.border_z_chunk);
         }
       }
       // Positive XY border
       if let (Some(chunk_samples_x), Some(chunk_samples_y), Some(chunk_samples_xy)) = (&chunk_samples_x, &chunk_samples_y, &chunk_samples_xy) {
         if let (LodJobOutput::Sample(chunk_samples_x), LodJobOutput::Sample(chunk_samples_y), LodJobOutput::Sample(chunk_samples_xy)) = (chunk_samples_x.borrow(), chunk_samples_y.borrow(), chunk_samples_xy.borrow()) {
-          let min_xy = aabb.sibling_positive_xy().unwrap().minimum_point();
-          self.surface_nets_lod.extract_border_xy(step, min, &chunk_samples, min_x.unwrap(), chunk_samples_x, min_y.unwrap(), chunk_samples_y, min_xy, chunk_samples_xy, &mut chunk.border_xy_chunk);
+          let min_xy = min + UVec3::new(size, size, 0);
+          self.surface_nets_lod.extract_border_xy(step, min, &chunk_samples, min_x, chunk_samples_x, min_y, chunk_samples_y, min_xy, chunk_samples_xy, &mut chunk.border_xy_chunk);
         }
       }
       // Positive YZ border
       if let (Some(chunk_samples_y), Some(chunk_samples_z), Some(chunk_samples_yz)) = (&chunk_samples_y, &chunk_samples_z, &chunk_samples_yz) {
         if let (LodJobOutput::Sample(chunk_samples_y), LodJobOutput::Sample(chunk_samples_z), LodJobOutput::Sample(chunk_samples_yz)) = (chunk_samples_y.borrow(), chunk_samples_z.borrow(), chunk_samples_yz.borrow()) {
-          let min_yz = aabb.sibling_positive_yz().unwrap().minimum_point();
-          self.surface_nets_lod.extract_border_yz(step, min, &chunk_samples, min_y.unwrap(), chunk_samples_y, min_z.unwrap(), chunk_samples_z, min_yz, chunk_samples_yz, &mut chunk.border_yz_chunk);
+          let min_yz = min + UVec3::new(0, size, size);
+          self.surface_nets_lod.extract_border_yz(step, min, &chunk_samples, min_y, chunk_samples_y, min_z, chunk_samples_z, min_yz, chunk_samples_yz, &mut chunk.border_yz_chunk);
         }
       }
       // Positive XZ border
       if let (Some(chunk_samples_x), Some(chunk_samples_z), Some(chunk_samples_xz)) = (&chunk_samples_x, &chunk_samples_z, &chunk_samples_xz) {
         if let (LodJobOutput::Sample(chunk_samples_x), LodJobOutput::Sample(chunk_samples_z), LodJobOutput::Sample(chunk_samples_xz)) = (chunk_samples_x.borrow(), chunk_samples_z.borrow(), chunk_samples_xz.borrow()) {
-          let min_xz = aabb.sibling_positive_xz().unwrap().minimum_point();
-          self.surface_nets_lod.extract_border_xz(step, min, &chunk_samples, min_x.unwrap(), chunk_samples_x, min_z.unwrap(), chunk_samples_z, min_xz, chunk_samples_xz, &mut chunk.border_xz_chunk);
+          let min_xz = min + UVec3::new(size, 0, size);
+          self.surface_nets_lod.extract_border_xz(step, min, &chunk_samples, min_x, chunk_samples_x, min_z, chunk_samples_z, min_xz, chunk_samples_xz, &mut chunk.border_xz_chunk);
         }
       }
+      if cancellation_token.is_cancelled() { return chunk; }
+      // Geomorphing
+      let geomorph = Geomorph::new();
+      for chunk_mesh in chunk.chunk_meshes_mut() {
+        geomorph.apply(min, step, chunk_samples.as_ref(), chunk_mesh);
+      }
     }
     chunk
   }
 
+  #[inline]
+  fn ambient_occlusion_settings(&self) -> AmbientOcclusionSettings { self.settings.ambient_occlusion }
+
   #[inline]
   fn update_render_data(&self, chunk: &Self::Chunk, vertices: &mut Vec<Vertex>, indices: &mut Vec<u16>, draws: &mut Vec<LodDraw>) {
     if self.settings.extract_regular_chunks {
@@ -218,16 +245,16 @@ pub struct SurfaceNetsJobInput {
 
 pub struct SurfaceNetsJobDependenciesIterator<C, V> {
   regular_aabb: Option<Aabb>,
-  x_aabb: Option<Aabb>,
-  x_front_aabb: Option<Aabb>,
-  x_front_y_aabb: Option<Aabb>,
-  x_back_aabb: Option<Aabb>,
-  x_back_y_aabb: Option<Aabb>,
-  y_aabb: Option<Aabb>,
-  z_aabb: Option<Aabb>,
-  xy_aabb: Option<Aabb>,
-  yz_aabb: Option<Aabb>,
-  xz_aabb: Option<Aabb>,
+  x_aabb: Option<(IVec3, Aabb)>,
+  x_front_aabb: Option<(IVec3, Aabb)>,
+  x_front_y_aabb: Option<(IVec3, Aabb)>,
+  x_back_aabb: Option<(IVec3, Aabb)>,
+  x_back_y_aabb: Option<(IVec3, Aabb)>,
+  y_aabb: Option<(IVec3, Aabb)>,
+  z_aabb: Option<(IVec3, Aabb)>,
+  xy_aabb: Option<(IVec3, Aabb)>,
+  yz_aabb: Option<(IVec3, Aabb)>,
+  xz_aabb: Option<(IVec3, Aabb)>,
   volume: V,
   _chunk_size_phantom: PhantomData<C>,
 }
@@ -235,19 +262,21 @@ pub struct SurfaceNetsJobDependenciesIterator<C, V> {
 impl<C: ChunkSize, V: Volume> SurfaceNetsJobDependenciesIterator<C, V> {
   #[inline]
   fn new(aabb: Aabb, neighbor_depths: NeighborDepths, volume: V, settings: SurfaceNetsExtractorSettings) -> Self {
+    // Neighbors may lie in a neighboring root when the octmap is part of a paged world, in which case `neighbor_depths`
+    // is non-zero for them; sample them from that root.
     let depth = aabb.depth();
-    let x_aabb = aabb.sibling_positive_x();
     let has_x_sibling = neighbor_depths.x != 0;
+    let x_aabb = has_x_sibling.then(|| aabb.neighbor_across_roots(IVec3::new(1, 0, 0)));
     let x_sibling_hires = neighbor_depths.x > depth;
-    let x_subdivided = x_aabb.map(|aabb| aabb.subdivide());
-    let x_front_aabb = x_subdivided.as_ref().map(|s| s.x);
-    let x_front_y_aabb = x_subdivided.as_ref().map(|s| s.xy);
-    let x_back_aabb = x_subdivided.as_ref().map(|s| s.xz);
-    let x_back_y_aabb = x_subdivided.as_ref().map(|s| s.xyz);
-    let y_aabb = aabb.sibling_positive_y();
+    let x_subdivided = x_aabb.map(|(root, aabb)| (root, aabb.subdivide()));
+    let x_front_aabb = x_subdivided.as_ref().map(|(root, s)| (*root, s.x));
+    let x_front_y_aabb = x_subdivided.as_ref().map(|(root, s)| (*root, s.xy));
+    let x_back_aabb = x_subdivided.as_ref().map(|(root, s)| (*root, s.xz));
+    let x_back_y_aabb = x_subdivided.as_ref().map(|(root, s)| (*root, s.xyz));
     let has_y_sibling = neighbor_depths.y != 0;
-    let z_aabb = aabb.sibling_positive_z();
+    let y_aabb = has_y_sibling.then(|| aabb.neighbor_across_roots(IVec3::new(0, 1, 0)));
     let has_z_sibling = neighbor_depths.z != 0;
+    let z_aabb = has_z_sibling.then(|| aabb.neighbor_across_roots(IVec3::new(0, 0, 1)));
     Self {
       regular_aabb: settings.extract_regular_chunks.then_some(aabb),
       x_aabb: ((!x_sibling_hires && settings.extract_border_x_chunks) || settings.extract_border_xy_chunks || settings.extract_border_xz_chunks).then_some(x_aabb).flatten(),
@@ -257,9 +286,9 @@ impl<C: ChunkSize, V: Volume> SurfaceNetsJobDependenciesIterator<C, V> {
       x_back_y_aabb: (x_sibling_hires && settings.extract_border_x_chunks).then_some(x_back_y_aabb).flatten(),
       y_aabb: (settings.extract_border_y_chunks || settings.extract_border_xy_chunks || settings.extract_border_yz_chunks).then_some(y_aabb).flatten(),
       z_aabb: (settings.extract_border_z_chunks || settings.extract_border_yz_chunks || settings.extract_border_xz_chunks).then_some(z_aabb).flatten(),
-      xy_aabb: (has_x_sibling && has_y_sibling && settings.extract_border_xy_chunks).then(|| aabb.sibling_positive_xy()).flatten(),
-      yz_aabb: (has_y_sibling && has_z_sibling && settings.extract_border_yz_chunks).then(|| aabb.sibling_positive_yz()).flatten(),
-      xz_aabb: (has_x_sibling && has_z_sibling && settings.extract_border_xz_chunks).then(|| aabb.sibling_positive_xz()).flatten(),
+      xy_aabb: (has_x_sibling && has_y_sibling && settings.extract_border_xy_chunks).then(|| aabb.neighbor_across_roots(IVec3::new(1, 1, 0))),
+      yz_aabb: (has_y_sibling && has_z_sibling && settings.extract_border_yz_chunks).then(|| aabb.neighbor_across_roots(IVec3::new(0, 1, 1))),
+      xz_aabb: (has_x_sibling && has_z_sibling && settings.extract_border_xz_chunks).then(|| aabb.neighbor_across_roots(IVec3::new(1, 0, 1))),
       volume,
       _chunk_size_phantom: PhantomData::default(),
     }
@@ -286,35 +315,36 @@ impl<C: ChunkSize, V: Volume> Iterator for SurfaceNetsJobDependenciesIterator<C,
     if let Some(aabb) = self.regular_aabb.take() {
       return Some((Regular, LodJob::new_sample(aabb, self.volume.clone())));
     }
+    let sample = |(root, aabb)| LodJob::new_sample_in_root(root, aabb, self.volume.clone());
     if let Some(aabb) = self.x_aabb.take() {
-      return Some((X, LodJob::new_sample(aabb, self.volume.clone())));
+      return Some((X, sample(aabb)));
     }
     if let Some(aabb) = self.x_front_aabb.take() {
-      return Some((XFront, LodJob::new_sample(aabb, self.volume.clone())));
+      return Some((XFront, sample(aabb)));
     }
     if let Some(aabb) = self.x_front_y_aabb.take() {
-      return Some((XFrontY, LodJob::new_sample(aabb, self.volume.clone())));
+      return Some((XFrontY, sample(aabb)));
     }
     if let Some(aabb) = self.x_back_aabb.take() {
-      return Some((XBack, LodJob::new_sample(aabb, self.volume.clone())));
+      return Some((XBack, sample(aabb)));
     }
     if let Some(aabb) = self.x_back_y_aabb.take() {
-      return Some((XBackY, LodJob::new_sample(aabb, self.volume.clone())));
+      return Some((XBackY, sample(aabb)));
     }
     if let Some(aabb) = self.y_aabb.take() {
-      return Some((Y, LodJob::new_sample(aabb, self.volume.clone())));
+      return Some((Y, sample(aabb)));
     }
     if let Some(aabb) = self.z_aabb.take() {
-      return Some((Z, LodJob::new_sample(aabb, self.volume.clone())));
+      return Some((Z, sample(aabb)));
     }
     if let Some(aabb) = self.xy_aabb.take() {
-      return Some((XY, LodJob::new_sample(aabb, self.volume.clone())));
+      return Some((XY, sample(aabb)));
     }
     if let Some(aabb) = self.yz_aabb.take() {
-      return Some((YZ, LodJob::new_sample(aabb, self.volume.clone())));
+      return Some((YZ, sample(aabb)));
     }
     if let Some(aabb) = self.xz_aabb.take() {
-      return Some((XZ, LodJob::new_sample(aabb, self.volume.clone())));
+      return Some((XZ, sample(aabb)));
     }
     None
   }
@@ -366,6 +396,19 @@ impl SurfaceNetsLodChunkMesh {
     Self::default()
   }
 
+  #[inline]
+  pub fn chunk_meshes_mut(&mut self) -> [&mut ChunkMesh; 7] {
+    [
+      &mut self.regular,
+      &mut self.border_x_chunk,
+      &mut self.border_y_chunk,
+      &mut self.border_z_chunk,
+      &mut self.border_xy_chunk,
+      &mut self.border_yz_chunk,
+      &mut self.border_xz_chunk,
+    ]
+  }
+
   #[inline]
   pub fn with_chunk_vertices(
     regular: ChunkMesh,
@@ -410,5 +453,21 @@ impl LodChunkMesh for SurfaceNetsLodChunkMesh {
     self.border_yz_chunk.clear();
     self.border_xz_chunk.clear();
   }
+
+  #[inline]
+  fn heap_size_in_bytes(&self) -> usize {
+    self.regular.heap_size_in_bytes()
+      + self.border_x_chunk.heap_size_in_bytes()
+      + self.border_y_chunk.heap_size_in_bytes()
+      + self.border_z_chunk.heap_size_in_bytes()
+      + self.border_xy_chunk.heap_size_in_bytes()
+      + self.border_yz_chunk.heap_size_in_bytes()
+      + self.border_xz_chunk.heap_size_in_bytes()
+  }
+
+  #[inline]
+  fn for_each_chunk_mesh_mut(&mut self, f: impl FnMut(&mut ChunkMesh)) {
+    self.chunk_meshes_mut().into_iter().for_each(f);
+  }
 }
 
@@ -1,6 +1,10 @@
 use std::marker::PhantomData;
 
+use job_queue::CancellationToken;
+
 use crate::chunk::mesh::{ChunkMesh, Vertex};
+use crate::chunk::morph::Geomorph;
+use crate::chunk::occlusion::AmbientOcclusionSettings;
 use crate::chunk::sample::MaybeCompressedChunkSampleArray;
 use crate::chunk::size::ChunkSize;
 use crate::lod::aabb::{Aabb, AabbWithSize};
@@ -25,6 +29,8 @@ pub struct TransvoxelExtractorSettings {
   pub extract_transition_hi_y_chunks: bool,
   pub extract_transition_lo_z_chunks: bool,
   pub extract_transition_hi_z_chunks: bool,
+  #[cfg_attr(feature = "serde", serde(default))]
+  pub ambient_occlusion: AmbientOcclusionSettings,
 }
 impl Default for TransvoxelExtractorSettings {
   #[inline]
@@ -37,6 +43,7 @@ impl Default for TransvoxelExtractorSettings {
       extract_transition_hi_y_chunks: false,
       extract_transition_lo_z_chunks: false,
       extract_transition_hi_z_chunks: false,
+      ambient_occlusion: Default::default(),
     }
   }
 }
@@ -64,16 +71,22 @@ impl<C: ChunkSize> LodExtractor<C> for TransvoxelExtractor<C> {
     volume: V,
     empty_lod_chunk_mesh: Self::Chunk,
   ) -> (Self::JobInput, Self::DependenciesIterator<V>) {
-    let input = TransvoxelJobInput { aabb, empty_lod_chunk_mesh };
+    let input = self.create_job_input(aabb, empty_lod_chunk_mesh);
     let dependencies = TransvoxelJobDependenciesIterator::new(aabb.inner, volume);
     (input, dependencies)
   }
 
+  #[inline]
+  fn create_job_input(&self, aabb: AabbWithSize, empty_lod_chunk_mesh: Self::Chunk) -> Self::JobInput {
+    TransvoxelJobInput { aabb, empty_lod_chunk_mesh }
+  }
+
   #[inline]
   fn run_job(
     &self,
     input: Self::JobInput,
     dependency_outputs: &[(Self::DependencyKey, LodJobOutput<MaybeCompressedChunkSampleArray<C>, Self::Chunk>)],
+    cancellation_token: &CancellationToken,
   ) -> Self::Chunk {
     if let (_, LodJobOutput::Sample(chunk_samples)) = &dependency_outputs[0] {
       let TransvoxelJobInput { aabb, empty_lod_chunk_mesh: mut chunk } = input;
@@ -84,34 +97,42 @@ impl<C: ChunkSize> LodExtractor<C> for TransvoxelExtractor<C> {
       if self.settings.extract_regular_chunks {
         self.marching_cubes.extract_chunk(lores_min, lores_step, &chunk_samples, &mut chunk.regular);
       }
+      if cancellation_token.is_cancelled() { return chunk; }
       if lores_step != 1 { // At max LOD level, no need to create transition cells.
         let volume = Sphere::new(SphereSettings::default()); // HACK: create volume here until we port this to use dependencies.
-        let hires_step = lores_step / 2;
         if self.settings.extract_transition_lo_x_chunks && lores_min.x > 0 {
-          self.extract_transvoxel_chunk(aabb, TransitionSide::LoX, &volume, hires_step, lores_step, &mut chunk.transition_lo_x_chunk);
+          self.extract_transvoxel_chunk(aabb, TransitionSide::LoX, &volume, lores_step, &mut chunk.transition_lo_x_chunk, cancellation_token);
         }
         if self.settings.extract_transition_hi_x_chunks && lores_max.x < root_size {
-          self.extract_transvoxel_chunk(aabb, TransitionSide::HiX, &volume, hires_step, lores_step, &mut chunk.transition_hi_x_chunk);
+          self.extract_transvoxel_chunk(aabb, TransitionSide::HiX, &volume, lores_step, &mut chunk.transition_hi_x_chunk, cancellation_token);
         }
         if self.settings.extract_transition_lo_y_chunks && lores_min.y > 0 {
-          self.extract_transvoxel_chunk(aabb, TransitionSide::LoY, &volume, hires_step, lores_step, &mut chunk.transition_lo_y_chunk);
+          self.extract_transvoxel_chunk(aabb, TransitionSide::LoY, &volume, lores_step, &mut chunk.transition_lo_y_chunk, cancellation_token);
         }
         if self.settings.extract_transition_hi_y_chunks && lores_max.y < root_size {
-          self.extract_transvoxel_chunk(aabb, TransitionSide::HiY, &volume, hires_step, lores_step, &mut chunk.transition_hi_y_chunk);
+          self.extract_transvoxel_chunk(aabb, TransitionSide::HiY, &volume, lores_step, &mut chunk.transition_hi_y_chunk, cancellation_token);
         }
         if self.settings.extract_transition_lo_z_chunks && lores_min.z > 0 {
-          self.extract_transvoxel_chunk(aabb, TransitionSide::LoZ, &volume, hires_step, lores_step, &mut chunk.transition_lo_z_chunk);
+          self.extract_transvoxel_chunk(aabb, TransitionSide::LoZ, &volume, lores_step, &mut chunk.transition_lo_z_chunk, cancellation_token);
         }
         if self.settings.extract_transition_hi_z_chunks && lores_max.z < root_size {
-          self.extract_transvoxel_chunk(aabb, TransitionSide::HiZ, &volume, hires_step, lores_step, &mut chunk.transition_hi_z_chunk);
+          self.extract_transvoxel_chunk(aabb, TransitionSide::HiZ, &volume, lores_step, &mut chunk.transition_hi_z_chunk, cancellation_token);
         }
       }
+      if cancellation_token.is_cancelled() { return chunk; }
+      let geomorph = Geomorph::new();
+      for chunk_mesh in chunk.chunk_meshes_mut() {
+        geomorph.apply(lores_min, lores_step, chunk_samples.as_ref(), chunk_mesh);
+      }
       chunk
     } else {
       panic!("Missing sample dependency output");
     }
   }
 
+  #[inline]
+  fn ambient_occlusion_settings(&self) -> AmbientOcclusionSettings { self.settings.ambient_occlusion }
+
   #[inline]
   fn update_render_data(&self, chunk: &Self::Chunk, vertices: &mut Vec<Vertex>, indices: &mut Vec<u16>, draws: &mut Vec<LodDraw>) {
     if self.settings.extract_regular_chunks {
@@ -150,16 +171,17 @@ impl<C: ChunkSize> TransvoxelExtractor<C> {
     aabb: AabbWithSize,
     side: TransitionSide,
     volume: &V,
-    hires_step: u32,
     lores_step: u32,
     chunk_vertices: &mut ChunkMesh,
+    cancellation_token: &CancellationToken,
   ) {
+    let hires_step = lores_step / 2;
     let hires_chunk_mins = side.subdivided_face_of_side_minimums(aabb);
     let hires_chunk_samples = [
-      volume.sample_chunk(hires_chunk_mins[0], hires_step),
-      volume.sample_chunk(hires_chunk_mins[1], hires_step),
-      volume.sample_chunk(hires_chunk_mins[2], hires_step),
-      volume.sample_chunk(hires_chunk_mins[3], hires_step),
+      volume.sample_chunk(hires_chunk_mins[0], hires_step, cancellation_token),
+      volume.sample_chunk(hires_chunk_mins[1], hires_step, cancellation_token),
+      volume.sample_chunk(hires_chunk_mins[2], hires_step, cancellation_token),
+      volume.sample_chunk(hires_chunk_mins[3], hires_step, cancellation_token),
     ];
     self.transvoxel.extract_chunk(
       side,
@@ -237,6 +259,19 @@ impl TransvoxelLodChunkMesh {
     Self::default()
   }
 
+  #[inline]
+  pub fn chunk_meshes_mut(&mut self) -> [&mut ChunkMesh; 7] {
+    [
+      &mut self.regular,
+      &mut self.transition_lo_x_chunk,
+      &mut self.transition_hi_x_chunk,
+      &mut self.transition_lo_y_chunk,
+      &mut self.transition_hi_y_chunk,
+      &mut self.transition_lo_z_chunk,
+      &mut self.transition_hi_z_chunk,
+    ]
+  }
+
   #[inline]
   pub fn with_chunk_vertices(
     regular: ChunkMesh,
@@ -281,4 +316,20 @@ impl LodChunkMesh for TransvoxelLodChunkMesh {
     self.transition_lo_z_chunk.clear();
     self.transition_hi_z_chunk.clear();
   }
+
+  #[inline]
+  fn heap_size_in_bytes(&self) -> usize {
+    self.regular.heap_size_in_bytes() +
+      self.transition_lo_x_chunk.heap_size_in_bytes() +
+      self.transition_hi_x_chunk.heap_size_in_bytes() +
+      self.transition_lo_y_chunk.heap_size_in_bytes() +
+      self.transition_hi_y_chunk.heap_size_in_bytes() +
+      self.transition_lo_z_chunk.heap_size_in_bytes() +
+      self.transition_hi_z_chunk.heap_size_in_bytes()
+  }
+
+  #[inline]
+  fn for_each_chunk_mesh_mut(&mut self, f: impl FnMut(&mut ChunkMesh)) {
+    self.chunk_meshes_mut().into_iter().for_each(f);
+  }
 }
@@ -16,7 +16,7 @@ use ultraviolet::{UVec3, Vec3};
 
 use crate::chunk::array::{Array, Slice};
 use crate::chunk::mesh::{ChunkMesh, Vertex};
-use crate::chunk::sample::{ChunkSampleArray, ChunkSamples, MaybeCompressedChunkSampleArray, MaybeCompressedChunkSamples};
+use crate::chunk::sample::{ChunkSamples, MaybeCompressedChunkSampleArray, MaybeCompressedChunkSamples};
 use crate::chunk::size::ChunkSize;
 use crate::marching_cubes::tables::RegularVertexData;
 
@@ -39,25 +39,40 @@ impl<C: ChunkSize> MarchingCubes<C> {
     chunk_samples: &MaybeCompressedChunkSampleArray<C>,
     chunk_mesh: &mut ChunkMesh,
   ) {
-    if let MaybeCompressedChunkSamples::Mixed(chunk_sample_array) = chunk_samples {
-      let mut shared_indices = C::MarchingCubesSharedIndicesArray::new(u16::MAX); // OPTO: reduce size and management of this array to the number of shared indices that we need to keep in memory?
-      for w in 0..C::CELLS_IN_CHUNK_ROW {
-        for v in 0..C::CELLS_IN_CHUNK_ROW {
-          for u in 0..C::CELLS_IN_CHUNK_ROW {
-            let cell = RegularCell::new(u, v, w);
-            Self::extract_cell(cell, min, step, chunk_sample_array, &mut shared_indices, chunk_mesh);
-          }
+    use MaybeCompressedChunkSamples::*;
+    match chunk_samples {
+      Quantized8(chunk_samples) => self.extract_chunk_from_samples(min, step, chunk_samples, chunk_mesh),
+      Quantized16(chunk_samples) => self.extract_chunk_from_samples(min, step, chunk_samples, chunk_mesh),
+      Mixed(chunk_sample_array) => self.extract_chunk_from_samples(min, step, chunk_sample_array.as_ref(), chunk_mesh),
+      _ => {} // All samples have the same sign: no surface to extract.
+    }
+  }
+
+  #[profiling::function]
+  pub fn extract_chunk_from_samples<CS: ChunkSamples<C>>(
+    &self,
+    min: UVec3,
+    step: u32,
+    chunk_samples: &CS,
+    chunk_mesh: &mut ChunkMesh,
+  ) {
+    let mut shared_indices = C::MarchingCubesSharedIndicesArray::new(u16::MAX); // OPTO: reduce size and management of this array to the number of shared indices that we need to keep in memory?
+    for w in 0..C::CELLS_IN_CHUNK_ROW {
+      for v in 0..C::CELLS_IN_CHUNK_ROW {
+        for u in 0..C::CELLS_IN_CHUNK_ROW {
+          let cell = RegularCell::new(u, v, w);
+          Self::extract_cell(cell, min, step, chunk_samples, &mut shared_indices, chunk_mesh);
         }
       }
     }
   }
 
   #[inline]
-  fn extract_cell(
+  fn extract_cell<CS: ChunkSamples<C>>(
     cell: RegularCell,
     min: UVec3,
     step: u32,
-    chunk_sample_array: &ChunkSampleArray<C>,
+    chunk_sample_array: &CS,
     shared_indices: &mut C::MarchingCubesSharedIndicesArray<u16>,
     chunk_mesh: &mut ChunkMesh,
   ) {
@@ -76,7 +91,7 @@ impl<C: ChunkSize> MarchingCubes<C> {
     let global_voxels = Self::global_coordinates(min, step, &local_coordinates);
     let mut cell_vertices_indices = [0; 12];
     for (i, vd) in vertices_data[0..vertex_count].iter().enumerate() {
-      let index = Self::create_or_reuse_vertex(vd, cell, &global_voxels, &values, shared_indices, chunk_mesh);
+      let index = Self::create_or_reuse_vertex(vd, cell, chunk_sample_array, &local_coordinates, &global_voxels, &values, shared_indices, chunk_mesh);
       cell_vertices_indices[i] = index;
     }
     for t in 0..triangle_count {
@@ -118,7 +133,7 @@ impl<C: ChunkSize> MarchingCubes<C> {
   }
 
   #[inline]
-  pub fn sample(chunk_sample_array: &ChunkSampleArray<C>, local_coordinates: &[UVec3; 8]) -> [f32; 8] {
+  pub fn sample<CS: ChunkSamples<C>>(chunk_sample_array: &CS, local_coordinates: &[UVec3; 8]) -> [f32; 8] {
     [
       chunk_sample_array.sample(local_coordinates[0]),
       chunk_sample_array.sample(local_coordinates[1]),
@@ -159,9 +174,11 @@ impl<C: ChunkSize> MarchingCubes<C> {
   }
 
   #[inline]
-  fn create_or_reuse_vertex(
+  fn create_or_reuse_vertex<CS: ChunkSamples<C>>(
     vertex_data: &RegularVertexData,
     cell: RegularCell,
+    chunk_sample_array: &CS,
+    local_coordinates: &[UVec3; 8],
     global_voxels: &[UVec3; 8],
     values: &[f32; 8],
     shared_indices: &mut C::MarchingCubesSharedIndicesArray<u16>,
@@ -169,7 +186,7 @@ impl<C: ChunkSize> MarchingCubes<C> {
   ) -> u16 {
     if vertex_data.new_vertex() {
       // Create a new vertex and index, and share the index.
-      let index = Self::create_vertex(vertex_data, global_voxels, values, chunk_mesh);
+      let index = Self::create_vertex(vertex_data, chunk_sample_array, local_coordinates, global_voxels, values, chunk_mesh);
       let shared_indices_index = Self::shared_index(cell, vertex_data.vertex_index());
       debug_assert!(shared_indices.contains(shared_indices_index), "Tried to write out of bounds shared index, at index: {}, position: {:?}", shared_indices_index, cell);
       debug_assert!(shared_indices[shared_indices_index] == u16::MAX, "Tried to write already set shared index, at index: {}, position: {:?}", shared_indices_index, cell);
@@ -196,15 +213,19 @@ impl<C: ChunkSize> MarchingCubes<C> {
         index
       } else {
         // Create a new vertex and index, but this vertex will never be shared, as it occurs on the minimal boundary.
-        let index = Self::create_vertex(vertex_data, global_voxels, values, chunk_mesh);
+        let index = Self::create_vertex(vertex_data, chunk_sample_array, local_coordinates, global_voxels, values, chunk_mesh);
         index
       }
     }
   }
 
+  /// Creates a vertex on the edge of `vertex_data`, with the material of the voxel on the solid (positive) side of the
+  /// edge.
   #[inline]
-  pub fn create_vertex(
+  pub fn create_vertex<CS: ChunkSamples<C>>(
     vertex_data: &RegularVertexData,
+    chunk_sample_array: &CS,
+    local_coordinates: &[UVec3; 8],
     global_voxels: &[UVec3; 8],
     values: &[f32; 8],
     chunk_mesh: &mut ChunkMesh,
@@ -217,7 +238,9 @@ impl<C: ChunkSize> MarchingCubes<C> {
     let pos_high = global_voxels[voxel_b_index as usize];
     let value_high = values[voxel_b_index as usize];
     let position = Self::vertex_position(pos_low, value_low, pos_high, value_high);
-    chunk_mesh.push_vertex(Vertex::new(position))
+    let solid_voxel_index = if value_low.is_sign_positive() { voxel_a_index } else { voxel_b_index };
+    let material = chunk_sample_array.material(local_coordinates[solid_voxel_index as usize]);
+    chunk_mesh.push_vertex(Vertex::new(position, material))
   }
 
   #[inline]
@@ -2,6 +2,8 @@
 #extension GL_OES_standard_derivatives : enable
 
 layout(location = 0) in vec3 inEyeRelativePosition;
+layout(location = 1) flat in uint inMaterial;
+layout(location = 2) in float inOcclusion;
 
 layout(location = 0) out vec4 outColor;
 
@@ -16,8 +18,13 @@ layout(std140, set = 0, binding = 1) uniform LightUniform {
   vec3 direction;
 } light;
 
+const uint MATERIAL_PALETTE_SIZE = 16u;
+layout(std140, set = 0, binding = 3) uniform MaterialPaletteUniform {
+  vec4 colors[MATERIAL_PALETTE_SIZE];
+} materialPalette;
+
 void main() {
-  vec3 objectColor = vec3(1.0, 1.0, 1.0);
+  vec3 objectColor = materialPalette.colors[inMaterial % MATERIAL_PALETTE_SIZE].rgb;
   vec3 lightDirection = normalize(light.direction);
 
   vec3 ambientColor = light.color * light.ambient;
@@ -33,6 +40,7 @@ void main() {
   float specular = pow(max(dot(normal, halfDirection), 0.0), 32.0);
   vec3 specularColor = specular * light.color;
 
-  vec3 color = (ambientColor + diffuseColor + specularColor) * objectColor;
+  float ambientVisibility = 1.0 - inOcclusion;
+  vec3 color = ((ambientColor + diffuseColor) * ambientVisibility + specularColor) * objectColor;
   outColor = vec4(color, 1.0);
 }
@@ -5,15 +5,19 @@ use gfx::{Gfx, GfxFrame, include_spirv_shader};
 use gfx::bind_group::{CombinedBindGroup, CombinedBindGroupBuilder};
 use gfx::buffer::{BufferBuilder, GfxBuffer};
 use gfx::growable_buffer::{GrowableBuffer, GrowableBufferBuilder};
+use gfx::texture_def::ArrayTextureDef;
 
 use crate::chunk::mesh::{ChunkMesh, Vertex};
 use crate::lod::render::LodRenderData;
-use crate::uniform::{CameraUniform, LightUniform, ModelUniform};
+use crate::uniform::{CameraUniform, LightUniform, MaterialPaletteUniform, ModelUniform, TriplanarUniform};
 
 pub struct VoxelRenderer {
   camera_uniform_buffer: GfxBuffer,
   light_uniform_buffer: GfxBuffer,
   model_uniform_buffer: GfxBuffer,
+  material_palette_uniform_buffer: GfxBuffer,
+  triplanar_uniform_buffer: Option<GfxBuffer>,
+  array_texture: Option<ArrayTextureDef>,
   uniform_bind_group: CombinedBindGroup,
   render_pipeline: RenderPipeline,
   staging_belt: StagingBelt,
@@ -22,11 +26,42 @@ pub struct VoxelRenderer {
 }
 
 impl VoxelRenderer {
+  /// Creates a renderer that colors voxels with the material palette.
   pub fn new(
     gfx: &Gfx,
     camera_uniform: CameraUniform,
     light_uniform: LightUniform,
     model_uniform: ModelUniform,
+    material_palette_uniform: MaterialPaletteUniform,
+    cull_mode: Option<Face>,
+    staging_belt: StagingBelt,
+  ) -> Self {
+    Self::new_internal(gfx, camera_uniform, light_uniform, model_uniform, material_palette_uniform, None, cull_mode, staging_belt)
+  }
+
+  /// Creates a renderer that textures voxels by triplanar mapping `array_texture`, using the texture layer of each
+  /// material from `triplanar_uniform`. Texture colors are multiplied by the material palette color.
+  pub fn new_textured(
+    gfx: &Gfx,
+    camera_uniform: CameraUniform,
+    light_uniform: LightUniform,
+    model_uniform: ModelUniform,
+    material_palette_uniform: MaterialPaletteUniform,
+    triplanar_uniform: TriplanarUniform,
+    array_texture: ArrayTextureDef,
+    cull_mode: Option<Face>,
+    staging_belt: StagingBelt,
+  ) -> Self {
+    Self::new_internal(gfx, camera_uniform, light_uniform, model_uniform, material_palette_uniform, Some((triplanar_uniform, array_texture)), cull_mode, staging_belt)
+  }
+
+  fn new_internal(
+    gfx: &Gfx,
+    camera_uniform: CameraUniform,
+    light_uniform: LightUniform,
+    model_uniform: ModelUniform,
+    material_palette_uniform: MaterialPaletteUniform,
+    textured: Option<(TriplanarUniform, ArrayTextureDef)>,
     cull_mode: Option<Face>,
     staging_belt: StagingBelt,
   ) -> Self {
@@ -48,19 +83,46 @@ impl VoxelRenderer {
       .build_with_data(&gfx.device, &[model_uniform]);
     let model_uniform_binding = model_uniform_buffer.binding(2, ShaderStages::VERTEX);
 
-    let vertex_shader_module = gfx.device.create_shader_module(include_spirv_shader!("render/vert"));
-    let fragment_shader_module = gfx.device.create_shader_module(include_spirv_shader!("render/frag"));
+    let material_palette_uniform_buffer = BufferBuilder::default()
+      .uniform_usage()
+      .label("Material palette uniform buffer")
+      .build_with_data(&gfx.device, &[material_palette_uniform]);
+    let material_palette_uniform_binding = material_palette_uniform_buffer.binding(3, ShaderStages::FRAGMENT);
+
+    let (triplanar_uniform, array_texture) = textured.unzip();
+    let triplanar_uniform_buffer = triplanar_uniform.map(|triplanar_uniform| BufferBuilder::default()
+      .uniform_usage()
+      .label("Triplanar uniform buffer")
+      .build_with_data(&gfx.device, &[triplanar_uniform]));
+    let triplanar_uniform_binding = triplanar_uniform_buffer.as_ref().map(|buffer| buffer.binding(4, ShaderStages::FRAGMENT));
 
+    let vertex_shader_module = gfx.device.create_shader_module(include_spirv_shader!("render/vert"));
+    let fragment_shader_module = if array_texture.is_some() {
+      gfx.device.create_shader_module(include_spirv_shader!("render/textured_frag"))
+    } else {
+      gfx.device.create_shader_module(include_spirv_shader!("render/frag"))
+    };
+
+    let mut uniform_layout_entries = vec![camera_uniform_binding.layout, light_uniform_binding.layout, model_uniform_binding.layout, material_palette_uniform_binding.layout];
+    let mut uniform_entries = vec![camera_uniform_binding.entry, light_uniform_binding.entry, model_uniform_binding.entry, material_palette_uniform_binding.entry];
+    if let Some(triplanar_uniform_binding) = triplanar_uniform_binding {
+      uniform_layout_entries.push(triplanar_uniform_binding.layout);
+      uniform_entries.push(triplanar_uniform_binding.entry);
+    }
     let uniform_bind_group = CombinedBindGroupBuilder::new()
       .layout_label("Voxel renderer uniform bind group layout")
       .label("Voxel renderer uniform bind group")
-      .layout_entries(&[camera_uniform_binding.layout, light_uniform_binding.layout, model_uniform_binding.layout])
-      .entries(&[camera_uniform_binding.entry, light_uniform_binding.entry, model_uniform_binding.entry])
+      .layout_entries(&uniform_layout_entries)
+      .entries(&uniform_entries)
       .build(&gfx.device);
 
+    let mut bind_group_layouts = vec![&uniform_bind_group.layout];
+    if let Some(array_texture) = &array_texture {
+      bind_group_layouts.push(&array_texture.bind_group.layout);
+    }
     let (_, render_pipeline) = gfx.render_pipeline_builder()
       .layout_label("Voxel renderer pipeline layout")
-      .bind_group_layouts(&[&uniform_bind_group.layout])
+      .bind_group_layouts(&bind_group_layouts)
       .label("Voxel renderer render pipeline")
       .vertex_module(&vertex_shader_module)
       .vertex_buffer_layouts(&[Vertex::buffer_layout()])
@@ -81,6 +143,9 @@ impl VoxelRenderer {
       camera_uniform_buffer,
       light_uniform_buffer,
       model_uniform_buffer,
+      material_palette_uniform_buffer,
+      triplanar_uniform_buffer,
+      array_texture,
       uniform_bind_group,
       render_pipeline,
       staging_belt,
@@ -101,6 +166,20 @@ impl VoxelRenderer {
     self.model_uniform_buffer.write_all_data(queue, &[model_uniform]);
   }
 
+  pub fn update_material_palette_uniform(&mut self, queue: &Queue, material_palette_uniform: MaterialPaletteUniform) {
+    self.material_palette_uniform_buffer.write_all_data(queue, &[material_palette_uniform]);
+  }
+
+  /// Updates the triplanar uniform. Does nothing if this renderer is not textured.
+  pub fn update_triplanar_uniform(&mut self, queue: &Queue, triplanar_uniform: TriplanarUniform) {
+    if let Some(triplanar_uniform_buffer) = &self.triplanar_uniform_buffer {
+      triplanar_uniform_buffer.write_all_data(queue, &[triplanar_uniform]);
+    }
+  }
+
+  #[inline]
+  pub fn is_textured(&self) -> bool { self.array_texture.is_some() }
+
   #[profiling::function]
   pub fn render_lod_mesh(
     &mut self,
@@ -116,6 +195,9 @@ impl VoxelRenderer {
     pass.push_debug_group("Render LOD mesh");
     pass.set_pipeline(&self.render_pipeline);
     pass.set_bind_group(0, &self.uniform_bind_group.entry, &[]);
+    if let Some(array_texture) = &self.array_texture {
+      pass.set_bind_group(1, &array_texture.bind_group.entry, &[]);
+    }
     pass.set_index_buffer(index_buffer.slice(..), IndexFormat::Uint16);
     for draw in &lod_mesh.draws {
       pass.set_vertex_buffer(0, vertex_buffer.slice_data::<Vertex>(draw.base_vertex..));
@@ -140,6 +222,9 @@ impl VoxelRenderer {
     pass.push_debug_group("Render chunk vertices");
     pass.set_pipeline(&self.render_pipeline);
     pass.set_bind_group(0, &self.uniform_bind_group.entry, &[]);
+    if let Some(array_texture) = &self.array_texture {
+      pass.set_bind_group(1, &array_texture.bind_group.entry, &[]);
+    }
     pass.set_index_buffer(index_buffer.slice(..), IndexFormat::Uint16);
     pass.set_vertex_buffer(0, vertex_buffer.slice(..));
     pass.draw_indexed(0..chunk_vertices.indices().len() as u32, 0, 0..1);
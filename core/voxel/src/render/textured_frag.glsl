@@ -0,0 +1,69 @@
+#version 450
+#extension GL_OES_standard_derivatives : enable
+
+layout(location = 0) in vec3 inEyeRelativePosition;
+layout(location = 1) flat in uint inMaterial;
+layout(location = 2) in float inOcclusion;
+
+layout(location = 0) out vec4 outColor;
+
+layout(std140, set = 0, binding = 0) uniform CameraUniform {
+  vec4 position;
+  mat4 viewProjection;
+} camera;
+
+layout(std140, set = 0, binding = 1) uniform LightUniform {
+  vec3 color;
+  float ambient;
+  vec3 direction;
+} light;
+
+const uint MATERIAL_PALETTE_SIZE = 16u;
+layout(std140, set = 0, binding = 3) uniform MaterialPaletteUniform {
+  vec4 colors[MATERIAL_PALETTE_SIZE];
+} materialPalette;
+
+layout(std140, set = 0, binding = 4) uniform TriplanarUniform {
+  float textureScale;
+  float blendSharpness;
+  uvec4 materialLayers[MATERIAL_PALETTE_SIZE / 4u];
+} triplanar;
+
+layout(set = 1, binding = 0) uniform texture2DArray textures;
+layout(set = 1, binding = 1) uniform sampler textureSampler;
+
+vec3 triplanarColor(vec3 worldPosition, vec3 normal, float layer) {
+  vec3 weights = pow(abs(normal), vec3(triplanar.blendSharpness));
+  weights /= weights.x + weights.y + weights.z;
+  vec3 uv = worldPosition * triplanar.textureScale;
+  vec3 colorX = texture(sampler2DArray(textures, textureSampler), vec3(uv.zy, layer)).rgb;
+  vec3 colorY = texture(sampler2DArray(textures, textureSampler), vec3(uv.xz, layer)).rgb;
+  vec3 colorZ = texture(sampler2DArray(textures, textureSampler), vec3(uv.xy, layer)).rgb;
+  return colorX * weights.x + colorY * weights.y + colorZ * weights.z;
+}
+
+void main() {
+  uint material = inMaterial % MATERIAL_PALETTE_SIZE;
+  vec3 lightDirection = normalize(light.direction);
+
+  vec3 ambientColor = light.color * light.ambient;
+
+  // From: https://stackoverflow.com/a/66206648 and https://www.enkisoftware.com/devlogpost-20150131-1-Normal-generation-in-the-pixel-shader
+  vec3 normal = normalize(cross(dFdx(inEyeRelativePosition), dFdy(inEyeRelativePosition)));
+
+  vec3 worldPosition = camera.position.xyz - inEyeRelativePosition;
+  float layer = float(triplanar.materialLayers[material / 4u][material % 4u]);
+  vec3 objectColor = materialPalette.colors[material].rgb * triplanarColor(worldPosition, normal, layer);
+
+  float diffuse = max(dot(normal, lightDirection), 0.0);
+  vec3 diffuseColor = light.color * diffuse;
+
+  vec3 viewDirection = normalize(inEyeRelativePosition);
+  vec3 halfDirection = normalize(viewDirection + lightDirection);
+  float specular = pow(max(dot(normal, halfDirection), 0.0), 32.0);
+  vec3 specularColor = specular * light.color;
+
+  float ambientVisibility = 1.0 - inOcclusion;
+  vec3 color = ((ambientColor + diffuseColor) * ambientVisibility + specularColor) * objectColor;
+  outColor = vec4(color, 1.0);
+}
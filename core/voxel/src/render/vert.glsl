@@ -1,8 +1,14 @@
 #version 450
 
 layout(location = 0) in vec3 inPosition;
+layout(location = 1) in uint inMaterial;
+layout(location = 2) in float inOcclusion;
+layout(location = 3) in vec3 inMorphPosition;
+layout(location = 4) in float inLodSize;
 
 layout(location = 0) out vec3 outEyeRelativePosition;
+layout(location = 1) flat out uint outMaterial;
+layout(location = 2) out float outOcclusion;
 
 layout(std140, set = 0, binding = 0) uniform CameraUniform {
   vec4 position;
@@ -11,10 +17,24 @@ layout(std140, set = 0, binding = 0) uniform CameraUniform {
 
 layout(std140, set = 0, binding = 2) uniform ModelUniform {
   mat4 model;
+  float lodDistancePerSize;
+  float geomorphRange;
 } modelUniform;
 
 void main() {
-  vec4 position = modelUniform.model * vec4(inPosition, 1.0);
+  vec3 localPosition = inPosition;
+  if (modelUniform.lodDistancePerSize > 0.0 && modelUniform.geomorphRange > 0.0 && inLodSize > 0.0) {
+    // Morph towards the position at the parent LOD, reaching it at the distance where the parent (which is twice the
+    // size) replaces this LOD.
+    float distance = length(camera.position.xyz - vec3(modelUniform.model * vec4(inPosition, 1.0)));
+    float morphEnd = modelUniform.lodDistancePerSize * 2.0 * inLodSize;
+    float morphStart = morphEnd * (1.0 - modelUniform.geomorphRange);
+    float morph = clamp((distance - morphStart) / (morphEnd - morphStart), 0.0, 1.0);
+    localPosition = mix(inPosition, inMorphPosition, morph);
+  }
+  vec4 position = modelUniform.model * vec4(localPosition, 1.0);
   gl_Position = camera.viewProjection * position;
   outEyeRelativePosition = camera.position.xyz - vec3(position);
+  outMaterial = inMaterial;
+  outOcclusion = inOcclusion;
 }
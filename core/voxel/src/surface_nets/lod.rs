@@ -21,6 +21,19 @@ type ShapeXZ<C> = <C as ChunkSize>::CellRowQuadXZShape;
 type RowVertexIndexArray<C> = <C as ChunkSize>::CellRowQuadArray<u16>;
 type RowCaseArray<C> = <C as ChunkSize>::CellRowQuadArray<Case>;
 
+/// Runs `$body` with `$samples` bound to the inner samples of mixed (full precision or quantized) `$chunk_samples`,
+/// matching on the kind of samples once instead of for each sample.
+macro_rules! with_mixed_samples {
+  ($chunk_samples:expr, |$samples:ident| $body:expr) => {
+    match $chunk_samples {
+      MaybeCompressedChunkSamples::Quantized8($samples) => $body,
+      MaybeCompressedChunkSamples::Quantized16($samples) => $body,
+      MaybeCompressedChunkSamples::Mixed($samples) => $body,
+      _ => {} // All samples have the same sign: no vertices to extract.
+    }
+  };
+}
+
 #[repr(transparent)]
 #[derive(Default, Copy, Clone)]
 pub struct SurfaceNetsLod<C: ChunkSize> {
@@ -45,11 +58,9 @@ impl<C: ChunkSize> SurfaceNetsLod<C> {
   ) {
     let mut cell_index_to_vertex_index = DeckVertexIndexArray::<C>::new(u16::MAX);
     let mut cell_index_to_case = DeckCaseArray::<C>::new(Case::default());
-    if let MaybeCompressedChunkSamples::Mixed(chunk_sample_array) = chunk_samples {
-      Self::extract_global_positions_border_x(0, step, min, chunk_sample_array, &mut cell_index_to_vertex_index, &mut cell_index_to_case, chunk_mesh);
-    }
-    if let MaybeCompressedChunkSamples::Mixed(chunk_sample_array) = chunk_samples_x {
-      Self::extract_global_positions_border_x(1, step, min_x, chunk_sample_array, &mut cell_index_to_vertex_index, &mut cell_index_to_case, chunk_mesh);
+    with_mixed_samples!(chunk_samples, |samples| Self::extract_global_positions_border_x(0, step, min, samples, &mut cell_index_to_vertex_index, &mut cell_index_to_case, chunk_mesh));
+    if chunk_samples_x.is_mixed() {
+      with_mixed_samples!(chunk_samples_x, |samples| Self::extract_global_positions_border_x(1, step, min_x, samples, &mut cell_index_to_vertex_index, &mut cell_index_to_case, chunk_mesh));
       Self::extract_quads_border_x(1, &cell_index_to_vertex_index, &cell_index_to_case, chunk_mesh);
     }
   }
@@ -73,24 +84,22 @@ impl<C: ChunkSize> SurfaceNetsLod<C> {
   ) {
     let mut cell_index_to_vertex_index = DeckVertexIndexArray::<C>::new(u16::MAX);
     let mut cell_index_to_case = DeckCaseArray::<C>::new(Case::default());
-    if let MaybeCompressedChunkSamples::Mixed(chunk_sample_array) = chunk_samples {
-      Self::extract_global_positions_border_x(0, lores_step, min, chunk_sample_array, &mut cell_index_to_vertex_index, &mut cell_index_to_case, chunk_mesh);
-    }
+    with_mixed_samples!(chunk_samples, |samples| Self::extract_global_positions_border_x(0, lores_step, min, samples, &mut cell_index_to_vertex_index, &mut cell_index_to_case, chunk_mesh));
     let mut extracted_positions = false;
-    if let MaybeCompressedChunkSamples::Mixed(chunk_sample_array) = chunk_samples_x_front {
-      Self::extract_global_positions_border_x_hires(1, C::CELLS_IN_CHUNK_ROW_DIV_TWO, C::CELLS_IN_CHUNK_ROW_DIV_TWO, hires_step, min_x_front, chunk_sample_array, &mut cell_index_to_vertex_index, &mut cell_index_to_case, chunk_mesh);
+    if chunk_samples_x_front.is_mixed() {
+      with_mixed_samples!(chunk_samples_x_front, |samples| Self::extract_global_positions_border_x_hires(1, C::CELLS_IN_CHUNK_ROW_DIV_TWO, C::CELLS_IN_CHUNK_ROW_DIV_TWO, hires_step, min_x_front, samples, &mut cell_index_to_vertex_index, &mut cell_index_to_case, chunk_mesh));
       extracted_positions |= true;
     }
-    if let MaybeCompressedChunkSamples::Mixed(chunk_sample_array) = chunk_samples_x_front_y {
-      Self::extract_global_positions_border_x_hires(1, 0, C::CELLS_IN_CHUNK_ROW_DIV_TWO, hires_step, min_x_front_y, chunk_sample_array, &mut cell_index_to_vertex_index, &mut cell_index_to_case, chunk_mesh);
+    if chunk_samples_x_front_y.is_mixed() {
+      with_mixed_samples!(chunk_samples_x_front_y, |samples| Self::extract_global_positions_border_x_hires(1, 0, C::CELLS_IN_CHUNK_ROW_DIV_TWO, hires_step, min_x_front_y, samples, &mut cell_index_to_vertex_index, &mut cell_index_to_case, chunk_mesh));
       extracted_positions |= true;
     }
-    if let MaybeCompressedChunkSamples::Mixed(chunk_sample_array) = chunk_samples_x_back {
-      Self::extract_global_positions_border_x_hires(1, C::CELLS_IN_CHUNK_ROW_DIV_TWO, 0, hires_step, min_x_back, chunk_sample_array, &mut cell_index_to_vertex_index, &mut cell_index_to_case, chunk_mesh);
+    if chunk_samples_x_back.is_mixed() {
+      with_mixed_samples!(chunk_samples_x_back, |samples| Self::extract_global_positions_border_x_hires(1, C::CELLS_IN_CHUNK_ROW_DIV_TWO, 0, hires_step, min_x_back, samples, &mut cell_index_to_vertex_index, &mut cell_index_to_case, chunk_mesh));
       extracted_positions |= true;
     }
-    if let MaybeCompressedChunkSamples::Mixed(chunk_sample_array) = chunk_samples_x_back_y {
-      Self::extract_global_positions_border_x_hires(1, 0, 0, hires_step, min_x_back_y, chunk_sample_array, &mut cell_index_to_vertex_index, &mut cell_index_to_case, chunk_mesh);
+    if chunk_samples_x_back_y.is_mixed() {
+      with_mixed_samples!(chunk_samples_x_back_y, |samples| Self::extract_global_positions_border_x_hires(1, 0, 0, hires_step, min_x_back_y, samples, &mut cell_index_to_vertex_index, &mut cell_index_to_case, chunk_mesh));
       extracted_positions |= true;
     }
     if extracted_positions {
@@ -110,11 +119,9 @@ impl<C: ChunkSize> SurfaceNetsLod<C> {
   ) {
     let mut cell_index_to_vertex_index = DeckVertexIndexArray::<C>::new(u16::MAX);
     let mut cell_index_to_case = DeckCaseArray::<C>::new(Case::default());
-    if let MaybeCompressedChunkSamples::Mixed(chunk_sample_array) = chunk_samples {
-      Self::extract_global_positions_border_y(0, step, min, chunk_sample_array, &mut cell_index_to_vertex_index, &mut cell_index_to_case, chunk_mesh);
-    }
-    if let MaybeCompressedChunkSamples::Mixed(chunk_sample_array) = chunk_samples_y {
-      Self::extract_global_positions_border_y(1, step, min_y, chunk_sample_array, &mut cell_index_to_vertex_index, &mut cell_index_to_case, chunk_mesh);
+    with_mixed_samples!(chunk_samples, |samples| Self::extract_global_positions_border_y(0, step, min, samples, &mut cell_index_to_vertex_index, &mut cell_index_to_case, chunk_mesh));
+    if chunk_samples_y.is_mixed() {
+      with_mixed_samples!(chunk_samples_y, |samples| Self::extract_global_positions_border_y(1, step, min_y, samples, &mut cell_index_to_vertex_index, &mut cell_index_to_case, chunk_mesh));
       Self::extract_quads_border_y(1, &cell_index_to_vertex_index, &cell_index_to_case, chunk_mesh);
     }
   }
@@ -131,11 +138,9 @@ impl<C: ChunkSize> SurfaceNetsLod<C> {
   ) {
     let mut cell_index_to_vertex_index = DeckVertexIndexArray::<C>::new(u16::MAX);
     let mut cell_index_to_case = DeckCaseArray::<C>::new(Case::default());
-    if let MaybeCompressedChunkSamples::Mixed(chunk_sample_array) = chunk_samples {
-      Self::extract_global_positions_border_z(0, step, min, chunk_sample_array, &mut cell_index_to_vertex_index, &mut cell_index_to_case, chunk_mesh);
-    }
-    if let MaybeCompressedChunkSamples::Mixed(chunk_sample_array) = chunk_samples_z {
-      Self::extract_global_positions_border_z(1, step, min_z, chunk_sample_array, &mut cell_index_to_vertex_index, &mut cell_index_to_case, chunk_mesh);
+    with_mixed_samples!(chunk_samples, |samples| Self::extract_global_positions_border_z(0, step, min, samples, &mut cell_index_to_vertex_index, &mut cell_index_to_case, chunk_mesh));
+    if chunk_samples_z.is_mixed() {
+      with_mixed_samples!(chunk_samples_z, |samples| Self::extract_global_positions_border_z(1, step, min_z, samples, &mut cell_index_to_vertex_index, &mut cell_index_to_case, chunk_mesh));
       Self::extract_quads_border_z(1, &cell_index_to_vertex_index, &cell_index_to_case, chunk_mesh);
     }
   }
@@ -156,17 +161,11 @@ impl<C: ChunkSize> SurfaceNetsLod<C> {
   ) {
     let mut cell_index_to_vertex_index = RowVertexIndexArray::<C>::new(u16::MAX);
     let mut cell_index_to_case = RowCaseArray::<C>::new(Case::default());
-    if let MaybeCompressedChunkSamples::Mixed(chunk_sample_array) = chunk_samples {
-      Self::extract_global_positions_border_xy(0, 0, step, min, chunk_sample_array, &mut cell_index_to_vertex_index, &mut cell_index_to_case, chunk_mesh);
-    }
-    if let MaybeCompressedChunkSamples::Mixed(chunk_sample_array) = chunk_samples_x {
-      Self::extract_global_positions_border_xy(1, 0, step, min_x, chunk_sample_array, &mut cell_index_to_vertex_index, &mut cell_index_to_case, chunk_mesh);
-    }
-    if let MaybeCompressedChunkSamples::Mixed(chunk_sample_array) = chunk_samples_y {
-      Self::extract_global_positions_border_xy(0, 1, step, min_y, chunk_sample_array, &mut cell_index_to_vertex_index, &mut cell_index_to_case, chunk_mesh);
-    }
-    if let MaybeCompressedChunkSamples::Mixed(chunk_sample_array) = chunk_samples_xy {
-      Self::extract_global_positions_border_xy(1, 1, step, min_xy, chunk_sample_array, &mut cell_index_to_vertex_index, &mut cell_index_to_case, chunk_mesh);
+    with_mixed_samples!(chunk_samples, |samples| Self::extract_global_positions_border_xy(0, 0, step, min, samples, &mut cell_index_to_vertex_index, &mut cell_index_to_case, chunk_mesh));
+    with_mixed_samples!(chunk_samples_x, |samples| Self::extract_global_positions_border_xy(1, 0, step, min_x, samples, &mut cell_index_to_vertex_index, &mut cell_index_to_case, chunk_mesh));
+    with_mixed_samples!(chunk_samples_y, |samples| Self::extract_global_positions_border_xy(0, 1, step, min_y, samples, &mut cell_index_to_vertex_index, &mut cell_index_to_case, chunk_mesh));
+    if chunk_samples_xy.is_mixed() {
+      with_mixed_samples!(chunk_samples_xy, |samples| Self::extract_global_positions_border_xy(1, 1, step, min_xy, samples, &mut cell_index_to_vertex_index, &mut cell_index_to_case, chunk_mesh));
       Self::extract_quads_border_xy(1, 1, &cell_index_to_vertex_index, &cell_index_to_case, chunk_mesh);
     }
   }
@@ -187,17 +186,11 @@ impl<C: ChunkSize> SurfaceNetsLod<C> {
   ) {
     let mut cell_index_to_vertex_index = RowVertexIndexArray::<C>::new(u16::MAX);
     let mut cell_index_to_case = RowCaseArray::<C>::new(Case::default());
-    if let MaybeCompressedChunkSamples::Mixed(chunk_sample_array) = chunk_samples {
-      Self::extract_global_positions_border_yz(0, 0, step, min, chunk_sample_array, &mut cell_index_to_vertex_index, &mut cell_index_to_case, chunk_mesh);
-    }
-    if let MaybeCompressedChunkSamples::Mixed(chunk_sample_array) = chunk_samples_y {
-      Self::extract_global_positions_border_yz(1, 0, step, min_y, chunk_sample_array, &mut cell_index_to_vertex_index, &mut cell_index_to_case, chunk_mesh);
-    }
-    if let MaybeCompressedChunkSamples::Mixed(chunk_sample_array) = chunk_samples_z {
-      Self::extract_global_positions_border_yz(0, 1, step, min_z, chunk_sample_array, &mut cell_index_to_vertex_index, &mut cell_index_to_case, chunk_mesh);
-    }
-    if let MaybeCompressedChunkSamples::Mixed(chunk_sample_array) = chunk_samples_yz {
-      Self::extract_global_positions_border_yz(1, 1, step, min_yz, chunk_sample_array, &mut cell_index_to_vertex_index, &mut cell_index_to_case, chunk_mesh);
+    with_mixed_samples!(chunk_samples, |samples| Self::extract_global_positions_border_yz(0, 0, step, min, samples, &mut cell_index_to_vertex_index, &mut cell_index_to_case, chunk_mesh));
+    with_mixed_samples!(chunk_samples_y, |samples| Self::extract_global_positions_border_yz(1, 0, step, min_y, samples, &mut cell_index_to_vertex_index, &mut cell_index_to_case, chunk_mesh));
+    with_mixed_samples!(chunk_samples_z, |samples| Self::extract_global_positions_border_yz(0, 1, step, min_z, samples, &mut cell_index_to_vertex_index, &mut cell_index_to_case, chunk_mesh));
+    if chunk_samples_yz.is_mixed() {
+      with_mixed_samples!(chunk_samples_yz, |samples| Self::extract_global_positions_border_yz(1, 1, step, min_yz, samples, &mut cell_index_to_vertex_index, &mut cell_index_to_case, chunk_mesh));
       Self::extract_quads_border_yz(1, 1, &cell_index_to_vertex_index, &cell_index_to_case, chunk_mesh);
     }
   }
@@ -218,17 +211,11 @@ impl<C: ChunkSize> SurfaceNetsLod<C> {
   ) {
     let mut cell_index_to_vertex_index = RowVertexIndexArray::<C>::new(u16::MAX);
     let mut cell_index_to_case = RowCaseArray::<C>::new(Case::default());
-    if let MaybeCompressedChunkSamples::Mixed(chunk_sample_array) = chunk_samples {
-      Self::extract_global_positions_border_xz(0, 0, step, min, chunk_sample_array, &mut cell_index_to_vertex_index, &mut cell_index_to_case, chunk_mesh);
-    }
-    if let MaybeCompressedChunkSamples::Mixed(chunk_sample_array) = chunk_samples_x {
-      Self::extract_global_positions_border_xz(1, 0, step, min_x, chunk_sample_array, &mut cell_index_to_vertex_index, &mut cell_index_to_case, chunk_mesh);
-    }
-    if let MaybeCompressedChunkSamples::Mixed(chunk_sample_array) = chunk_samples_z {
-      Self::extract_global_positions_border_xz(0, 1, step, min_z, chunk_sample_array, &mut cell_index_to_vertex_index, &mut cell_index_to_case, chunk_mesh);
-    }
-    if let MaybeCompressedChunkSamples::Mixed(chunk_sample_array) = chunk_samples_xz {
-      Self::extract_global_positions_border_xz(1, 1, step, min_xz, chunk_sample_array, &mut cell_index_to_vertex_index, &mut cell_index_to_case, chunk_mesh);
+    with_mixed_samples!(chunk_samples, |samples| Self::extract_global_positions_border_xz(0, 0, step, min, samples, &mut cell_index_to_vertex_index, &mut cell_index_to_case, chunk_mesh));
+    with_mixed_samples!(chunk_samples_x, |samples| Self::extract_global_positions_border_xz(1, 0, step, min_x, samples, &mut cell_index_to_vertex_index, &mut cell_index_to_case, chunk_mesh));
+    with_mixed_samples!(chunk_samples_z, |samples| Self::extract_global_positions_border_xz(0, 1, step, min_z, samples, &mut cell_index_to_vertex_index, &mut cell_index_to_case, chunk_mesh));
+    if chunk_samples_xz.is_mixed() {
+      with_mixed_samples!(chunk_samples_xz, |samples| Self::extract_global_positions_border_xz(1, 1, step, min_xz, samples, &mut cell_index_to_vertex_index, &mut cell_index_to_case, chunk_mesh));
       Self::extract_quads_border_xz(1, 1, &cell_index_to_vertex_index, &cell_index_to_case, chunk_mesh);
     }
   }
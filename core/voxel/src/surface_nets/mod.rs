@@ -9,6 +9,7 @@ use ultraviolet::{UVec3, Vec3};
 
 use crate::chunk::array::Array;
 use crate::chunk::index::{CellIndex, VoxelIndex};
+use crate::chunk::material::MaterialId;
 use crate::chunk::mesh::{ChunkMesh, Vertex};
 use crate::chunk::sample::{ChunkSamples, MaybeCompressedChunkSamples};
 use crate::chunk::shape::Shape;
@@ -41,8 +42,12 @@ impl<C: ChunkSize> SurfaceNets<C> {
     maybe_compressed_chunk_samples: &MaybeCompressedChunkSamples<CS>,
     chunk_mesh: &mut ChunkMesh,
   ) {
-    if let MaybeCompressedChunkSamples::Mixed(chunk_samples) = maybe_compressed_chunk_samples {
-      self.extract_chunk_from_samples(min, step, chunk_samples, chunk_mesh);
+    use MaybeCompressedChunkSamples::*;
+    match maybe_compressed_chunk_samples {
+      Quantized8(chunk_samples) => self.extract_chunk_from_samples(min, step, chunk_samples, chunk_mesh),
+      Quantized16(chunk_samples) => self.extract_chunk_from_samples(min, step, chunk_samples, chunk_mesh),
+      Mixed(chunk_samples) => self.extract_chunk_from_samples(min, step, chunk_samples, chunk_mesh),
+      _ => {} // All samples have the same sign: no surface to extract.
     }
   }
 
@@ -99,7 +104,8 @@ impl<C: ChunkSize> SurfaceNets<C> {
     if case.is_uniform() { return; }
     let global_voxel_positions = Self::global_voxel_positions(min, step, &local_voxel_positions);
     let vertex_position = Self::centroid_of_edge_intersections(case, &values, &global_voxel_positions);
-    Self::write_vertex_position(cell_index_to_vertex_index, chunk_mesh, cell_index, vertex_position);
+    let material = Self::dominant_material_of_edge_intersections(case, chunk_samples, &local_voxel_positions);
+    Self::write_vertex_position(cell_index_to_vertex_index, chunk_mesh, cell_index, vertex_position, material);
     Self::write_case(cell_index_to_case, cell_index, case);
   }
 
@@ -130,6 +136,41 @@ impl<C: ChunkSize> SurfaceNets<C> {
     sum / count as f32
   }
 
+  // Find the most common material on the solid (positive) side of edges that cross the isosurface. Ties are broken by
+  // taking the material that was found first.
+  #[inline]
+  pub fn dominant_material_of_edge_intersections<CS: ChunkSamples<C>>(
+    case: Case,
+    chunk_samples: &CS,
+    local_voxel_positions: &[UVec3; 8],
+  ) -> MaterialId {
+    let mut materials = [0; 12];
+    let mut counts = [0u8; 12];
+    let mut len = 0;
+    for corner in &Self::EDGE_TO_VOXEL_INDICES {
+      let voxel_a_index = corner >> 4 /* High nibble */;
+      let voxel_b_index = corner & 0b0000_1111; /* Low nibble */
+      let a_negative = case.is_negative(voxel_a_index);
+      let b_negative = case.is_negative(voxel_b_index);
+      if a_negative != b_negative {
+        let solid_voxel_index = if a_negative { voxel_b_index } else { voxel_a_index };
+        let material = chunk_samples.material(local_voxel_positions[solid_voxel_index as usize]);
+        if let Some(i) = materials[..len].iter().position(|m| *m == material) {
+          counts[i] += 1;
+        } else {
+          materials[len] = material;
+          counts[len] = 1;
+          len += 1;
+        }
+      }
+    }
+    let mut dominant = 0;
+    for i in 1..len {
+      if counts[i] > counts[dominant] { dominant = i; }
+    }
+    materials[dominant]
+  }
+
   // Given two cube corners, find the point between them where the SDF is zero. (This might not exist).
   #[inline]
   fn surface_edge_intersection(position_a: Vec3, value_a: f32, position_b: Vec3, value_b: f32) -> Vec3 {
@@ -289,8 +330,8 @@ impl<C: ChunkSize> SurfaceNets<C> {
 
   // Read/Write helpers
   #[inline]
-  fn write_vertex_position(cell_index_to_vertex_index: &mut impl Array<u16, CellIndex>, chunk_mesh: &mut ChunkMesh, cell_index: CellIndex, position: Vec3) {
-    let vertex_index = chunk_mesh.push_vertex(Vertex { position });
+  fn write_vertex_position(cell_index_to_vertex_index: &mut impl Array<u16, CellIndex>, chunk_mesh: &mut ChunkMesh, cell_index: CellIndex, position: Vec3, material: MaterialId) {
+    let vertex_index = chunk_mesh.push_vertex(Vertex::new(position, material));
     debug_assert!(cell_index_to_vertex_index.contains(cell_index), "Tried to write out of bounds cell index {} (>= {}) in cell index to vertex index array, with vertex index: {}", cell_index, cell_index_to_vertex_index.len(), vertex_index);
     debug_assert!(cell_index_to_vertex_index[cell_index] == u16::MAX, "Tried to write to already written cell index {} in cell index to vertex index array, with vertex index: {}", cell_index, vertex_index);
     debug_assert!(vertex_index < u16::MAX, "Tried to write vertex index {} that is equal to or larger than {} in cell index to vertex index array, at cell index: {}", vertex_index, u16::MAX, cell_index);
@@ -224,7 +224,7 @@ impl<C: ChunkSize> Transvoxel<C> {
       if i >= vertex_count {
         break;
       }
-      cell_vertices_indices[i] = Self::create_or_reuse_vertex(TransitionVertexData(*vd), u, v, &global_voxels, &values, shared_indices, chunk_mesh);
+      cell_vertices_indices[i] = Self::create_or_reuse_vertex(TransitionVertexData(*vd), u, v, hires_chunk_samples, &hires_local_voxels, &global_voxels, &values, shared_indices, chunk_mesh);
     }
 
     // Write the indices that form the triangulation of this transition cell.
@@ -252,6 +252,8 @@ impl<C: ChunkSize> Transvoxel<C> {
     vertex_data: TransitionVertexData,
     u: u32,
     v: u32,
+    hires_chunk_samples: &MaybeCompressedChunkSampleArray<C>,
+    hires_local_voxels: &[UVec3; 9],
     global_voxels: &[Vec3; 13],
     values: &[f32; 13],
     shared_indices: &mut C::TransvoxelSharedIndicesArray<u16>,
@@ -259,7 +261,7 @@ impl<C: ChunkSize> Transvoxel<C> {
   ) -> u16 {
     if vertex_data.new_reusable_vertex() {
       // Create a new vertex and index, and share the index.
-      let index = Self::create_vertex(vertex_data, global_voxels, values, chunk_mesh);
+      let index = Self::create_vertex(vertex_data, hires_chunk_samples, hires_local_voxels, global_voxels, values, chunk_mesh);
       let shared_indices_index = Self::shared_index(u, v, vertex_data.vertex_index());
       debug_assert!(shared_indices.contains(shared_indices_index), "Tried to write out of bounds shared transition index, at index: {}, position: {}, {}", shared_indices_index, u, v);
       debug_assert!(shared_indices[shared_indices_index] == u16::MAX, "Tried to write already set shared transition index, at index: {}, position: {}, {}", shared_indices_index, u, v);
@@ -267,7 +269,7 @@ impl<C: ChunkSize> Transvoxel<C> {
       index
     } else if vertex_data.new_interior_vertex() {
       // Create a new vertex and index, but this vertex will never be shared, as it is an interior vertex.
-      let index = Self::create_vertex(vertex_data, global_voxels, values, chunk_mesh);
+      let index = Self::create_vertex(vertex_data, hires_chunk_samples, hires_local_voxels, global_voxels, values, chunk_mesh);
       index
     } else {
       let subtract_u = vertex_data.subtract_u();
@@ -289,15 +291,19 @@ impl<C: ChunkSize> Transvoxel<C> {
         index
       } else {
         // Create a new vertex and index, but this vertex will never be shared, as it occurs on the minimal boundary.
-        let index = Self::create_vertex(vertex_data, global_voxels, values, chunk_mesh);
+        let index = Self::create_vertex(vertex_data, hires_chunk_samples, hires_local_voxels, global_voxels, values, chunk_mesh);
         index
       }
     }
   }
 
+  /// Creates a vertex on the edge of `vertex_data`, with the material of the voxel on the solid (positive) side of the
+  /// edge.
   #[inline]
   fn create_vertex(
     vertex_data: TransitionVertexData,
+    hires_chunk_samples: &MaybeCompressedChunkSampleArray<C>,
+    hires_local_voxels: &[UVec3; 9],
     global_voxels: &[Vec3; 13],
     values: &[f32; 13],
     chunk_mesh: &mut ChunkMesh,
@@ -310,7 +316,21 @@ impl<C: ChunkSize> Transvoxel<C> {
     let pos_high = global_voxels[voxel_b_index as usize];
     let value_high = values[voxel_b_index as usize];
     let position = Self::vertex_position(pos_low, value_low, pos_high, value_high);
-    chunk_mesh.push_vertex(Vertex::new(position))
+    let solid_voxel_index = if value_low.is_sign_positive() { voxel_a_index } else { voxel_b_index };
+    let material = hires_chunk_samples.material(hires_local_voxels[Self::hires_voxel_index(solid_voxel_index) as usize]);
+    chunk_mesh.push_vertex(Vertex::new(position, material))
+  }
+
+  /// Maps low-resolution voxel indices (9-C) to the high-resolution voxel (0, 2, 6, 8) at the same position.
+  #[inline]
+  fn hires_voxel_index(voxel_index: u8) -> u8 {
+    match voxel_index {
+      0x9 => 0,
+      0xA => 2,
+      0xB => 6,
+      0xC => 8,
+      i => i,
+    }
   }
 
   #[inline]
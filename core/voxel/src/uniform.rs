@@ -6,6 +6,9 @@ use ultraviolet::{Isometry3, Mat4, Rotor3, Vec3, Vec4};
 use gfx::camera::Camera;
 use gui::widget::UiWidgetsExt;
 
+use crate::chunk::material::MaterialId;
+use crate::volume::{ROCK, SAND, SNOW};
+
 // Camera
 
 #[repr(C)]
@@ -108,11 +111,25 @@ impl LightSettings {
 #[derive(Default, Copy, Clone, Pod, Zeroable, Debug)]
 pub struct ModelUniform {
   pub model: Mat4,
+  /// Distance from the viewer at which LOD nodes are replaced by their parent, relative to the size of the parent.
+  /// Vertices are morphed towards their position at the parent LOD as that distance is approached. 0 disables
+  /// geomorphing.
+  pub lod_distance_per_size: f32,
+  /// Fraction of the distance at which the parent LOD replaces a vertex, over which the vertex is morphed.
+  pub geomorph_range: f32,
+  _dummy: [f32; 2],
 }
 
 impl ModelUniform {
   #[inline]
-  pub fn new(model: Mat4) -> Self { Self { model } }
+  pub fn new(model: Mat4) -> Self { Self { model, lod_distance_per_size: 0.0, geomorph_range: 0.0, _dummy: [0.0; 2] } }
+
+  #[inline]
+  pub fn with_geomorph(mut self, lod_distance_per_size: f32, geomorph_range: f32) -> Self {
+    self.lod_distance_per_size = lod_distance_per_size;
+    self.geomorph_range = geomorph_range;
+    self
+  }
 
   #[inline]
   pub fn from_transform(transform: Isometry3) -> Self { Self::new(transform.into_homogeneous_matrix()) }
@@ -120,3 +137,105 @@ impl ModelUniform {
   #[inline]
   pub fn identity() -> Self { Self::from_transform(Isometry3::identity()) }
 }
+
+// Material palette
+
+/// Maximum number of materials in a [`MaterialPaletteUniform`]. Materials with higher IDs wrap around.
+pub const MATERIAL_PALETTE_SIZE: usize = 16;
+
+#[repr(C)]
+#[derive(Copy, Clone, Pod, Zeroable, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub struct MaterialPaletteUniform {
+  /// Color of each material, indexed by material ID. The alpha component is unused.
+  pub colors: [Vec4; MATERIAL_PALETTE_SIZE],
+}
+
+impl MaterialPaletteUniform {
+  #[inline]
+  pub fn new(colors: [Vec4; MATERIAL_PALETTE_SIZE]) -> Self { Self { colors } }
+
+  pub fn show(&mut self, ui: &mut Ui) {
+    ui.collapsing_open_with_grid("Material Palette", "Grid", |mut ui| {
+      for (material, color) in self.colors.iter_mut().enumerate() {
+        ui.label(format!("Material {}", material));
+        let mut rgba = Rgba::from_rgba_premultiplied(color.x, color.y, color.z, 1.0).into();
+        color_picker::color_edit_button_srgba(&mut ui, &mut rgba, Alpha::Opaque);
+        let rgba: Rgba = rgba.into();
+        *color = Vec4::new(rgba.r(), rgba.g(), rgba.b(), 1.0);
+        ui.end_row();
+      }
+    });
+  }
+}
+
+impl Default for MaterialPaletteUniform {
+  fn default() -> Self {
+    let mut colors = [Vec4::one(); MATERIAL_PALETTE_SIZE];
+    colors[ROCK as usize] = Vec4::new(0.45, 0.42, 0.40, 1.0);
+    colors[SAND as usize] = Vec4::new(0.76, 0.70, 0.50, 1.0);
+    colors[SNOW as usize] = Vec4::new(0.95, 0.95, 0.97, 1.0);
+    Self::new(colors)
+  }
+}
+
+// Triplanar texturing
+
+#[repr(C)]
+#[derive(Copy, Clone, Pod, Zeroable, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub struct TriplanarUniform {
+  /// Number of texture repetitions per world unit.
+  pub texture_scale: f32,
+  /// Exponent applied to the normal when blending the three planar projections. Higher values give sharper transitions.
+  pub blend_sharpness: f32,
+  _dummy: [f32; 2],
+  /// Texture array layer of each material, indexed by material ID. Packed 4 per element to match std140 array layout.
+  pub material_layers: [[u32; 4]; MATERIAL_PALETTE_SIZE / 4],
+}
+
+impl TriplanarUniform {
+  pub fn new(texture_scale: f32, blend_sharpness: f32) -> Self {
+    let mut material_layers = [[0; 4]; MATERIAL_PALETTE_SIZE / 4];
+    for material in 0..MATERIAL_PALETTE_SIZE {
+      material_layers[material / 4][material % 4] = material as u32;
+    }
+    Self { texture_scale, blend_sharpness, _dummy: [0.0; 2], material_layers }
+  }
+
+  #[inline]
+  pub fn material_layer(&self, material: MaterialId) -> u32 {
+    let material = material as usize % MATERIAL_PALETTE_SIZE;
+    self.material_layers[material / 4][material % 4]
+  }
+
+  #[inline]
+  pub fn set_material_layer(&mut self, material: MaterialId, layer: u32) {
+    let material = material as usize % MATERIAL_PALETTE_SIZE;
+    self.material_layers[material / 4][material % 4] = layer;
+  }
+
+  pub fn show(&mut self, ui: &mut Ui, layer_count: u32) {
+    ui.collapsing_open_with_grid("Triplanar Texturing", "Grid", |ui| {
+      ui.label("Texture scale");
+      ui.add(DragValue::new(&mut self.texture_scale).speed(0.001).clamp_range(0.0001..=f32::INFINITY));
+      ui.end_row();
+      ui.label("Blend sharpness");
+      ui.add(DragValue::new(&mut self.blend_sharpness).speed(0.1).clamp_range(1.0..=64.0));
+      ui.end_row();
+      for material in 0..MATERIAL_PALETTE_SIZE as MaterialId {
+        ui.label(format!("Material {} layer", material));
+        let mut layer = self.material_layer(material);
+        ui.add(DragValue::new(&mut layer).clamp_range(0..=layer_count.saturating_sub(1)));
+        self.set_material_layer(material, layer);
+        ui.end_row();
+      }
+    });
+  }
+}
+
+impl Default for TriplanarUniform {
+  fn default() -> Self {
+    Self::new(0.05, 4.0)
+  }
+}
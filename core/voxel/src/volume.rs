@@ -1,6 +1,11 @@
+use std::hash::Hasher;
+
 use ultraviolet::{UVec3, Vec3};
 
+use job_queue::CancellationToken;
+
 use crate::chunk::array::{Array, SliceMut};
+use crate::chunk::material::{ChunkMaterials, MaterialId};
 use crate::chunk::sample::{ChunkSampleArray, MaybeCompressedChunkSampleArray, MaybeCompressedChunkSamples};
 use crate::chunk::shape::Shape;
 use crate::chunk::size::ChunkSize;
@@ -11,43 +16,115 @@ pub trait Volume: Clone + Send + 'static {
   /// Samples a single position, returning its value.
   fn sample(&self, position: UVec3) -> f32;
 
-  /// Samples an entire chunk, returning a value indicating whether the chunk is all zero, positive, negative, or mixed.
+  /// Samples the material at a single position. Defaults to material `0`.
+  #[inline]
+  fn sample_material(&self, _position: UVec3) -> MaterialId { 0 }
+
+  /// Writes a description of this volume into `state`. Volumes that produce different samples must write different
+  /// descriptions, as the resulting hash is used to key persistently cached samples.
+  fn hash_description<H: Hasher>(&self, state: &mut H);
+
+  /// Samples a position between the integer positions of the volume, for sampling along curved grids. Defaults to
+  /// sampling the nearest integer position.
+  #[inline]
+  fn sample_at(&self, position: Vec3) -> f32 { self.sample(nearest_position(position)) }
+
+  /// Samples the material at a position between the integer positions of the volume. Defaults to sampling the material
+  /// at the nearest integer position.
+  #[inline]
+  fn sample_material_at(&self, position: Vec3) -> MaterialId { self.sample_material(nearest_position(position)) }
+
+  /// Samples an entire chunk, returning a value indicating whether the chunk is all zero, all the same value, positive,
+  /// negative, or mixed. Mixed samples are returned at full precision, along with their materials.
+  ///
+  /// `cancellation_token` is checked once per slice of the chunk. When it is cancelled, sampling stops and zero samples
+  /// are returned, which are meant to be discarded.
   #[profiling::function]
-  fn sample_chunk<C: ChunkSize>(&self, start: UVec3, step: u32) -> MaybeCompressedChunkSampleArray<C> {
-    let mut all_zero = true;
-    let mut all_positive = true;
-    let mut all_negative = true;
-    let mut array = C::VoxelChunkArray::new(0.0);
-    C::VoxelChunkShape::for_all(|x, y, z, i| {
-      let position = start + step * UVec3::new(x, y, z);
-      let value = self.sample(position);
-      if value != 0.0 { all_zero = false; }
-      if value.is_sign_positive() { all_negative = false; } else { all_positive = false; }
-      array.set(i, value);
-    });
-    if all_zero {
-      MaybeCompressedChunkSamples::Zero
-    } else if all_positive {
-      MaybeCompressedChunkSamples::Positive
-    } else if all_negative {
-      MaybeCompressedChunkSamples::Negative
-    } else {
-      MaybeCompressedChunkSamples::Mixed(ChunkSampleArray::new(array))
+  fn sample_chunk<C: ChunkSize>(&self, start: UVec3, step: u32, cancellation_token: &CancellationToken) -> MaybeCompressedChunkSampleArray<C> {
+    let position = |x, y, z| start + step * UVec3::new(x, y, z);
+    sample_chunk_with(|x, y, z| self.sample(position(x, y, z)), |x, y, z| self.sample_material(position(x, y, z)), cancellation_token)
+  }
+
+  /// Samples an entire chunk like [`sample_chunk`](Self::sample_chunk), where `position` maps the position of each
+  /// sample in the chunk (from 0 to `C::VOXELS_IN_CHUNK_ROW` exclusive) to the position in the volume to sample.
+  #[profiling::function]
+  fn sample_chunk_at<C: ChunkSize>(&self, position: impl Fn(UVec3) -> Vec3, cancellation_token: &CancellationToken) -> MaybeCompressedChunkSampleArray<C> {
+    let position = |x, y, z| position(UVec3::new(x, y, z));
+    sample_chunk_with(|x, y, z| self.sample_at(position(x, y, z)), |x, y, z| self.sample_material_at(position(x, y, z)), cancellation_token)
+  }
+}
+
+#[inline]
+fn nearest_position(position: Vec3) -> UVec3 {
+  let position = position.max_by_component(Vec3::zero()).map(|p| p.round());
+  UVec3::new(position.x as u32, position.y as u32, position.z as u32)
+}
+
+#[inline]
+fn sample_chunk_with<C: ChunkSize>(
+  mut sample: impl FnMut(u32, u32, u32) -> f32,
+  mut sample_material: impl FnMut(u32, u32, u32) -> MaterialId,
+  cancellation_token: &CancellationToken,
+) -> MaybeCompressedChunkSampleArray<C> {
+  let mut all_zero = true;
+  let mut all_positive = true;
+  let mut all_negative = true;
+  let mut first_value = None;
+  let mut all_equal = true;
+  let mut cancelled = false;
+  let mut array = C::VoxelChunkArray::new(0.0);
+  C::VoxelChunkShape::for_all(|x, y, z, i| {
+    if x == 0 && y == 0 { // Start of a slice.
+      cancelled = cancellation_token.is_cancelled();
     }
+    if cancelled { return; }
+    let value = sample(x, y, z);
+    if value != 0.0 { all_zero = false; }
+    if value.is_sign_positive() { all_negative = false; } else { all_positive = false; }
+    if *first_value.get_or_insert(value) != value { all_equal = false; }
+    array.set(i, value);
+  });
+  if all_zero || cancelled {
+    MaybeCompressedChunkSamples::Zero
+  } else if all_equal {
+    MaybeCompressedChunkSamples::Uniform(first_value.unwrap()) // Unwrap OK: chunks contain at least one voxel.
+  } else if all_positive {
+    MaybeCompressedChunkSamples::Positive
+  } else if all_negative {
+    MaybeCompressedChunkSamples::Negative
+  } else {
+    let mut materials = Vec::with_capacity(C::VOXELS_IN_CHUNK_USIZE);
+    C::VoxelChunkShape::for_all(|x, y, z, _| {
+      materials.push(sample_material(x, y, z));
+    });
+    MaybeCompressedChunkSamples::Mixed(Box::new(ChunkSampleArray::with_materials(array, ChunkMaterials::from_materials(materials))))
   }
 }
 
+// Materials
+
+/// Material of rock, produced by the built-in volumes.
+pub const ROCK: MaterialId = 0;
+/// Material of sand, produced by the built-in volumes.
+pub const SAND: MaterialId = 1;
+/// Material of snow, produced by the built-in volumes.
+pub const SNOW: MaterialId = 2;
+
 // Sphere
 
 #[derive(Copy, Clone, Debug)]
-#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize), serde(default))]
 pub struct SphereSettings {
   pub radius: f32,
+  /// Voxels with an altitude (distance from the surface of the sphere) below this are sand.
+  pub sand_altitude: f32,
+  /// Voxels with an altitude (distance from the surface of the sphere) above this are snow.
+  pub snow_altitude: f32,
 }
 impl Default for SphereSettings {
   #[inline]
   fn default() -> Self {
-    Self { radius: 4096.0 }
+    Self { radius: 4096.0, sand_altitude: 4.0, snow_altitude: 48.0 }
   }
 }
 
@@ -55,20 +132,53 @@ impl Default for SphereSettings {
 pub struct Sphere {
   radius: f32,
   half_radius_vec: Vec3,
+  sand_altitude: f32,
+  snow_altitude: f32,
 }
 impl Sphere {
   #[inline]
   pub fn new(settings: SphereSettings) -> Self {
-    Self { radius: settings.radius, half_radius_vec: Vec3::one() * (settings.radius / 2.0) }
+    Self {
+      radius: settings.radius,
+      half_radius_vec: Vec3::one() * (settings.radius / 2.0),
+      sand_altitude: settings.sand_altitude,
+      snow_altitude: settings.snow_altitude,
+    }
   }
 }
 impl Volume for Sphere {
   #[inline]
-  fn sample(&self, position: UVec3) -> f32 {
+  fn sample(&self, position: UVec3) -> f32 { self.sample_at(position.into()) }
+
+  #[inline]
+  fn sample_material(&self, position: UVec3) -> MaterialId { self.sample_material_at(position.into()) }
+
+  #[inline]
+  fn sample_at(&self, position: Vec3) -> f32 {
     // Transform position from 0..n to -half_radius..half_radius.
-    let position = Vec3::from(position) - self.half_radius_vec;
+    let position = position - self.half_radius_vec;
     0.5 - position.mag() / self.radius
   }
+
+  #[inline]
+  fn sample_material_at(&self, position: Vec3) -> MaterialId {
+    let position = position - self.half_radius_vec;
+    let altitude = position.mag() - self.radius / 2.0;
+    if altitude < self.sand_altitude {
+      SAND
+    } else if altitude > self.snow_altitude {
+      SNOW
+    } else {
+      ROCK
+    }
+  }
+
+  fn hash_description<H: Hasher>(&self, state: &mut H) {
+    state.write(b"sphere");
+    state.write_u32(self.radius.to_bits());
+    state.write_u32(self.sand_altitude.to_bits());
+    state.write_u32(self.snow_altitude.to_bits());
+  }
 }
 
 // Noise
@@ -110,12 +220,24 @@ impl Noise {
 
 impl Volume for Noise {
   #[inline]
-  fn sample(&self, position: UVec3) -> f32 {
+  fn sample(&self, position: UVec3) -> f32 { self.sample_at(position.into()) }
+
+  #[inline]
+  fn sample_at(&self, position: Vec3) -> f32 {
     let freq = self.settings.frequency;
     unsafe {
-      simdnoise::scalar::fbm_3d(position.x as f32 * freq, position.y as f32 * freq, position.z as f32 * freq, self.settings.lacunarity, self.settings.gain, self.settings.octaves, self.settings.seed)
+      simdnoise::scalar::fbm_3d(position.x * freq, position.y * freq, position.z * freq, self.settings.lacunarity, self.settings.gain, self.settings.octaves, self.settings.seed)
     }
   }
+
+  fn hash_description<H: Hasher>(&self, state: &mut H) {
+    state.write(b"noise");
+    state.write_i32(self.settings.seed);
+    state.write_u32(self.settings.lacunarity.to_bits());
+    state.write_u32(self.settings.frequency.to_bits());
+    state.write_u32(self.settings.gain.to_bits());
+    state.write_u8(self.settings.octaves);
+  }
 }
 
 // Plus
@@ -138,4 +260,26 @@ impl<V1: Volume, V2: Volume> Volume for Plus<V1, V2> {
   fn sample(&self, position: UVec3) -> f32 {
     self.volume_1.sample(position) + self.volume_2.sample(position)
   }
+
+  /// Materials are taken from the first volume.
+  #[inline]
+  fn sample_material(&self, position: UVec3) -> MaterialId {
+    self.volume_1.sample_material(position)
+  }
+
+  #[inline]
+  fn sample_at(&self, position: Vec3) -> f32 {
+    self.volume_1.sample_at(position) + self.volume_2.sample_at(position)
+  }
+
+  #[inline]
+  fn sample_material_at(&self, position: Vec3) -> MaterialId {
+    self.volume_1.sample_material_at(position)
+  }
+
+  fn hash_description<H: Hasher>(&self, state: &mut H) {
+    state.write(b"plus");
+    self.volume_1.hash_description(state);
+    self.volume_2.hash_description(state);
+  }
 }
@@ -1,20 +1,25 @@
 use std::iter::FusedIterator;
 
-use tracing::{info, trace};
+use tracing::{error, info, trace};
 
-use job_queue::JobQueue;
+use job_queue::{CancellationToken, JobQueue};
+use os::ApplicationOptions;
+use os::directory::Directories;
 
 fn main() {
   // TODO: "featurize" os. Because this depends on `os` which has stuff like creating windows, which is not needed for
   // this app.
   os::init();
   let _tracing = os::init_tracing();
+  let application = ApplicationOptions { name: "Job Queue".to_string(), ..ApplicationOptions::default() };
+  let directories = Directories::new(&application.name, &application.organization, &application.qualifier);
+  let job_graph_dot_file_path = directories.cache_dir().join("job_graph.dot");
 
   let job_queue = JobQueue::new(
     8,
     1024,
     1024,
-    move |key: i32, input: f32, deps: &[(f32, f32)]| {
+    move |key: i32, input: f32, deps: &[(f32, f32)], _cancellation_token: &CancellationToken| {
       trace!("Executing job {} with deps {:?}", key, deps);
       let (dependency_key, dependency_output) = if deps.len() > 0 {
         let dep = &deps[0];
@@ -25,10 +30,9 @@ fn main() {
       key as f32 * input * (1.0 / dependency_output) * dependency_key
     }).unwrap();
 
-  job_queue.try_add_job(Job(1024)).unwrap();
+  job_queue.try_add_job(Job(1024), 0).unwrap();
 
   let receiver = job_queue.get_message_receiver();
-  let mut done = false;
   for message in receiver.iter() {
     use job_queue::JobQueueMessage::*;
     match message {
@@ -36,12 +40,23 @@ fn main() {
       PendingJobRemoved(job_key, input) => info!("Pending job {} with input {} removed", job_key, input),
       RunningJobRemoved(job_key) => info!("Running job {} removed", job_key),
       CompletedJobRemoved(job_key, output) => info!("Completed job {} with output {:?} removed", job_key, output),
+      JobFailed(job_key, error) => error!("Job {} failed: {}", job_key, error),
+      FailedJobRemoved(job_key) => info!("Failed job {} removed", job_key),
+      Metrics(metrics) => info!("Metrics: {:?}", metrics),
       QueueEmpty => {
-        done = true;
         info!("Done!");
+        job_queue.request_job_graph().unwrap();
+      }
+      JobGraph(job_graph) => {
+        let result = std::fs::create_dir_all(directories.cache_dir())
+          .and_then(|_| std::fs::write(&job_graph_dot_file_path, job_graph.to_dot()));
+        match result {
+          Ok(_) => info!("Wrote job graph to '{}'", job_graph_dot_file_path.display()),
+          Err(e) => error!("Failed to write job graph to '{}': {:?}", job_graph_dot_file_path.display(), e),
+        }
+        break;
       }
     }
-    if done && receiver.is_empty() { break; }
   }
   job_queue.stop_and_join().unwrap();
 }
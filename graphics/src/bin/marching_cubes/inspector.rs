@@ -7,7 +7,7 @@ use gfx::fmt_math::UVec3DisplayExt;
 use gui::Gui;
 use gui::widget::UiWidgetsExt;
 use voxel::chunk::mesh::ChunkMesh;
-use voxel::chunk::sample::{ChunkSampleArray, ChunkSamples, ChunkSamplesMut, MaybeCompressedChunkSampleArray};
+use voxel::chunk::sample::{ChunkSampleArray, ChunkSamples, ChunkSamplesMut};
 use voxel::chunk::size::{ChunkSize, ChunkSize1};
 use voxel::marching_cubes;
 use voxel::marching_cubes::{MarchingCubes, RegularCell};
@@ -272,7 +272,7 @@ impl Inspector {
 
   pub fn extract_chunk(&self, chunk_vertices: &mut ChunkMesh) {
     // HACK: pass LORES_STEP (2) here, to make global voxels draw as if this was a 2x2 chunk grid.
-    self.marching_cubes.extract_chunk(MIN, STEP, &MaybeCompressedChunkSampleArray::Mixed(self.samples.clone()), chunk_vertices);
+    self.marching_cubes.extract_chunk_from_samples(MIN, STEP, &self.samples, chunk_vertices);
   }
 
   pub fn debug_draw(&self, debug_renderer: &mut DebugRenderer) {
@@ -14,7 +14,7 @@ use gfx::Gfx;
 use os::Os;
 use voxel::chunk::mesh::ChunkMesh;
 use voxel::render::VoxelRenderer;
-use voxel::uniform::{CameraUniform, ModelUniform};
+use voxel::uniform::{CameraUniform, MaterialPaletteUniform, ModelUniform};
 
 use crate::data::Data;
 
@@ -61,6 +61,7 @@ impl app::Application for MarchingCubesDemo {
       camera_uniform,
       data.light.uniform,
       model_uniform,
+      MaterialPaletteUniform::default(),
       None,
       StagingBelt::new(256), // Tiny staging belt: tiny buffers in this demo.
     );
@@ -15,7 +15,7 @@ use os::Os;
 use voxel::chunk::mesh::ChunkMesh;
 use voxel::chunk::size::{ChunkSize, ChunkSize2, ChunkSize6};
 use voxel::render::VoxelRenderer;
-use voxel::uniform::{CameraUniform, ModelUniform};
+use voxel::uniform::{CameraUniform, MaterialPaletteUniform, ModelUniform};
 
 use crate::data::Data;
 use crate::inspector::SurfaceNetsInspector;
@@ -66,6 +66,7 @@ impl app::Application for SurfaceNetsDemo {
       camera_uniform,
       data.light.uniform,
       model_uniform,
+      MaterialPaletteUniform::default(),
       None,
       StagingBelt::new(1024), // Small staging belt: small buffers in this demo.
     );
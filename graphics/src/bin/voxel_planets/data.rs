@@ -1,4 +1,5 @@
 use std::mem::size_of_val;
+use std::path::Path;
 
 use egui::{Align2, ComboBox, Ui};
 use egui::color_picker::Alpha;
@@ -9,11 +10,16 @@ use gfx::camera::inspector::CameraInspector;
 use gfx::camera::system::CameraSystemState;
 use gfx::Gfx;
 use gui::widget::UiWidgetsExt;
+use job_queue::inspector::JobQueueInspector;
+use voxel::chunk::occlusion::AmbientOcclusionSettings;
+use voxel::chunk::sample::ChunkSampleQuantization;
 use voxel::chunk::size::ChunkSize16;
 use voxel::lod::builder::LodManagerBuilder;
 use voxel::lod::chunk_mesh::LodChunkMeshManagerParameters;
+use voxel::lod::extract::LodExtractor;
 use voxel::lod::marching_cubes::{MarchingCubesExtractor, MarchingCubesExtractorSettings};
-use voxel::lod::octmap::LodOctmapSettings;
+use voxel::lod::octmap::{LodMetric, LodOctmapSettings};
+use voxel::lod::planet::LodPlanetSettings;
 use voxel::lod::render::{LodRenderData, LodRenderDataManager, LodRenderDataSettings};
 use voxel::lod::surface_nets::{SurfaceNetsExtractor, SurfaceNetsExtractorSettings};
 use voxel::lod::transvoxel::{TransvoxelExtractor, TransvoxelExtractorSettings};
@@ -21,7 +27,7 @@ use voxel::marching_cubes::MarchingCubes;
 use voxel::surface_nets::lod::SurfaceNetsLod;
 use voxel::surface_nets::SurfaceNets;
 use voxel::transvoxel::Transvoxel;
-use voxel::uniform::LightSettings;
+use voxel::uniform::{LightSettings, MaterialPaletteUniform, TriplanarUniform};
 use voxel::volume::{Noise, NoiseSettings, Plus, Sphere, SphereSettings, Volume};
 
 use crate::stars::StarsRendererSettings;
@@ -48,8 +54,11 @@ pub enum ExtractorType {
 pub struct Data {
   pub camera_manager_state: CameraSystemState,
   pub camera_inspector: CameraInspector,
+  pub job_queue_inspector: JobQueueInspector,
 
   pub light: LightSettings,
+  pub material_palette: MaterialPaletteUniform,
+  pub triplanar: TriplanarUniform,
 
   pub volume_type: VolumeType,
   pub sphere_settings: SphereSettings,
@@ -61,6 +70,10 @@ pub struct Data {
   pub surface_nets_settings: SurfaceNetsExtractorSettings,
 
   pub lod_octmap_settings: LodOctmapSettings,
+  pub use_sample_cache: bool,
+  pub use_planet_lod: bool,
+  pub planet_shell_depth: f32,
+  pub planet_shell_height: f32,
 
   pub lod_render_data_settings: LodRenderDataSettings,
   pub auto_update: bool,
@@ -77,7 +90,10 @@ impl Default for Data {
         window_anchor: Some(Align2::LEFT_BOTTOM),
         ..Default::default()
       },
+      job_queue_inspector: Default::default(),
       light: Default::default(),
+      material_palette: Default::default(),
+      triplanar: Default::default(),
       volume_type: Default::default(),
       sphere_settings: Default::default(),
       noise_settings: Default::default(),
@@ -85,7 +101,12 @@ impl Default for Data {
       marching_cubes_settings: Default::default(),
       transvoxel_settings: Default::default(),
       surface_nets_settings: Default::default(),
-      lod_octmap_settings: Default::default(),
+      // Half the memory of full precision samples, without visible differences in the meshes.
+      lod_octmap_settings: LodOctmapSettings { sample_quantization: ChunkSampleQuantization::Bits16, ..Default::default() },
+      use_sample_cache: true,
+      use_planet_lod: false,
+      planet_shell_depth: 256.0,
+      planet_shell_height: 256.0,
       lod_render_data_settings: Default::default(),
       auto_update: true,
       stars_renderer_settings: Default::default(),
@@ -101,8 +122,10 @@ impl Data {
     gfx: &Gfx,
     transform: Isometry3,
     view_projection_matrix: Mat4,
+    sample_cache_directory: &Path,
   ) -> Box<dyn LodRenderDataManager<C16>> {
     let builder = LodManagerBuilder::new::<C16>();
+    let builder = if self.use_sample_cache { builder.with_sample_cache_directory(sample_cache_directory) } else { builder };
     match self.volume_type {
       VolumeType::Sphere => self.build_lod_render_data_manager(gfx, builder.with_volume(Sphere::new(self.sphere_settings)), transform, view_projection_matrix),
       VolumeType::Noise => self.build_lod_render_data_manager(gfx, builder.with_volume(Noise::new(self.noise_settings)), transform, view_projection_matrix),
@@ -118,18 +141,43 @@ impl Data {
     view_projection_matrix: Mat4,
   ) -> Box<dyn LodRenderDataManager<C16>> {
     match self.extractor_type {
-      ExtractorType::MarchingCubes => builder
-        .with_extractor(MarchingCubesExtractor::new(MarchingCubes::<C16>::default(), self.marching_cubes_settings))
-        .build_boxed(gfx, self.lod_octmap_settings, transform, view_projection_matrix),
-      ExtractorType::Transvoxel => builder
-        .with_extractor(TransvoxelExtractor::new(MarchingCubes::<C16>::default(), Transvoxel::<C16>::default(), self.transvoxel_settings))
-        .build_boxed(gfx, self.lod_octmap_settings, transform, view_projection_matrix),
-      ExtractorType::SurfaceNets => builder
-        .with_extractor(SurfaceNetsExtractor::new(SurfaceNets::<C16>::default(), SurfaceNetsLod::<C16>::default(), self.surface_nets_settings))
-        .build_boxed(gfx, self.lod_octmap_settings, transform, view_projection_matrix),
-      ExtractorType::Noop => builder
-        .with_extractor(())
-        .build_boxed(gfx, self.lod_octmap_settings, transform, view_projection_matrix),
+      ExtractorType::MarchingCubes => self.build_boxed(gfx, builder.with_extractor(MarchingCubesExtractor::new(MarchingCubes::<C16>::default(), self.marching_cubes_settings)), transform, view_projection_matrix),
+      ExtractorType::Transvoxel => self.build_boxed(gfx, builder.with_extractor(TransvoxelExtractor::new(MarchingCubes::<C16>::default(), Transvoxel::<C16>::default(), self.transvoxel_settings)), transform, view_projection_matrix),
+      ExtractorType::SurfaceNets => self.build_boxed(gfx, builder.with_extractor(SurfaceNetsExtractor::new(SurfaceNets::<C16>::default(), SurfaceNetsLod::<C16>::default(), self.surface_nets_settings)), transform, view_projection_matrix),
+      ExtractorType::Noop => self.build_boxed(gfx, builder.with_extractor(()), transform, view_projection_matrix),
+    }
+  }
+
+  fn build_boxed<V: Volume, E: LodExtractor<C16>>(
+    &self,
+    gfx: &Gfx,
+    builder: LodManagerBuilder<C16, V, E>,
+    transform: Isometry3,
+    view_projection_matrix: Mat4,
+  ) -> Box<dyn LodRenderDataManager<C16>> {
+    if self.use_planet_lod {
+      builder.build_planet_boxed(gfx, self.lod_planet_settings(), transform, view_projection_matrix)
+    } else {
+      builder.build_boxed(gfx, self.lod_octmap_settings, transform, view_projection_matrix)
+    }
+  }
+
+  /// Gets settings for a planet around the surface of the sphere, which is centered in the volume. The shell is clamped
+  /// so that it fits inside the sphere and inside the faces of the planet, as the settings can be changed freely in the
+  /// GUI.
+  fn lod_planet_settings(&self) -> LodPlanetSettings {
+    let radius = (self.sphere_settings.radius / 2.0).max(1.0);
+    // A quarter circle at the surface spans a face, see `CubeSphere::new`. Leave one cell of slack for rounding.
+    let root_size = self.lod_octmap_settings.root_size;
+    let max_shell_size = radius * std::f32::consts::FRAC_PI_2 * root_size.saturating_sub(1) as f32 / root_size as f32;
+    let shell_depth = self.planet_shell_depth.clamp(0.0, (radius - 1.0).min(max_shell_size));
+    let shell_height = self.planet_shell_height.clamp(0.0, max_shell_size - shell_depth);
+    LodPlanetSettings {
+      octmap: self.lod_octmap_settings,
+      center: Vec3::broadcast(radius),
+      radius,
+      shell_depth,
+      shell_height,
     }
   }
 
@@ -138,6 +186,7 @@ impl Data {
     if ui.button("Reset to defaults (double click)").double_clicked() {
       *self = Self {
         camera_inspector: self.camera_inspector,
+        job_queue_inspector: self.job_queue_inspector,
         ..Self::default()
       };
       return true;
@@ -149,6 +198,14 @@ impl Data {
     self.light.show(ui, camera_direction_inverse);
   }
 
+  pub fn draw_material_palette_gui(&mut self, ui: &mut Ui) {
+    self.material_palette.show(ui);
+  }
+
+  pub fn draw_triplanar_gui(&mut self, ui: &mut Ui, texture_layer_count: u32) {
+    self.triplanar.show(ui, texture_layer_count);
+  }
+
   /// Returns true if update button was pressed.
   pub fn draw_volume_gui(&mut self, ui: &mut Ui) -> bool {
     ui.collapsing_open_with_grid("Volume", "Grid", |ui| {
@@ -177,6 +234,12 @@ impl Data {
     ui.label("Radius");
     ui.drag_unlabelled(&mut self.sphere_settings.radius, 0.1);
     ui.end_row();
+    ui.label("Sand altitude");
+    ui.drag_unlabelled(&mut self.sphere_settings.sand_altitude, 0.1);
+    ui.end_row();
+    ui.label("Snow altitude");
+    ui.drag_unlabelled(&mut self.sphere_settings.snow_altitude, 0.1);
+    ui.end_row();
   }
 
   fn draw_noise_settings(&mut self, ui: &mut Ui) {
@@ -212,7 +275,9 @@ impl Data {
         });
       ui.end_row();
       match self.extractor_type {
-        ExtractorType::MarchingCubes => {}
+        ExtractorType::MarchingCubes => {
+          Self::draw_ambient_occlusion_settings(ui, &mut self.marching_cubes_settings.ambient_occlusion);
+        }
         ExtractorType::Transvoxel => {
           ui.label("Extract regular chunks?");
           ui.checkbox(&mut self.transvoxel_settings.extract_regular_chunks, "");
@@ -230,6 +295,7 @@ impl Data {
             ui.end_row();
           });
           ui.end_row();
+          Self::draw_ambient_occlusion_settings(ui, &mut self.transvoxel_settings.ambient_occlusion);
         }
         ExtractorType::SurfaceNets => {
           ui.label("Extract regular chunks?");
@@ -247,6 +313,7 @@ impl Data {
             ui.end_row();
           });
           ui.end_row();
+          Self::draw_ambient_occlusion_settings(ui, &mut self.surface_nets_settings.ambient_occlusion);
         }
         ExtractorType::Noop => {}
       }
@@ -254,17 +321,70 @@ impl Data {
     }).body_returned.map(|i| i.inner).unwrap_or(false)
   }
 
+  fn draw_ambient_occlusion_settings(ui: &mut Ui, settings: &mut AmbientOcclusionSettings) {
+    ui.label("Ambient occlusion?");
+    ui.checkbox(&mut settings.enabled, "");
+    ui.end_row();
+    if settings.enabled {
+      ui.label("Ambient occlusion quality");
+      ui.drag_unlabelled_range(&mut settings.quality, 1, 1..=64);
+      ui.end_row();
+      ui.label("Ambient occlusion radius");
+      ui.drag_unlabelled_range(&mut settings.radius, 0.1, 0.1..=16.0);
+      ui.end_row();
+    }
+  }
+
   pub fn draw_lod_octmap_gui(
     &mut self,
     ui: &mut Ui,
   ) -> bool {
     ui.collapsing_open_with_grid("LOD octmap", "Grid", |ui| {
+      ui.label("LOD metric");
+      ComboBox::from_id_source("LOD metric")
+        .selected_text(format!("{:?}", self.lod_octmap_settings.lod_metric))
+        .show_ui(ui, |ui| {
+          ui.selectable_value(&mut self.lod_octmap_settings.lod_metric, LodMetric::Distance, "Distance");
+          ui.selectable_value(&mut self.lod_octmap_settings.lod_metric, LodMetric::ScreenSpaceError, "Screen-space error");
+        });
+      ui.end_row();
+      ui.label("Max screen-space error (px)");
+      ui.drag_unlabelled_range(&mut self.lod_octmap_settings.max_screen_space_error, 0.5, 1.0..=256.0);
+      ui.end_row();
       ui.label("Thread pool threads");
       ui.drag_unlabelled_range(&mut self.lod_octmap_settings.job_queue_worker_threads, 1, 1..=2usize.pow(8));
       ui.end_row();
       ui.label("Chunk mesh cache size");
       ui.drag_unlabelled_range(&mut self.lod_octmap_settings.empty_lod_chunk_mesh_cache_size, 1, 1..=2usize.pow(16));
       ui.end_row();
+      ui.label("Removed chunk samples cache (MiB)");
+      let mut removed_chunk_cache_mebibytes = self.lod_octmap_settings.removed_chunk_cache_byte_budget / (1024 * 1024);
+      ui.drag_unlabelled_range(&mut removed_chunk_cache_mebibytes, 1, 0..=2usize.pow(14));
+      self.lod_octmap_settings.removed_chunk_cache_byte_budget = removed_chunk_cache_mebibytes * 1024 * 1024;
+      ui.end_row();
+      ui.label("Sample quantization");
+      ComboBox::from_id_source("Sample quantization")
+        .selected_text(format!("{:?}", self.lod_octmap_settings.sample_quantization))
+        .show_ui(ui, |ui| {
+          ui.selectable_value(&mut self.lod_octmap_settings.sample_quantization, ChunkSampleQuantization::None, "None");
+          ui.selectable_value(&mut self.lod_octmap_settings.sample_quantization, ChunkSampleQuantization::Bits16, "16 bits");
+          ui.selectable_value(&mut self.lod_octmap_settings.sample_quantization, ChunkSampleQuantization::Bits8, "8 bits");
+        });
+      ui.end_row();
+      ui.label("Cache samples on disk?");
+      ui.checkbox(&mut self.use_sample_cache, "");
+      ui.end_row();
+      ui.label("Planet LOD?");
+      ui.checkbox(&mut self.use_planet_lod, "");
+      ui.end_row();
+      if self.use_planet_lod {
+        ui.label("Planet shell depth");
+        ui.drag_unlabelled_range(&mut self.planet_shell_depth, 1.0, 0.0..=4096.0);
+        ui.end_row();
+        ui.label("Planet shell height");
+        ui.drag_unlabelled_range(&mut self.planet_shell_height, 1.0, 0.0..=4096.0);
+        ui.end_row();
+      }
       return ui.button("Update").clicked();
     }).body_returned.map(|i| i.inner).unwrap_or(false)
   }
@@ -280,6 +400,10 @@ impl Data {
       ui.drag_unlabelled_range(lod_chunk_mesh_manager.get_lod_factor_mut(), 0.1, 0.0..=4.0);
       self.lod_octmap_settings.lod_factor = lod_chunk_mesh_manager.get_lod_factor(); // Also update settings.
       ui.end_row();
+      ui.label("LOD hysteresis");
+      ui.drag_unlabelled_range(lod_chunk_mesh_manager.get_lod_hysteresis_mut(), 0.01, 0.0..=1.0);
+      self.lod_octmap_settings.lod_hysteresis = lod_chunk_mesh_manager.get_lod_hysteresis(); // Also update settings.
+      ui.end_row();
       ui.label("Fixed LOD level?");
       ui.horizontal(|ui| {
         let mut use_fixed_lod_level = lod_chunk_mesh_manager.get_fixed_lod_level().is_some();
@@ -307,6 +431,12 @@ impl Data {
     ui: &mut Ui,
   ) -> bool {
     ui.collapsing_open_with_grid("LOD render data manager", "Grid", |ui| {
+      ui.label("Frustum culling?");
+      ui.checkbox(&mut self.lod_render_data_settings.frustum_culling, "");
+      ui.end_row();
+      ui.label("Geomorph range");
+      ui.drag_unlabelled_range(&mut self.lod_render_data_settings.geomorph_range, 0.01, 0.0..=1.0);
+      ui.end_row();
       ui.label("Debug render vertices?");
       ui.horizontal(|ui| {
         ui.checkbox(&mut self.lod_render_data_settings.debug_render_vertices, "");
@@ -327,6 +457,12 @@ impl Data {
         ui.edit_color_vec4(&mut self.lod_render_data_settings.debug_render_octree_node_empty_color, Alpha::OnlyBlend);
       });
       ui.end_row();
+      ui.label("Debug render culled octree nodes?");
+      ui.horizontal(|ui| {
+        ui.checkbox(&mut self.lod_render_data_settings.debug_render_culled_octree_nodes, "");
+        ui.edit_color_vec4(&mut self.lod_render_data_settings.debug_render_octree_node_culled_color, Alpha::OnlyBlend);
+      });
+      ui.end_row();
       ui.label("Debug render AABB closest points?");
       ui.horizontal(|ui| {
         ui.checkbox(&mut self.lod_render_data_settings.debug_render_octree_aabb_closest_points, "");
@@ -1,10 +1,12 @@
+use std::path::PathBuf;
+
 use egui::{Align2, Ui};
 use ultraviolet::{Isometry3, Rotor3, Vec3};
 use wgpu::CommandBuffer;
 use wgpu::util::StagingBelt;
 
 use app::{AppRunner, RenderInput};
-use common::input::RawInput;
+use common::input::{KeyboardKey, RawInput};
 use common::screen::ScreenSize;
 use gfx::camera::{CameraSettings, CameraState};
 use gfx::camera::controller::{ArcballSettings, ArcballState, CameraControllerInput, CameraControllerSettings, CameraControllerState, ControlType};
@@ -12,17 +14,22 @@ use gfx::camera::projection::CameraProjectionSettings;
 use gfx::camera::system::{CameraData, CameraSystem};
 use gfx::debug_renderer::DebugRenderer;
 use gfx::Gfx;
+use gui::Gui;
 use os::Os;
+use tracing::{error, info};
 use voxel::chunk::size::ChunkSize16;
+use voxel::lod::chunk_mesh::LodObserver;
 use voxel::lod::render::{LodRenderData, LodRenderDataManager};
 use voxel::render::VoxelRenderer;
 use voxel::uniform::{CameraUniform, ModelUniform};
 
 use crate::data::Data;
 use crate::stars::StarsRenderer;
+use crate::textures::create_material_textures;
 
 pub mod data;
 pub mod stars;
+pub mod textures;
 
 pub struct VoxelPlanets {
   data: Data,
@@ -32,14 +39,18 @@ pub struct VoxelPlanets {
 
   stars_renderer: StarsRenderer,
   voxel_renderer: VoxelRenderer,
+  material_texture_layer_count: u32,
 
   lod_octmap_transform: Isometry3,
+  sample_cache_directory: PathBuf,
+  job_graph_dot_file_path: PathBuf,
   lod_render_data_manager: Box<dyn LodRenderDataManager<ChunkSize16>>,
   lod_render_data: LodRenderData,
 }
 
 pub struct Input {
   camera: CameraControllerInput,
+  dump_job_graph: bool,
 }
 
 const EXTENDS: f32 = 4096.0 / 2.0;
@@ -47,7 +58,7 @@ const EXTENDS: f32 = 4096.0 / 2.0;
 impl app::Application for VoxelPlanets {
   type Data = Data;
   #[profiling::function]
-  fn new(_os: &Os, gfx: &Gfx, viewport: ScreenSize, mut data: Self::Data) -> Self {
+  fn new(os: &Os, gfx: &Gfx, viewport: ScreenSize, mut data: Self::Data) -> Self {
     let lod_octmap_transform = Isometry3::new(Vec3::new(-EXTENDS, -EXTENDS, -EXTENDS), Rotor3::identity());
 
     let default_camera_data = CameraData {
@@ -82,16 +93,23 @@ impl app::Application for VoxelPlanets {
     let camera_uniform = CameraUniform::from_camera(camera.camera);
 
     let stars_renderer = StarsRenderer::new(gfx, *camera.inverse_view_matrix());
-    let voxel_renderer = VoxelRenderer::new(
+    let material_textures = create_material_textures(gfx);
+    let material_texture_layer_count = material_textures.texture.size().depth_or_array_layers;
+    let voxel_renderer = VoxelRenderer::new_textured(
       gfx,
       camera_uniform,
       data.light.uniform,
       ModelUniform::identity(),
+      data.material_palette,
+      data.triplanar,
+      material_textures,
       None,
       StagingBelt::new(4096 * 1024), // 4 MiB staging belt
     );
 
-    let lod_render_data_manager = data.create_lod_render_data_manager(gfx, lod_octmap_transform, *camera.view_projection_matrix());
+    let sample_cache_directory = os.directories.cache_dir().join("chunk_samples");
+    let lod_render_data_manager = data.create_lod_render_data_manager(gfx, lod_octmap_transform, *camera.view_projection_matrix(), &sample_cache_directory);
+    let job_graph_dot_file_path = os.directories.cache_dir().join("job_graph.dot");
 
     Self {
       data,
@@ -101,8 +119,11 @@ impl app::Application for VoxelPlanets {
 
       stars_renderer,
       voxel_renderer,
+      material_texture_layer_count,
 
       lod_octmap_transform,
+      sample_cache_directory,
+      job_graph_dot_file_path,
       lod_render_data_manager,
       lod_render_data: LodRenderData::default(),
     }
@@ -121,11 +142,18 @@ impl app::Application for VoxelPlanets {
   #[profiling::function]
   fn process_input(&mut self, input: RawInput) -> Input {
     let camera_controller = CameraControllerInput::from(&input);
-    Input { camera: camera_controller }
+    let dump_job_graph = input.keys_pressed().any(|key| key.keyboard == Some(KeyboardKey::F9));
+    Input { camera: camera_controller, dump_job_graph }
   }
 
   fn add_to_debug_menu(&mut self, ui: &mut Ui) {
     self.data.camera_inspector.add_to_menu(ui);
+    self.data.job_queue_inspector.add_to_menu(ui);
+  }
+
+  fn show_debug_windows(&mut self, gui: &Gui) {
+    let metrics = self.lod_render_data_manager.get_mesh_manager_parameters().get_job_queue_metrics();
+    self.data.job_queue_inspector.show_window(gui, metrics);
   }
 
   #[profiling::function]
@@ -145,6 +173,8 @@ impl app::Application for VoxelPlanets {
         let mut recreate = false;
         recreate |= self.data.draw_reset_to_defaults_button(ui);
         self.data.draw_light_gui(ui, camera_inverse_direction);
+        self.data.draw_material_palette_gui(ui);
+        self.data.draw_triplanar_gui(ui, self.material_texture_layer_count);
         recreate |= self.data.draw_volume_gui(ui);
         recreate |= self.data.draw_extractor_gui(ui);
         recreate |= self.data.draw_lod_octmap_gui(ui);
@@ -157,10 +187,26 @@ impl app::Application for VoxelPlanets {
 
     // LOD render data
     if recreate_lod_render_data_manager {
-      self.lod_render_data_manager = self.data.create_lod_render_data_manager(gfx, self.lod_octmap_transform, *self.camera_system.camera_at(0).view_projection_matrix());
+      self.lod_render_data_manager = self.data.create_lod_render_data_manager(gfx, self.lod_octmap_transform, *self.camera_system.camera_at(0).view_projection_matrix(), &self.sample_cache_directory);
     }
     if update_lod_render_data {
-      self.lod_render_data_manager.update(self.camera_system.camera_at(0).position(), &self.data.lod_render_data_settings, &mut self.lod_render_data);
+      let lod_camera = self.camera_system.camera_at(0);
+      let vertical_fov_radians = lod_camera.settings.projection.perspective.vertical_fov_radians;
+      let viewport_height = lod_camera.viewport().height as f32;
+      self.lod_render_data_manager.get_mesh_manager_parameters_mut().set_projection(vertical_fov_radians, viewport_height);
+      let observers = [LodObserver::new(lod_camera.position())];
+      self.lod_render_data_manager.update(&observers, *lod_camera.view_projection_matrix(), &self.data.lod_render_data_settings, &mut self.lod_render_data);
+    }
+
+    // Dump the job graph as a Graphviz DOT file when F9 is pressed. The graph is received at a later update.
+    if input.dump_job_graph {
+      self.lod_render_data_manager.get_mesh_manager_parameters_mut().request_job_graph_dot();
+    }
+    if let Some(job_graph_dot) = self.lod_render_data_manager.get_mesh_manager_parameters_mut().take_job_graph_dot() {
+      match std::fs::write(&self.job_graph_dot_file_path, job_graph_dot) {
+        Ok(_) => info!("Wrote job graph to '{}'", self.job_graph_dot_file_path.display()),
+        Err(e) => error!("Failed to write job graph to '{}': {:?}", self.job_graph_dot_file_path.display(), e),
+      }
     }
 
     // Render stars
@@ -169,8 +215,11 @@ impl app::Application for VoxelPlanets {
     // Render voxels
     self.voxel_renderer.update_camera_uniform(&gfx.queue, self.camera_uniform);
     self.voxel_renderer.update_light_uniform(&gfx.queue, self.data.light.uniform);
-    let model = self.lod_render_data.model;
-    self.voxel_renderer.update_model_uniform(&gfx.queue, ModelUniform::new(model));
+    self.voxel_renderer.update_material_palette_uniform(&gfx.queue, self.data.material_palette);
+    self.voxel_renderer.update_triplanar_uniform(&gfx.queue, self.data.triplanar);
+    let model_uniform = ModelUniform::new(self.lod_render_data.model)
+      .with_geomorph(self.lod_render_data.lod_distance_per_size, self.lod_render_data.geomorph_range);
+    self.voxel_renderer.update_model_uniform(&gfx.queue, model_uniform);
     self.voxel_renderer.render_lod_mesh(gfx, &mut gfx_frame, false, &self.lod_render_data);
 
     // LOD render data debug draw (last so it draws over everything)
@@ -0,0 +1,61 @@
+use image::{DynamicImage, GrayImage, Luma};
+use rand::{Rng, SeedableRng};
+use rand::rngs::SmallRng;
+
+use gfx::Gfx;
+use gfx::texture_def::{ArrayTextureDef, ArrayTextureDefBuilder};
+use voxel::chunk::material::MaterialId;
+use voxel::volume::{ROCK, SAND, SNOW};
+
+const TEXTURE_SIZE: u32 = 256;
+
+/// Creates tileable grayscale detail textures for the built-in materials, with the texture of each material at the layer
+/// equal to its material ID. The voxel renderer tints them with the material palette.
+pub fn create_material_textures(gfx: &Gfx) -> ArrayTextureDef {
+  let textures: [(MaterialId, &[(u32, f32)], f32); 3] = [ // Ordered by material ID.
+    (ROCK, &[(8, 0.5), (16, 0.3), (64, 0.2)], 0.55),
+    (SAND, &[(64, 0.6), (128, 0.4)], 0.8),
+    (SNOW, &[(16, 0.5), (32, 0.5)], 0.9),
+  ];
+  let mut builder = ArrayTextureDefBuilder::new(TEXTURE_SIZE, TEXTURE_SIZE);
+  for (material, octaves, minimum) in textures {
+    let image = create_detail_texture(material as u64, octaves, minimum);
+    builder.add_texture(DynamicImage::ImageLuma8(image)).unwrap();
+  }
+  builder.build(
+    &gfx.device,
+    &gfx.queue,
+    "Voxel material texture array",
+    "Voxel material texture array view",
+    "Voxel material texture sampler",
+    "Voxel material texture bind group layout",
+    "Voxel material texture bind group",
+  )
+}
+
+/// Creates a tileable texture by summing value noise `octaves` of (cells per row, weight), remapped to `minimum..=1`.
+fn create_detail_texture(seed: u64, octaves: &[(u32, f32)], minimum: f32) -> GrayImage {
+  let mut rng = SmallRng::seed_from_u64(seed);
+  let lattices: Vec<(u32, f32, Vec<f32>)> = octaves.iter()
+    .map(|&(cells, weight)| (cells, weight, (0..cells * cells).map(|_| rng.gen::<f32>()).collect()))
+    .collect();
+  GrayImage::from_fn(TEXTURE_SIZE, TEXTURE_SIZE, |x, y| {
+    let value: f32 = lattices.iter()
+      .map(|(cells, weight, lattice)| weight * value_noise(x, y, *cells, lattice))
+      .sum();
+    Luma([((minimum + (1.0 - minimum) * value) * 255.0) as u8])
+  })
+}
+
+fn value_noise(x: u32, y: u32, cells: u32, lattice: &[f32]) -> f32 {
+  let cell_size = TEXTURE_SIZE as f32 / cells as f32;
+  let (fx, fy) = (x as f32 / cell_size, y as f32 / cell_size);
+  let (x0, y0) = (fx as u32 % cells, fy as u32 % cells);
+  let (x1, y1) = ((x0 + 1) % cells, (y0 + 1) % cells); // Wrap around to make the texture tileable.
+  let smoothstep = |t: f32| t * t * (3.0 - 2.0 * t);
+  let (tx, ty) = (smoothstep(fx.fract()), smoothstep(fy.fract()));
+  let sample = |x: u32, y: u32| lattice[(y * cells + x) as usize];
+  let top = sample(x0, y0) + (sample(x1, y0) - sample(x0, y0)) * tx;
+  let bottom = sample(x0, y1) + (sample(x1, y1) - sample(x0, y1)) * tx;
+  top + (bottom - top) * ty
+}